@@ -1,12 +1,13 @@
 use async_trait::async_trait;
 use axum::{
-    Form, Router,
-    extract::{Path, State},
-    response::Redirect,
-    routing::{get, post},
+    Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post, put},
 };
 use {{ db_crate_name }}::{
-    Entity,
+    {% if owned %}OwnedEntity{% else %}Entity{% endif %}, PageRequest, Validate as _,
     entities::{{ entity_plural_name }}::{{ entity_struct_name }},
     entities::{{ entity_plural_name}}::{{ entity_struct_name }}Changeset,
 };
@@ -14,100 +15,189 @@ use shipwright_ui::view_engine::{View, ViewEngine};
 
 use crate::{
     error::Error,
-    middlewares::flash::{Flash, IncomingFlashes},
+    form::FormState,
+    htmx::HxRequest,
+    middlewares::{
+        auth::{% if owned %}{AuthSession, Permissions}{% else %}Permissions{% endif %},
+        flash::{Flash, IncomingFlashes},
+    },
+    negotiation::{Format, FormOrJson},
+    permission_required,
     state::AppState,
     views::{{ entity_plural_name }}::{{ entity_struct_name }}View,
 };
 
-use super::Controller;
+use super::{% if owned %}OwnedController{% else %}Controller{% endif %};
 
 pub struct {{ entity_struct_name }}Controller;
 
 #[async_trait]
-impl Controller for {{ entity_struct_name }}Controller {
+impl {% if owned %}OwnedController{% else %}Controller{% endif %} for {{ entity_struct_name }}Controller {
     type Id = String;
 
     type View = {{ entity_struct_name }}View;
 
+    type Record = {{ entity_struct_name }};
+
     type EntityChangeset = {{ entity_struct_name }}Changeset;
 
     type Error = Error;
 
+    const PATH: &'static str = "/{{ entity_plural_name }}";
+
     fn router() -> Router<AppState> {
+        let item = format!("{% raw %}{}/{{id}}{% endraw %}", Self::PATH);
+
         Router::new()
-            .route("/{{ entity_plural_name }}", get(Self::read_all).post(Self::create))
-            .route("/{{ entity_plural_name }}/batch", post(Self::create_batch))
-            .route(
-                "/{{ entity_plural_name }}/{id}",
-                get(Self::read_one).put(Self::update).delete(Self::delete),
-            )
+            .route(Self::PATH, post(Self::create))
+            .route(&format!("{}/batch", Self::PATH), post(Self::create_batch))
+            .route(&item, put(Self::update).delete(Self::delete))
+            .route_layer(permission_required!("{{ entity_plural_name }}.write"))
+            .route(Self::PATH, get(Self::read_all))
+            .route(&item, get(Self::read_one))
     }
 
     async fn read_all(
+        format: Format,
         v: ViewEngine<View>,
         flashes: IncomingFlashes,
+        permissions: Permissions,
+        {%- if owned %}
+        auth_session: AuthSession,
+        {%- endif %}
         State(app_state): State<AppState>,
+        Query(page): Query<PageRequest>,
     ) -> Result<(IncomingFlashes, Self::View), Self::Error> {
-        let {{ entity_plural_name }} = {{ entity_struct_name }}::load_all(&app_state.db_pool).await?;
+        {%- if owned %}
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        {%- endif %}
+        let {{ entity_plural_name }} = {{ entity_struct_name }}::{% if owned %}load_page_for_owner(user.id, &page, &app_state.db_pool){% else %}load_page(&page, &app_state.db_pool){% endif %}.await?;
 
-        Ok((flashes.clone(), {{ entity_struct_name }}View::Index(v, {{ entity_plural_name }}, flashes)))
+        Ok((flashes.clone(), {{ entity_struct_name }}View::Index(format, v, {{ entity_plural_name }}, flashes, permissions)))
     }
 
     async fn create(
+        format: Format,
+        hx: HxRequest,
+        v: ViewEngine<View>,
         flash: Flash,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
+        {%- if owned %}
+        auth_session: AuthSession,
+        {%- endif %}
         State(app_state): State<AppState>,
-        Form(record): Form<Self::EntityChangeset>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let {{ entity_singular_name }} = {{ entity_struct_name }}::create(record, &app_state.db_pool).await?;
-
-        Ok((
-            flash.success(&format!("✅ created new {{ entity_singular_name }}")),
-            Redirect::to(&format!("/{{ entity_plural_name }}/{}", {{ entity_singular_name }}.id)),
-        ))
+        FormOrJson(record): FormOrJson<Self::EntityChangeset>,
+    ) -> Result<Response, Self::Error> {
+        {%- if owned %}
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        {%- endif %}
+        if !format.is_json() && let Err(errors) = record.validate() {
+            let form = FormState::invalid(&errors){% for field in changeset_struct_fields %}.value("{{ field.name }}", &record.{{ field.name }}){% endfor %};
+            let {{ entity_plural_name }} = {{ entity_struct_name }}::{% if owned %}load_page_for_owner(user.id, &PageRequest::default(), &app_state.db_pool){% else %}load_page(&PageRequest::default(), &app_state.db_pool){% endif %}.await?;
+            return Ok((flashes.clone(), {{ entity_struct_name }}View::CreateForm(hx, v, {{ entity_plural_name }}, form, flashes, permissions)).into_response());
+        }
+        let {{ entity_singular_name }} = {{ entity_struct_name }}::{% if owned %}create_for_owner(user.id, record, &app_state.db_pool){% else %}create(record, &app_state.db_pool){% endif %}.await?;
+
+        Ok(format.respond(StatusCode::CREATED, {{ entity_singular_name }}, |{{ entity_singular_name }}| {
+            (
+                flash.success("✅ created new {{ entity_singular_name }}"),
+                Redirect::to(&format!("/{{ entity_plural_name }}/{}", {{ entity_singular_name }}.id)),
+            )
+        }))
     }
 
     async fn create_batch(
+        format: Format,
         flash: Flash,
+        {%- if owned %}
+        auth_session: AuthSession,
+        {%- endif %}
         State(app_state): State<AppState>,
-        Form(records): Form<Vec<Self::EntityChangeset>>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let _records = {{ entity_struct_name }}::create_batch(records, &app_state.db_pool).await?;
-
-        Ok((flash.success(&format!("✅ created {{ entity_plural_name }}")), Redirect::to("/{{ entity_plural_name }}")))
+        FormOrJson(records): FormOrJson<Vec<Self::EntityChangeset>>,
+    ) -> Result<Response, Self::Error> {
+        {%- if owned %}
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        {%- endif %}
+        let {{ entity_plural_name }} = {{ entity_struct_name }}::{% if owned %}create_batch_for_owner(user.id, records, &app_state.db_pool){% else %}create_batch(records, &app_state.db_pool){% endif %}.await?;
+
+        Ok(format.respond(StatusCode::CREATED, {{ entity_plural_name }}, |_| {
+            (flash.success("✅ created {{ entity_plural_name }}"), Redirect::to("/{{ entity_plural_name }}"))
+        }))
     }
 
     async fn read_one(
+        format: Format,
         v: ViewEngine<View>,
         flashes: IncomingFlashes,
+        permissions: Permissions,
+        {%- if owned %}
+        auth_session: AuthSession,
+        {%- endif %}
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
     ) -> Result<(IncomingFlashes, Self::View), Self::Error> {
-        let {{ entity_singular_name }} = {{ entity_struct_name }}::load(id, &app_state.db_pool).await?;
+        {%- if owned %}
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        {%- endif %}
+        let {{ entity_singular_name }} = {{ entity_struct_name }}::{% if owned %}load_for_owner(id, user.id, &app_state.db_pool){% else %}load(id, &app_state.db_pool){% endif %}.await?;
 
-        Ok((flashes.clone(), {{ entity_struct_name }}View::Show(v, {{ entity_singular_name }}, flashes)))
+        Ok((flashes.clone(), {{ entity_struct_name }}View::Show(format, v, {{ entity_singular_name }}, flashes, permissions)))
     }
 
     async fn update(
+        format: Format,
+        hx: HxRequest,
+        v: ViewEngine<View>,
         flash: Flash,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
+        {%- if owned %}
+        auth_session: AuthSession,
+        {%- endif %}
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
-        Form(form): Form<Self::EntityChangeset>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let {{ entity_singular_name }} = {{ entity_struct_name }}::update(id, form, &app_state.db_pool).await?;
-
-        Ok((
-            flash.success(&format!("✅ updated {{ entity_singular_name }}")),
-            Redirect::to(&format!("/{{ entity_plural_name }}/{}", {{ entity_singular_name }}.id)),
-        ))
+        FormOrJson(record): FormOrJson<Self::EntityChangeset>,
+    ) -> Result<Response, Self::Error> {
+        {%- if owned %}
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        {%- endif %}
+        if !format.is_json() && let Err(errors) = record.validate() {
+            let form = FormState::invalid(&errors){% for field in changeset_struct_fields %}.value("{{ field.name }}", &record.{{ field.name }}){% endfor %};
+            let {{ entity_singular_name }} = {{ entity_struct_name }}::{% if owned %}load_for_owner(id, user.id, &app_state.db_pool){% else %}load(id, &app_state.db_pool){% endif %}.await?;
+            return Ok((flashes.clone(), {{ entity_struct_name }}View::UpdateForm(hx, v, {{ entity_singular_name }}, form, flashes, permissions)).into_response());
+        }
+        let {{ entity_singular_name }} = {{ entity_struct_name }}::{% if owned %}update_for_owner(id, user.id, record, &app_state.db_pool){% else %}update(id, record, &app_state.db_pool){% endif %}.await?;
+
+        // The form swaps itself, the flash goes out as an event
+        if hx.is_partial() && !format.is_json() {
+            return Ok((flash.success("✅ updated {{ entity_singular_name }}"), {{ entity_struct_name }}View::Update(v, {{ entity_singular_name }})).into_response());
+        }
+
+        Ok(format.respond(StatusCode::OK, {{ entity_singular_name }}, |{{ entity_singular_name }}| {
+            (
+                flash.success("✅ updated {{ entity_singular_name }}"),
+                Redirect::to(&format!("/{{ entity_plural_name }}/{}", {{ entity_singular_name }}.id)),
+            )
+        }))
     }
 
     async fn delete(
+        format: Format,
         flash: Flash,
+        {%- if owned %}
+        auth_session: AuthSession,
+        {%- endif %}
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let _{{ entity_singular_name }} = {{ entity_struct_name }}::delete(id, &app_state.db_pool).await?;
-
-        Ok((flash.info(&format!("deleted {{ entity_singular_name }}")), Redirect::to("/{{ entity_plural_name }}")))
+    ) -> Result<Response, Self::Error> {
+        {%- if owned %}
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        {%- endif %}
+        let {{ entity_singular_name }} = {{ entity_struct_name }}::{% if owned %}delete_for_owner(id, user.id, &app_state.db_pool){% else %}delete(id, &app_state.db_pool){% endif %}.await?;
+
+        Ok(format.respond(StatusCode::OK, {{ entity_singular_name }}, |_| {
+            (flash.info("deleted {{ entity_singular_name }}"), Redirect::to("/{{ entity_plural_name }}"))
+        }))
     }
 }
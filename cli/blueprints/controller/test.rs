@@ -1,5 +1,6 @@
-use crate::{authenticated_request, test_request_with_db};
+use crate::{authenticated_request, mock_logged_in_admin, test_request_with_db};
 use {{ db_crate_name }}::{DbPool, MIGRATOR, entities::{{ entity_plural_name}}::{{ entity_struct_name }}Changeset};
+use axum::http::{HeaderValue, StatusCode, header::ACCEPT};
 use fake::{Fake, Faker};
 
 #[sqlx::test(migrator = "MIGRATOR")]
@@ -16,7 +17,10 @@ async fn index_page_works_for_authenticated_users(pool: DbPool) {
 async fn create_{{entity_singular_name}}_redirects_and_displays_in_ui(pool: DbPool) {
     let {{ entity_singular_name}}: {{ entity_struct_name }}Changeset = Faker.fake();
 
-    test_request_with_db::<_, _>(pool, |request| async move {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_admin(&request, &pool).await;
+
         let response = request.post("/{{ entity_plural_name }}").form(&{{ entity_singular_name }}).await;
 
         response.assert_status_see_other();
@@ -30,9 +34,28 @@ async fn create_{{entity_singular_name}}_redirects_and_displays_in_ui(pool: DbPo
             .unwrap();
 
         let response = request.get(location).await;
-        {% for field in changeset_struct_fields %}
+        {% for field in changeset_struct_fields %}{% if field.ty == "String" %}
         response.assert_text_contains({{ entity_singular_name}}.{{ field.name }});
-        {%- endfor %}
+        {%- endif %}{% endfor %}
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn create_{{entity_singular_name}}_answers_json_clients_with_the_record(pool: DbPool) {
+    let {{ entity_singular_name}}: {{ entity_struct_name }}Changeset = Faker.fake();
+
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_admin(&request, &pool).await;
+
+        let response = request
+            .post("/{{ entity_plural_name }}")
+            .add_header(ACCEPT, HeaderValue::from_static("application/json"))
+            .json(&{{ entity_singular_name }})
+            .await;
+
+        response.assert_status(StatusCode::CREATED);
     })
     .await
 }
@@ -1 +1,10 @@
-{{generated_sql}}
+{{generated_sql}} ;
+{%- if permission %}
+
+-- Only admins may write until other roles are granted the permission
+INSERT INTO permissions (name) VALUES ('{{ permission }}') ;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT roles.id, permissions.id FROM roles, permissions
+WHERE roles.name = 'admin' AND permissions.name = '{{ permission }}' ;
+{%- endif %}
@@ -1,36 +1,68 @@
 use axum::response::{IntoResponse, Response};
-use {{ db_crate_name }}::entities::{{ entity_plural_name }}::{{ entity_struct_name }};
+use {{ db_crate_name }}::{Page, entities::{{ entity_plural_name }}::{{ entity_struct_name }}};
 use shipwright_ui::view_engine::{View, ViewEngine};
 use serde_json::json;
 
-use crate::{format, middlewares::flash::IncomingFlashes};
+use crate::{
+    form::FormState,
+    format,
+    htmx::HxRequest,
+    middlewares::{auth::Permissions, flash::IncomingFlashes},
+    negotiation::Format,
+};
 
+/// Renders the {{ entity_singular_name }} templates, or serializes the {{ entity_plural_name }} for JSON clients.
 pub enum {{ entity_struct_name }}View {
-    Index(ViewEngine<View>, Vec<{{ entity_struct_name }}>, IncomingFlashes),
-    Show(ViewEngine<View>, {{ entity_struct_name }}, IncomingFlashes),
+    Index(Format, ViewEngine<View>, Page<{{ entity_struct_name }}>, IncomingFlashes, Permissions),
+    Show(Format, ViewEngine<View>, {{ entity_struct_name }}, IncomingFlashes, Permissions),
+    /// The update form of the show page, for htmx requests swapping it
+    Update(ViewEngine<View>, {{ entity_struct_name }}),
+    /// The index page with an invalid new {{ entity_singular_name }}, htmx requests only get the form
+    CreateForm(HxRequest, ViewEngine<View>, Page<{{ entity_struct_name }}>, FormState, IncomingFlashes, Permissions),
+    /// The show page with an invalid update of the {{ entity_singular_name }}, htmx requests only get the form
+    UpdateForm(HxRequest, ViewEngine<View>, {{ entity_struct_name }}, FormState, IncomingFlashes, Permissions),
 }
 
 impl IntoResponse for {{ entity_struct_name }}View {
     fn into_response(self) -> Response {
         match self {
-            {{ entity_struct_name }}View::Index(ViewEngine(v), {{ entity_plural_name }}, IncomingFlashes { flashes, .. }) => {
+            {{ entity_struct_name }}View::Index(format, ViewEngine(v), page, IncomingFlashes { flashes, .. }, permissions) => {
                 format::render()
-                    .view(
+                    .negotiate(
+                        format,
                         &v,
                         "{{ entity_plural_name }}/index.html",
-                        json!({ "{{ entity_plural_name }}": {{ entity_plural_name }}, "flashes": flashes }),
+                        json!({ "{{ entity_plural_name }}": page.records, "page": page.meta, "flashes": flashes, "permissions": permissions }),
+                        &page,
                     )
                     .into_response()
             }
-            {{ entity_struct_name }}View::Show(ViewEngine(v), {{ entity_singular_name }}, IncomingFlashes { flashes, .. }) => {
+            {{ entity_struct_name }}View::Show(format, ViewEngine(v), {{ entity_singular_name }}, IncomingFlashes { flashes, .. }, permissions) => {
                 format::render()
-                    .view(
+                    .negotiate(
+                        format,
                         &v,
                         "{{ entity_plural_name }}/show.html",
-                        json!({ "{{ entity_singular_name }}": {{ entity_singular_name }}, "flashes": flashes }),
+                        json!({ "{{ entity_singular_name }}": {{ entity_singular_name }}, "flashes": flashes, "permissions": permissions }),
+                        &{{ entity_singular_name }},
                     )
                     .into_response()
             }
+            {{ entity_struct_name }}View::Update(ViewEngine(v), {{ entity_singular_name }}) => {
+                format::render()
+                    .view_block(&v, "{{ entity_plural_name }}/show.html", "update", json!({ "{{ entity_singular_name }}": {{ entity_singular_name }} }))
+                    .into_response()
+            }
+            {{ entity_struct_name }}View::CreateForm(hx, ViewEngine(v), page, form, IncomingFlashes { flashes, .. }, permissions) => {
+                format::render()
+                    .invalid_form(&hx, &v, "{{ entity_plural_name }}/index.html", "form", json!({ "{{ entity_plural_name }}": page.records, "page": page.meta, "form": form, "flashes": flashes, "permissions": permissions }))
+                    .into_response()
+            }
+            {{ entity_struct_name }}View::UpdateForm(hx, ViewEngine(v), {{ entity_singular_name }}, form, IncomingFlashes { flashes, .. }, permissions) => {
+                format::render()
+                    .invalid_form(&hx, &v, "{{ entity_plural_name }}/show.html", "update", json!({ "{{ entity_singular_name }}": {{ entity_singular_name }}, "form": form, "flashes": flashes, "permissions": permissions }))
+                    .into_response()
+            }
         }
     }
 }
@@ -6,6 +6,13 @@ use color_eyre::{
 use guppy::{Version, VersionReq};
 use shipwright_cli::{Error, util::ui::UI};
 use shipwright_config::{Config, DatabaseConfig, Environment, load_config, parse_env};
+use shipwright_context::Account;
+use shipwright_db::{
+    Database, DbPool, connect_pool,
+    entities::{
+        account_deletion::AccountDeletion, session::Session, user::User, user_data::UserData,
+    },
+};
 use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
 use sqlx::{
     ConnectOptions, Connection, Executor,
@@ -15,6 +22,7 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::{ExitCode, Stdio};
+use time::OffsetDateTime;
 use tokio::io::{AsyncBufReadExt, stdin};
 use url::Url;
 
@@ -66,6 +74,25 @@ enum Commands {
     Seed,
     #[command(about = "Generate query metadata to support offline compile-time verification")]
     Prepare,
+    #[command(about = "Sign a user out of all their sessions")]
+    RevokeSessions {
+        #[arg(help = "The email of the user.")]
+        email: String,
+    },
+    #[command(about = "Export everything stored about a user as JSON")]
+    ExportUser {
+        #[arg(help = "The email of the user.")]
+        email: String,
+        #[arg(short, long, help = "The file to write the export to.")]
+        output: PathBuf,
+    },
+    #[command(about = "Delete a user with everything linked to them")]
+    DeleteUser {
+        #[arg(help = "The email of the user.")]
+        email: String,
+        #[arg(long, help = "Delete right away instead of after the grace period.")]
+        now: bool,
+    },
 }
 
 #[allow(missing_docs)]
@@ -120,6 +147,36 @@ async fn cli(ui: &mut UI<'_>, cli: Cli) -> Result<(), Error> {
                     ui.success(&format!("Reset database {} successfully.", db_name));
                     Ok(())
                 }
+                Commands::RevokeSessions { email } => {
+                    ui.info(&format!("Revoking sessions of {}…", &email));
+                    let revoked = revoke_sessions(&config, &email)
+                        .await
+                        .context("Could not revoke sessions!")?;
+                    ui.success(&format!("Revoked {} sessions.", revoked));
+                    Ok(())
+                }
+                Commands::ExportUser { email, output } => {
+                    ui.info(&format!("Exporting the data of {}…", &email));
+                    export_user(&config, &email, &output)
+                        .await
+                        .context("Could not export the user!")?;
+                    ui.success(&format!("Exported to {}.", output.display()));
+                    Ok(())
+                }
+                Commands::DeleteUser { email, now } => {
+                    ui.info(&format!("Deleting {}…", &email));
+                    let deletion = delete_user(&config, &email, now)
+                        .await
+                        .context("Could not delete the user!")?;
+                    match deletion {
+                        Some(deletion) => ui.success(&format!(
+                            "The account will be deleted after {}.",
+                            deletion.delete_after.date()
+                        )),
+                        None => ui.success("Deleted the account."),
+                    }
+                    Ok(())
+                }
                 Commands::Prepare => {
                     ensure_sqlx_cli_installed(ui).await?;
 
@@ -243,6 +300,73 @@ async fn seed(config: &DatabaseConfig) -> Result<(), Error> {
     Ok(())
 }
 
+async fn revoke_sessions(config: &Config, email: &str) -> Result<u64, Error> {
+    let db_pool = connect_pool(Database::Primary, config)
+        .await
+        .context("Failed to connect to database!")?;
+    let user = find_user(&db_pool, email).await?;
+
+    let revoked = Session::delete_all_for_user(user.id, None, &db_pool)
+        .await
+        .context("Failed to delete sessions!")?;
+
+    Ok(revoked)
+}
+
+async fn find_user(db_pool: &DbPool, email: &str) -> Result<User, Error> {
+    let user = User::try_get_by_email(email, db_pool)
+        .await
+        .context("Failed to look up user!")?
+        .wrap_err(format!("There is no user with email {email}!"))?;
+
+    Ok(user)
+}
+
+async fn export_user(config: &Config, email: &str, output: &Path) -> Result<(), Error> {
+    let db_pool = connect_pool(Database::Primary, config)
+        .await
+        .context("Failed to connect to database!")?;
+    let user = find_user(&db_pool, email).await?;
+
+    let data = UserData::load(user.id, &db_pool)
+        .await
+        .context("Failed to load the data of the user!")?;
+    let json = serde_json::to_string_pretty(&data).context("Failed to serialize the data!")?;
+    fs::write(output, json).context("Failed to write the export!")?;
+
+    Ok(())
+}
+
+/// Deletes the user right away with `now`, otherwise the deletion is scheduled like it is when
+/// users delete their own account and returned.
+async fn delete_user(
+    config: &Config,
+    email: &str,
+    now: bool,
+) -> Result<Option<AccountDeletion>, Error> {
+    let db_pool = connect_pool(Database::Primary, config)
+        .await
+        .context("Failed to connect to database!")?;
+    let user = find_user(&db_pool, email).await?;
+
+    if now {
+        UserData::delete(user.id, &db_pool)
+            .await
+            .context("Failed to delete the user!")?;
+        return Ok(None);
+    }
+
+    let delete_after = Account::deletion_date(
+        config.account.deletion_grace_days,
+        OffsetDateTime::now_utc(),
+    );
+    let deletion = AccountDeletion::schedule(user.id, delete_after, &db_pool)
+        .await
+        .context("Failed to schedule the deletion!")?;
+
+    Ok(Some(deletion))
+}
+
 async fn reset(ui: &mut UI<'_>, config: &DatabaseConfig) -> Result<String, Error> {
     ui.log("Dropping database…");
     drop(config).await?;
@@ -13,10 +13,11 @@ use shipwright_cli::{
         ui::UI,
     },
 };
+use shipwright_web::middlewares::cookie_keys::CookieKeys;
 use std::fs::{self, File, OpenOptions};
 use std::io::prelude::*;
 use std::process::ExitCode;
-use std::time::SystemTime;
+use time::OffsetDateTime;
 
 static BLUEPRINTS_DIR: include_dir::Dir =
     include_dir::include_dir!("$CARGO_MANIFEST_DIR/blueprints");
@@ -92,6 +93,8 @@ enum Commands {
         #[arg(help = "The name of the view.")]
         name: String,
     },
+    #[command(about = "Generate a key to sign the session and flash cookies")]
+    Key,
     #[command(
         about = "Generate a complete scaffold (migration, entity, controller, test, and view)"
     )]
@@ -100,6 +103,11 @@ enum Commands {
         name: String,
         #[arg(help = "Column definitions like: 'id:uuid^', 'name:string256!', 'avatar:references=avatars(id)'", num_args = 0..)]
         fields: Vec<String>,
+        #[arg(
+            long,
+            help = "Scope the records to the user who created them, adds a user_id column."
+        )]
+        owned: bool,
     },
 }
 
@@ -115,14 +123,15 @@ async fn cli(ui: &mut UI<'_>, cli: Cli) -> Result<(), Error> {
             Ok(())
         }
         Commands::Controller { name, fields } => {
+            let parsed_fields = parse_cli_fields(fields)?;
             ui.info("Generating controller…");
-            let file_name = generate_controller(name.clone())
+            let file_name = generate_controller(name.clone(), parsed_fields.clone(), false)
                 .await
                 .wrap_err("Could not generate controller!")?;
             ui.success(&format!("Generated controller {}.", &file_name));
             ui.info("Do not forget to route the controller's actions in ./web/src/routes.rs!");
             ui.info("Generating test for controller…");
-            let file_name = generate_controller_test(name, parse_cli_fields(fields)?)
+            let file_name = generate_controller_test(name, parsed_fields)
                 .await
                 .wrap_err("Could not generate test for controller!")?;
             ui.success(&format!("Generated test for controller {}.", &file_name));
@@ -140,15 +149,16 @@ async fn cli(ui: &mut UI<'_>, cli: Cli) -> Result<(), Error> {
             ui.info("Generating migration…");
             let table_name = to_plural(&table);
             let migration_name = format!("create_{}_table", table_name);
-            let file_name = generate_migration(migration_name, table, parse_cli_fields(fields)?)
-                .await
-                .wrap_err("Could not generate migration!")?;
+            let file_name =
+                generate_migration(migration_name, table, parse_cli_fields(fields)?, None)
+                    .await
+                    .wrap_err("Could not generate migration!")?;
             ui.success(&format!("Generated migration {}.", &file_name));
             Ok(())
         }
         Commands::Entity { name, fields } => {
             ui.info("Generating entity…");
-            let struct_name = generate_entity(name, parse_cli_fields(fields)?)
+            let struct_name = generate_entity(name, parse_cli_fields(fields)?, false)
                 .await
                 .wrap_err("Could not generate entity!")?;
             ui.success(&format!("Generated entity {}.", &struct_name));
@@ -162,7 +172,18 @@ async fn cli(ui: &mut UI<'_>, cli: Cli) -> Result<(), Error> {
             ui.success(&format!("Generated view {}.", &file_name));
             Ok(())
         }
-        Commands::Scaffold { name, fields } => {
+        Commands::Key => {
+            ui.success(
+                "Generated a cookie signing key, put it first in cookie.keys to rotate to it:",
+            );
+            ui.log(&CookieKeys::generate());
+            Ok(())
+        }
+        Commands::Scaffold {
+            name,
+            fields,
+            owned,
+        } => {
             let parsed_fields = parse_cli_fields(fields)?;
             let name_snake = to_snake_case(&name).to_lowercase();
             let name_plural = to_plural(&name_snake);
@@ -170,22 +191,34 @@ async fn cli(ui: &mut UI<'_>, cli: Cli) -> Result<(), Error> {
             // Generate migration
             ui.info("Generating migration…");
             let migration_name = format!("create_{}_table", name_plural);
-            let file_name =
-                generate_migration(migration_name, name_snake.clone(), parsed_fields.clone())
-                    .await
-                    .wrap_err("Could not generate migration!")?;
+            let mut migration_fields = parsed_fields.clone();
+            if owned {
+                migration_fields.push(Field::ForeignKey {
+                    local_key: "user_id".to_string(),
+                    references_table: "users".to_string(),
+                    references_column: "id".to_string(),
+                });
+            }
+            let file_name = generate_migration(
+                migration_name,
+                name_snake.clone(),
+                migration_fields,
+                Some(format!("{}.write", name_plural)),
+            )
+            .await
+            .wrap_err("Could not generate migration!")?;
             ui.success(&format!("Generated migration {}.", &file_name));
 
             // Generate entity
             ui.info("Generating entity…");
-            let struct_name = generate_entity(name.clone(), parsed_fields.clone())
+            let struct_name = generate_entity(name.clone(), parsed_fields.clone(), owned)
                 .await
                 .wrap_err("Could not generate entity!")?;
             ui.success(&format!("Generated entity {}.", &struct_name));
 
             // Generate controller
             ui.info("Generating controller…");
-            let file_name = generate_controller(name.clone())
+            let file_name = generate_controller(name.clone(), parsed_fields.clone(), owned)
                 .await
                 .wrap_err("Could not generate controller!")?;
             ui.success(&format!("Generated controller {}.", &file_name));
@@ -232,7 +265,14 @@ async fn generate_middleware(name: String) -> Result<String, Error> {
     Ok(file_path)
 }
 
-async fn generate_controller(name: String) -> Result<String, Error> {
+/// An `owned` controller implements `OwnedController` and only touches the records of the current user.
+///
+/// Invalid forms are shown again with the submitted values of the changeset `fields`.
+async fn generate_controller(
+    name: String,
+    fields: Vec<Field>,
+    owned: bool,
+) -> Result<String, Error> {
     let name = to_snake_case(&name).to_lowercase();
     let name_plural = to_plural(&name);
     let name_singular = to_singular(&name);
@@ -240,12 +280,16 @@ async fn generate_controller(name: String) -> Result<String, Error> {
     let db_crate_name = get_member_package_name("db")?;
     let db_crate_name = to_snake_case(&db_crate_name);
 
+    let (_, changeset_struct_fields) = generate_struct_fields(&fields);
+
     let template = get_liquid_template("controller/file.rs")?;
     let variables = liquid::object!({
         "entity_struct_name": struct_name,
         "entity_singular_name": name_singular,
         "entity_plural_name": name_plural,
         "db_crate_name": db_crate_name,
+        "changeset_struct_fields": changeset_struct_fields,
+        "owned": owned,
     });
     let output = template
         .render(&variables)
@@ -297,10 +341,12 @@ async fn generate_controller_test(name: String, fields: Vec<Field>) -> Result<St
     Ok(file_path)
 }
 
+/// Scaffolds pass the `permission` guarding their write routes, it is seeded and granted to admins.
 async fn generate_migration(
     name: String,
     table: String,
     fields: Vec<Field>,
+    permission: Option<String>,
 ) -> Result<String, Error> {
     let table_name = to_plural(&table);
     let generated_sql = generate_sql(&table_name, fields).await?;
@@ -309,20 +355,32 @@ async fn generate_migration(
 
     let variables = liquid::object!({
         "generated_sql": generated_sql,
+        "permission": permission,
     });
     let output = template
         .render(&variables)
         .wrap_err("Failed to render Liquid template")?;
 
-    let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
-    let file_name = format!("{}__{}.sql", timestamp.as_secs(), name);
+    // Same version format as the other migrations so the new one sorts after them
+    let now = OffsetDateTime::now_utc();
+    let file_name = format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}_{}.sql",
+        now.year(),
+        u8::from(now.month()),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second(),
+        name
+    );
     let path = format!("./db/migrations/{}", file_name);
     create_project_file(&path, output.as_bytes())?;
 
     Ok(path)
 }
 
-async fn generate_entity(name: String, fields: Vec<Field>) -> Result<String, Error> {
+/// An `owned` entity gets a `user_id` field and implements `OwnedEntity`.
+async fn generate_entity(name: String, fields: Vec<Field>, owned: bool) -> Result<String, Error> {
     let name = to_singular(&name).to_lowercase();
     let name_plural = to_plural(&name);
     let struct_name = to_class_case(&name);
@@ -337,6 +395,7 @@ async fn generate_entity(name: String, fields: Vec<Field>) -> Result<String, Err
         "entity_plural_name": name_plural,
         "entity_struct_fields": entity_struct_fields,
         "changeset_struct_fields": changeset_struct_fields,
+        "owned": owned,
     });
 
     let output = template
@@ -0,0 +1,58 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use color_eyre::{Result, eyre::Context};
+use shipwright_cli::util::ui::UI;
+use shipwright_config::{Environment, load_config, parse_env};
+use shipwright_web::openapi;
+
+fn main() -> ExitCode {
+    let mut stdout = std::io::stdout();
+    let mut stderr = std::io::stderr();
+
+    let args = Cli::parse();
+    let mut ui = UI::new(&mut stdout, &mut stderr, !args.no_color, !args.quiet);
+
+    match cli(&mut ui, args) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            ui.error(e.to_string().as_str(), &e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[derive(Parser)]
+#[command(author, version, about = "A CLI tool to write the OpenAPI document of the app.", long_about = None)]
+struct Cli {
+    #[arg(
+        short,
+        long,
+        help = "The file to write the document to.",
+        default_value = "openapi.json"
+    )]
+    output: PathBuf,
+
+    #[arg(short, long, help = "Choose the environment (development, test, production).", value_parser = parse_env, default_value = "development")]
+    env: Environment,
+
+    #[arg(long, help = "Disable colored output.")]
+    no_color: bool,
+
+    #[arg(long, help = "Disable debug output.")]
+    quiet: bool,
+}
+
+fn cli(ui: &mut UI<'_>, cli: Cli) -> Result<()> {
+    let config = load_config(&cli.env).context("Could not load the configuration!")?;
+
+    ui.info("Writing the OpenAPI document…");
+    let document = serde_json::to_string_pretty(&openapi::document(&config))
+        .context("Could not serialize the document!")?;
+    fs::write(&cli.output, document + "\n").context("Could not write the document!")?;
+    ui.success(&format!("Wrote {}.", cli.output.display()));
+
+    Ok(())
+}
@@ -309,6 +309,18 @@ impl FieldType {
             _ => None,
         }
     }
+
+    /// The type to describe the field with in the OpenAPI document, for types without a
+    /// `JsonSchema` implementation.
+    pub fn as_schema_with(&self) -> Option<String> {
+        match self {
+            // How `time` serializes an `OffsetDateTime` without the `serde-human-readable` feature
+            FieldType::Date | FieldType::DateTime => {
+                Some("Option<(i32, u16, u8, u8, u8, u32, i8, i8, i8)>".to_string())
+            }
+            _ => None,
+        }
+    }
 }
 
 // rest of your code remains unchanged
@@ -394,6 +406,9 @@ pub async fn generate_sql(table_name: &str, fields: Vec<Field>) -> Result<String
 pub struct StructField {
     pub name: String,
     pub ty: String,
+    /// Set by the database and never written from the changeset.
+    pub read_only: bool,
+    pub schema_with: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -402,6 +417,7 @@ pub struct ChangesetField {
     pub ty: String,
     pub validation: Option<String>,
     pub faker: Option<String>,
+    pub schema_with: Option<String>,
 }
 
 pub fn generate_struct_fields(fields: &[Field]) -> (Vec<StructField>, Vec<ChangesetField>) {
@@ -412,20 +428,24 @@ pub fn generate_struct_fields(fields: &[Field]) -> (Vec<StructField>, Vec<Change
         match field {
             Field::Column(name, field_type) => {
                 let ty = field_type.as_sqlx_type();
+                let read_only = name == "created_at" || name == "updated_at";
 
                 // Always include in the main struct
                 struct_fields.push(StructField {
                     name: name.clone(),
                     ty: ty.clone(),
+                    read_only,
+                    schema_with: field_type.as_schema_with(),
                 });
 
                 // Skip `id`, `created_at`, `updated_at` in changeset
-                if name != "id" && name != "created_at" && name != "updated_at" {
+                if name != "id" && !read_only {
                     changeset_fields.push(ChangesetField {
                         name: name.clone(),
                         ty,
                         validation: field_type.as_validation(),
                         faker: field_type.as_faker(),
+                        schema_with: field_type.as_schema_with(),
                     });
                 }
             }
@@ -435,6 +455,8 @@ pub fn generate_struct_fields(fields: &[Field]) -> (Vec<StructField>, Vec<Change
                 struct_fields.push(StructField {
                     name: local_key.clone(),
                     ty: "Uuid".to_string(),
+                    read_only: false,
+                    schema_with: None,
                 });
 
                 changeset_fields.push(ChangesetField {
@@ -442,6 +464,7 @@ pub fn generate_struct_fields(fields: &[Field]) -> (Vec<StructField>, Vec<Change
                     ty: "Uuid".to_string(),
                     validation: None,
                     faker: Some("Uuid()".to_string()),
+                    schema_with: None,
                 });
             }
         }
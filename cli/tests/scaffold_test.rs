@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use sqlx::{Connection, SqliteConnection, migrate::Migrator};
+
+/// Copies the workspace to `to`, without its build output and history.
+fn copy_workspace(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let name = entry.file_name();
+        if name == "target" || name == ".git" {
+            continue;
+        }
+        if entry.file_type().unwrap().is_dir() {
+            copy_workspace(&entry.path(), &to.join(&name));
+        } else {
+            fs::copy(entry.path(), to.join(&name)).unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn scaffolded_owned_resources_without_a_description_build() {
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let tmp = Path::new(env!("CARGO_TARGET_TMPDIR")).join("scaffold");
+    let app = tmp.join("app");
+    let _ = fs::remove_dir_all(&app);
+    copy_workspace(workspace, &app);
+
+    let generate = Command::new(env!("CARGO_BIN_EXE_generate"))
+        .args([
+            "scaffold",
+            "book",
+            "id:uuid!^",
+            "title:string256!",
+            "pages:int",
+            "--owned",
+        ])
+        .current_dir(&app)
+        .output()
+        .unwrap();
+    assert!(
+        generate.status.success(),
+        "{}",
+        String::from_utf8_lossy(&generate.stderr)
+    );
+
+    // Invalid forms are refilled with the fields of the changeset
+    let controller = fs::read_to_string(app.join("web/src/controllers/book.rs")).unwrap();
+    assert!(controller.contains(r#".value("title", &record.title).value("pages", &record.pages)"#));
+    assert!(!controller.contains("description"));
+
+    // The queries of the entity are checked against a database with the new table
+    let url = format!("sqlite://{}?mode=rwc", app.join("scaffold.db").display());
+    let mut connection = SqliteConnection::connect(&url).await.unwrap();
+    Migrator::new(app.join("db/migrations"))
+        .await
+        .unwrap()
+        .run(&mut connection)
+        .await
+        .unwrap();
+
+    let check = Command::new(env!("CARGO"))
+        .args(["check", "--workspace", "--all-targets"])
+        .current_dir(&app)
+        .env("CARGO_TARGET_DIR", tmp.join("target"))
+        .env("DATABASE_URL", &url)
+        .env("SQLX_OFFLINE", "false")
+        .env_remove("SQLX_OFFLINE_DIR")
+        .output()
+        .unwrap();
+    assert!(
+        check.status.success(),
+        "{}",
+        String::from_utf8_lossy(&check.stderr)
+    );
+}
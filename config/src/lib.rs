@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     env,
     fmt::{Display, Formatter},
     net::{IpAddr, Ipv4Addr, SocketAddr},
@@ -27,6 +28,10 @@ pub struct Config {
     pub view: ViewConfig,
     pub mailer: MailerConfig,
     pub worker: WorkerConfig,
+    pub auth: AuthConfig,
+    pub cookie: CookieConfig,
+    pub csrf: CsrfConfig,
+    pub account: AccountConfig,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -46,6 +51,16 @@ pub struct ServerConfig {
 
     /// The host to bind to, e.g. "localhost"
     pub host: String,
+
+    /// Whether requests only reach the app through a proxy that sets `client_ip_header`, e.g. on
+    /// Fly. The header is ignored otherwise, clients talking to the app directly can put any ip
+    /// in it and get past the per-ip login throttle.
+    #[serde(default)]
+    pub trusted_proxy: bool,
+
+    /// The header the trusted proxy puts the client ip in, `x-forwarded-for` when this isn't
+    /// set. The ip of the connection is used without a trusted proxy.
+    pub client_ip_header: Option<String>,
 }
 
 impl Default for ServerConfig {
@@ -54,6 +69,8 @@ impl Default for ServerConfig {
             ip: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
             port: 3000,
             host: "http://localhost".to_string(),
+            trusted_proxy: false,
+            client_ip_header: None,
         }
     }
 }
@@ -77,6 +94,9 @@ impl ServerConfig {
 #[cfg_attr(test, derive(PartialEq))]
 pub struct DatabaseConfig {
     /// The URL to use to connect to the database, e.g. "sqlite://database.db"
+    ///
+    /// The URL cannot switch the backend: a `postgres://` URL only works after rebuilding with
+    /// `--features postgres`, otherwise it is refused at startup.
     pub url: String,
 }
 
@@ -105,6 +125,8 @@ pub struct ViewConfig {
     pub templates_path: String,
     /// The path to the component directory e.g. /assets/components
     pub components_path: String,
+    /// The templates of the error pages, see [`ErrorTemplatesConfig`]
+    pub error_templates: ErrorTemplatesConfig,
 }
 
 impl Default for ViewConfig {
@@ -112,6 +134,38 @@ impl Default for ViewConfig {
         Self {
             templates_path: "assets/templates".to_string(),
             components_path: "assets/components".to_string(),
+            error_templates: ErrorTemplatesConfig::default(),
+        }
+    }
+}
+
+/// The templates browsers get when a request fails, relative to `templates_path`.
+///
+/// Every template gets the `status`, its `title` and, for errors the user can fix, a `detail`
+/// and the `errors` of each field. Statuses without their own template use `default`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct ErrorTemplatesConfig {
+    /// `401 Unauthorized`, e.g. "errors/401.html"
+    pub unauthorized: String,
+    /// `404 Not Found`, e.g. "errors/404.html"
+    pub not_found: String,
+    /// `422 Unprocessable Entity`, e.g. "errors/422.html"
+    pub unprocessable_entity: String,
+    /// `500 Internal Server Error`, e.g. "errors/500.html"
+    pub internal_server_error: String,
+    /// Any other status, e.g. "errors/error.html"
+    pub default: String,
+}
+
+impl Default for ErrorTemplatesConfig {
+    fn default() -> Self {
+        Self {
+            unauthorized: "errors/401.html".to_string(),
+            not_found: "errors/404.html".to_string(),
+            unprocessable_entity: "errors/422.html".to_string(),
+            internal_server_error: "errors/500.html".to_string(),
+            default: "errors/error.html".to_string(),
         }
     }
 }
@@ -136,6 +190,159 @@ pub struct WorkerConfig {
     pub database_url: String,
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct AuthConfig {
+    /// Limits on failed logins, see [`ThrottleConfig`].
+    pub throttle: ThrottleConfig,
+    /// The OAuth2 / OpenID Connect providers users can log in with, keyed by the name used in
+    /// their urls, e.g. "google" for `/auth/oauth/google`.
+    #[serde(default)]
+    pub oauth: BTreeMap<String, OAuthProviderConfig>,
+    /// Whether users can ask for a link by email that logs them in without their password
+    #[serde(default)]
+    pub magic_link: bool,
+}
+
+/// Failed logins are counted per email and per ip.
+///
+/// After each failure the next attempt has to wait `base_delay_secs`, doubling with every
+/// further failure. Once `max_failures` is reached the email or ip is locked out for
+/// `lockout_secs`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct ThrottleConfig {
+    /// The number of failed logins before a lockout, e.g. 5
+    pub max_failures: u32,
+    /// The delay after the first failure in seconds, e.g. 1
+    pub base_delay_secs: u64,
+    /// How long a lockout lasts in seconds, e.g. 900
+    pub lockout_secs: u64,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: 5,
+            base_delay_secs: 1,
+            lockout_secs: 900,
+        }
+    }
+}
+
+/// An OAuth2 / OpenID Connect provider for "Sign in with …".
+///
+/// Users are sent to the `authorize_url` and come back to `<server.host>/auth/oauth/<name>/callback`,
+/// which has to be registered with the provider. Keep the client secret out of the toml files and
+/// set it with e.g. `APP_AUTH__OAUTH__GOOGLE__CLIENT_SECRET`.
+#[derive(Deserialize, Serialize, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct OAuthProviderConfig {
+    /// The name shown on the login button, e.g. "Google"
+    pub display_name: String,
+    /// The client id issued by the provider
+    pub client_id: String,
+    /// The client secret issued by the provider
+    pub client_secret: String,
+    /// Where users authorize the app, e.g. "https://accounts.google.com/o/oauth2/v2/auth"
+    pub authorize_url: String,
+    /// Where the authorization code is exchanged, e.g. "https://oauth2.googleapis.com/token"
+    pub token_url: String,
+    /// Where the claims of the user are fetched, e.g. "https://openidconnect.googleapis.com/v1/userinfo"
+    pub userinfo_url: String,
+    /// The scopes to request, the email is needed to link existing accounts
+    #[serde(default = "OAuthProviderConfig::default_scopes")]
+    pub scopes: Vec<String>,
+}
+
+// Manual implementation of Debug to redact the client secret
+impl std::fmt::Debug for OAuthProviderConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OAuthProviderConfig")
+            .field("display_name", &self.display_name)
+            .field("client_id", &self.client_id)
+            .field("client_secret", &"[redacted]")
+            .field("authorize_url", &self.authorize_url)
+            .field("token_url", &self.token_url)
+            .field("userinfo_url", &self.userinfo_url)
+            .field("scopes", &self.scopes)
+            .finish()
+    }
+}
+
+impl OAuthProviderConfig {
+    fn default_scopes() -> Vec<String> {
+        vec!["openid".to_string(), "email".to_string()]
+    }
+}
+
+/// The keys signing the session and flash cookies.
+///
+/// New cookies are signed with the first key and cookies signed with any of the keys are
+/// accepted, so a key is rotated by putting a new key first and removing the old one once its
+/// cookies have expired. Keep the keys out of the toml files, set them with e.g.
+/// `APP_COOKIE__KEYS='["..."]'` or point `keys_file` at a secret file. Generate a key with
+/// `cargo gen key`.
+///
+/// Without any keys a random key is used, which is fine for development but logs everybody out
+/// on every restart, so staging and production refuse to start without one.
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct CookieConfig {
+    /// Base64 encoded keys of at least 64 bytes
+    #[serde(default)]
+    pub keys: Vec<String>,
+    /// A file with more keys, one per line, they come after `keys`
+    pub keys_file: Option<String>,
+}
+
+// Manual implementation of Debug to redact the keys
+impl std::fmt::Debug for CookieConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CookieConfig")
+            .field("keys", &format!("[{} redacted]", self.keys.len()))
+            .field("keys_file", &self.keys_file)
+            .finish()
+    }
+}
+
+/// Protection against cross-site request forgery.
+///
+/// Every form posted to the app has to carry the csrf token of the session, either in the
+/// `_csrf` field or the `X-CSRF-Token` header htmx sends. Requests authenticated with an api
+/// token don't need one.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct CsrfConfig {
+    /// Whether requests without a valid token are refused, only turn this off in tests
+    pub enabled: bool,
+}
+
+impl Default for CsrfConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Settings for the accounts of users.
+///
+/// A deleted account is kept for `deletion_grace_days`, the user can cancel the deletion until
+/// then. Once the grace period is over a background job deletes it with everything linked to it.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct AccountConfig {
+    /// How many days a deleted account is kept before it is gone for good, e.g. 30
+    pub deletion_grace_days: u32,
+}
+
+impl Default for AccountConfig {
+    fn default() -> Self {
+        Self {
+            deletion_grace_days: 30,
+        }
+    }
+}
+
 /// Loads the application configuration for a particular environment.
 ///
 /// Depending on the environment, this function will behave differently:
@@ -196,6 +403,10 @@ where
         )
         .merge(Serialized::defaults(ViewConfig::default()).key("view"))
         .merge(Serialized::defaults(StaticAssetsConfig::default()).key("static_assets"))
+        .merge(Serialized::defaults(AuthConfig::default()).key("auth"))
+        .merge(Serialized::defaults(CookieConfig::default()).key("cookie"))
+        .merge(Serialized::defaults(CsrfConfig::default()).key("csrf"))
+        .merge(Serialized::defaults(AccountConfig::default()).key("account"))
         .merge(Toml::file("config/app.toml"))
         .merge(Toml::file(format!(
             "config/environments/{}",
@@ -1,11 +1,22 @@
 use argon2::{
-    Argon2, PasswordHasher,
-    password_hash::{self, SaltString, rand_core::OsRng},
+    Argon2, PasswordHasher, PasswordVerifier,
+    password_hash::{self, PasswordHash, SaltString, rand_core::OsRng},
 };
-use shipwright_db::{
-    Error as DbError,
-    entities::user::{User, UserCredentials, UserStatus},
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use rand::Rng as _;
+use sha2::{Digest as _, Sha256};
+use shipwright_config::ThrottleConfig;
+use shipwright_db::entities::{
+    api_token::{ApiScope, ApiToken},
+    identity::{Identity, IdentityClaims},
+    login_throttle::LoginThrottle,
+    user::{RegisterUser, User, UserCredentials, UserStatus},
 };
+use time::{Duration, OffsetDateTime};
+use totp_rs::{Algorithm, Secret, TOTP};
+use validator::Validate as _;
+
+use crate::Error;
 
 /// The Account context handles all business logic related to user accounts,
 /// authentication, and sessions without directly interacting with the database.
@@ -25,24 +36,547 @@ impl Account {
     }
 
     /// Validates user credentials against a user record.
-    pub fn validate_credentials(user: &User, credentials: &UserCredentials) -> Result<(), DbError> {
+    ///
+    /// This is the only place credentials are checked. The password is verified before the
+    /// status so a pending account is only revealed to someone who knows its password.
+    ///
+    /// Verifying the password is slow, call this from a blocking task.
+    pub fn validate_credentials(user: &User, credentials: &UserCredentials) -> Result<(), Error> {
+        Self::verify_password(user, &credentials.password)?;
+
         if user.status != UserStatus::Confirmed {
-            return Err(DbError::ValidationError(validator::ValidationErrors::new()));
+            return Err(Error::PendingConfirmation);
         }
 
-        let argon2 = Argon2::default();
-        let parsed_hash = password_hash::PasswordHash::new(&user.password_hash)
-            .map_err(|_| DbError::PasswordHashError(password_hash::Error::Password))?;
+        Ok(())
+    }
 
-        argon2
-            .verify_password(credentials.password.as_bytes(), &parsed_hash)
-            .map_err(|_| DbError::PasswordHashError(password_hash::Error::Password))?;
+    /// Checks the password of a user, e.g. a logged in user about to change their account.
+    ///
+    /// Verifying the password is slow, call this from a blocking task.
+    pub fn verify_password(user: &User, password: &str) -> Result<(), Error> {
+        let parsed_hash = PasswordHash::new(&user.password_hash)?;
 
-        Ok(())
+        Argon2::default()
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .map_err(|_| Error::InvalidCredentials)
     }
 
     /// Validates a user's registration data.
-    pub fn validate_registration(credentials: &UserCredentials) -> Result<(), DbError> {
-        credentials.validate().map_err(DbError::ValidationError)
+    pub fn validate_registration(credentials: &UserCredentials) -> Result<(), Error> {
+        credentials.validate()?;
+
+        Ok(())
+    }
+
+    /// Refuses a login while the email or ip it comes from is throttled.
+    pub fn check_throttle(
+        throttle: Option<&LoginThrottle>,
+        now: OffsetDateTime,
+    ) -> Result<(), Error> {
+        match throttle {
+            Some(throttle) if throttle.locked_until > now => Err(Error::TooManyAttempts {
+                retry_after: (throttle.locked_until - now).whole_seconds().max(1) as u64,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the failure count and when the next login may be attempted after a failed login.
+    ///
+    /// The delay doubles with every failure until `max_failures` is reached, which locks the
+    /// email or ip out for `lockout_secs`. Failures are forgotten once there hasn't been one for
+    /// a whole lockout.
+    pub fn throttle_failure(
+        throttle: Option<&LoginThrottle>,
+        config: &ThrottleConfig,
+        now: OffsetDateTime,
+    ) -> (i64, OffsetDateTime) {
+        let lockout = Duration::seconds(config.lockout_secs as i64);
+
+        let failures = match throttle {
+            Some(throttle) if throttle.locked_until + lockout > now => throttle.failures + 1,
+            _ => 1,
+        };
+
+        if failures >= i64::from(config.max_failures) {
+            return (failures, now + lockout);
+        }
+
+        let backoff = 1u64.checked_shl((failures - 1) as u32).unwrap_or(u64::MAX);
+        let delay = config
+            .base_delay_secs
+            .saturating_mul(backoff)
+            .min(config.lockout_secs);
+
+        (failures, now + Duration::seconds(delay as i64))
+    }
+}
+
+impl Account {
+    /// The number of recovery codes handed out when two-factor authentication is enabled.
+    pub const RECOVERY_CODES: usize = 10;
+
+    /// Generates a new base32 encoded secret for an authenticator app.
+    pub fn generate_totp_secret() -> String {
+        Secret::generate_secret().to_encoded().to_string()
+    }
+
+    /// Builds the TOTP for a secret, with the standard 6 digits every 30 seconds.
+    ///
+    /// The issuer and email only show up in authenticator apps, see [`TOTP::get_url`].
+    pub fn totp(secret: &str, issuer: &str, email: &str) -> Result<TOTP, Error> {
+        build_totp(secret, Some(issuer.to_string()), email.to_string())
+    }
+
+    /// Checks a code from an authenticator app, allowing for one step of clock drift.
+    ///
+    /// Returns the time step of the code. A code stays valid for a while, so codes of
+    /// `last_used_step` or before are refused to keep an intercepted code from being replayed.
+    pub fn verify_totp(
+        secret: &str,
+        code: &str,
+        now: OffsetDateTime,
+        last_used_step: Option<i64>,
+    ) -> Result<i64, Error> {
+        let totp = build_totp(secret, None, String::new())?;
+        let skew = u64::from(totp.skew);
+        let current_step = now.unix_timestamp() as u64 / totp.step;
+        let exact = TOTP { skew: 0, ..totp };
+
+        (current_step.saturating_sub(skew)..=current_step + skew)
+            .filter(|step| last_used_step.is_none_or(|last| *step as i64 > last))
+            .find(|step| exact.check(code.trim(), step * exact.step))
+            .map(|step| step as i64)
+            .ok_or(Error::InvalidTotpCode)
+    }
+
+    /// Whether a submitted code looks like a recovery code rather than a code from an app.
+    pub fn is_recovery_code(code: &str) -> bool {
+        code.trim().contains('-')
+    }
+
+    /// Generates single use recovery codes such as `x7k2m-9qp4z`.
+    pub fn generate_recovery_codes() -> Vec<String> {
+        (0..Self::RECOVERY_CODES)
+            .map(|_| {
+                let code = random_alphanumeric(10).to_ascii_lowercase();
+                format!("{}-{}", &code[..5], &code[5..])
+            })
+            .collect()
+    }
+
+    /// Hashes a recovery code for storage.
+    ///
+    /// The codes are long and random so a fast hash is enough, unlike passwords.
+    pub fn hash_recovery_code(code: &str) -> String {
+        sha256_hex(&code.trim().to_lowercase())
+    }
+}
+
+impl Account {
+    /// The prefix of personal access tokens, so they are easy to spot in code and secret scanners.
+    pub const API_TOKEN_PREFIX: &'static str = "swp_";
+
+    /// Generates a new personal access token such as `swp_3Xk9…`.
+    pub fn generate_api_token() -> String {
+        format!("{}{}", Self::API_TOKEN_PREFIX, random_alphanumeric(40))
+    }
+
+    /// Hashes a personal access token for storage, see [`Account::hash_recovery_code`].
+    pub fn hash_api_token(token: &str) -> String {
+        sha256_hex(token.trim())
+    }
+
+    /// When a token created now with an expiry of `expires_in_days` expires, `0` never expires.
+    pub fn api_token_expiry(expires_in_days: u32, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        match expires_in_days {
+            0 => None,
+            days => Some(now + Duration::days(i64::from(days))),
+        }
+    }
+
+    /// Checks that a token may be used for a request that needs `scope`.
+    ///
+    /// An expired token is as good as no token, a token with too narrow a scope is refused.
+    pub fn authorize_api_token(
+        token: &ApiToken,
+        scope: ApiScope,
+        now: OffsetDateTime,
+    ) -> Result<(), Error> {
+        if token.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(Error::InvalidApiToken);
+        }
+
+        match (token.scope, scope) {
+            (ApiScope::Read, ApiScope::Write) => Err(Error::InsufficientScope),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Account {
+    /// Names a session in pages and urls, the session id itself is the secret in the cookie.
+    pub fn session_handle(session_id: &str) -> String {
+        sha256_hex(session_id)[..16].to_string()
+    }
+
+    /// When an account whose deletion is asked for now is deleted, after `grace_days`.
+    pub fn deletion_date(grace_days: u32, now: OffsetDateTime) -> OffsetDateTime {
+        now + Duration::days(i64::from(grace_days))
+    }
+}
+
+/// How a login through an OAuth2 / OpenID Connect provider maps to a user.
+#[derive(Debug)]
+pub enum IdentityLink {
+    /// The identity has logged in before and belongs to this user.
+    Existing(i64),
+    /// The identity is new but a user with the same verified email exists, link it to them.
+    Link(i64),
+    /// The identity is new and the user with the same verified email never confirmed it.
+    ///
+    /// Whoever registered may not own the email, so the password they chose is replaced and
+    /// their sessions are ended before the account is confirmed and linked.
+    Claim { user_id: i64, password: String },
+    /// Nobody uses the verified email yet, register a user for it.
+    Register(RegisterUser),
+}
+
+impl Account {
+    /// Generates the random `state` that ties a provider's callback to the login that started it.
+    pub fn generate_oauth_state() -> String {
+        random_alphanumeric(32)
+    }
+
+    /// Generates a PKCE code verifier, see RFC 7636.
+    pub fn generate_pkce_verifier() -> String {
+        random_alphanumeric(64)
+    }
+
+    /// Derives the `S256` code challenge sent to the provider from a PKCE code verifier.
+    pub fn pkce_challenge(verifier: &str) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+    }
+
+    /// Generates the token that proves a form was posted from a page of the app.
+    pub fn generate_csrf_token() -> String {
+        random_alphanumeric(32)
+    }
+
+    /// Compares a posted csrf token with the one of the session.
+    ///
+    /// Both are hashed first and every byte is compared, so the time it takes doesn't tell how
+    /// much of the token was right.
+    pub fn verify_csrf_token(expected: &str, given: &str) -> bool {
+        let (expected, given) = (
+            Sha256::digest(expected.as_bytes()),
+            Sha256::digest(given.as_bytes()),
+        );
+
+        expected
+            .iter()
+            .zip(given.iter())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    }
+
+    /// Decides which user a login through a provider belongs to.
+    ///
+    /// A known identity logs in its user. Otherwise the email the provider returned is used to
+    /// find or register the user, but only if the provider verified it. Linking on an unverified
+    /// email would hand over the account to whoever typed that address in at the provider.
+    ///
+    /// `user` is the user with the email from the claims, if there is one.
+    pub fn link_identity(
+        identity: Option<&Identity>,
+        user: Option<&User>,
+        claims: &IdentityClaims,
+    ) -> Result<IdentityLink, Error> {
+        if let Some(identity) = identity {
+            return Ok(IdentityLink::Existing(identity.user_id));
+        }
+
+        let email = match &claims.email {
+            Some(email) if claims.email_verified => email,
+            _ => return Err(Error::UnverifiedEmail),
+        };
+
+        // The user logs in through the provider, nobody knows this password. They can still set
+        // one with the forgot password flow.
+        let password = random_alphanumeric(32);
+        match user {
+            Some(user) if user.status == UserStatus::Pending => Ok(IdentityLink::Claim {
+                user_id: user.id,
+                password,
+            }),
+            Some(user) => Ok(IdentityLink::Link(user.id)),
+            None => Ok(IdentityLink::Register(RegisterUser {
+                email: email.clone(),
+                password: password.clone(),
+                confirm_password: password,
+            })),
+        }
+    }
+}
+
+fn sha256_hex(value: &str) -> String {
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn random_alphanumeric(len: usize) -> String {
+    rand::rng()
+        .sample_iter(rand::distr::Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn build_totp(secret: &str, issuer: Option<String>, account_name: String) -> Result<TOTP, Error> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| Error::InvalidTotpSecret(format!("{e:?}")))?;
+
+    TOTP::new(Algorithm::SHA1, 6, 1, 30, secret, issuer, account_name)
+        .map_err(|e| Error::InvalidTotpSecret(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ThrottleConfig {
+        ThrottleConfig {
+            max_failures: 4,
+            base_delay_secs: 2,
+            lockout_secs: 600,
+        }
+    }
+
+    fn throttle(failures: i64, locked_until: OffsetDateTime) -> LoginThrottle {
+        LoginThrottle {
+            throttle_key: "email:hownow@browncow.com".to_string(),
+            failures,
+            locked_until,
+        }
+    }
+
+    #[test]
+    fn delay_doubles_until_lockout() {
+        let now = OffsetDateTime::now_utc();
+
+        let (failures, locked_until) = Account::throttle_failure(None, &config(), now);
+        assert_eq!((failures, locked_until - now), (1, Duration::seconds(2)));
+
+        let previous = throttle(failures, locked_until);
+        let (failures, locked_until) = Account::throttle_failure(Some(&previous), &config(), now);
+        assert_eq!((failures, locked_until - now), (2, Duration::seconds(4)));
+
+        let previous = throttle(3, now);
+        let (failures, locked_until) = Account::throttle_failure(Some(&previous), &config(), now);
+        assert_eq!((failures, locked_until - now), (4, Duration::seconds(600)));
+    }
+
+    #[test]
+    fn failures_are_forgotten_after_a_quiet_lockout() {
+        let now = OffsetDateTime::now_utc();
+        let previous = throttle(4, now - Duration::seconds(601));
+
+        let (failures, _) = Account::throttle_failure(Some(&previous), &config(), now);
+
+        assert_eq!(failures, 1);
+    }
+
+    #[test]
+    fn check_throttle_refuses_until_the_lock_expires() {
+        let now = OffsetDateTime::now_utc();
+        let locked = throttle(1, now + Duration::seconds(30));
+
+        assert!(matches!(
+            Account::check_throttle(Some(&locked), now),
+            Err(Error::TooManyAttempts { retry_after: 30 })
+        ));
+        assert!(Account::check_throttle(Some(&locked), now + Duration::seconds(30)).is_ok());
+        assert!(Account::check_throttle(None, now).is_ok());
+    }
+
+    #[test]
+    fn verify_totp_accepts_the_current_code() {
+        let secret = Account::generate_totp_secret();
+        let now = OffsetDateTime::now_utc();
+        let code = Account::totp(&secret, "shipwright", "hownow@browncow.com")
+            .unwrap()
+            .generate(now.unix_timestamp() as u64);
+
+        assert!(Account::verify_totp(&secret, &code, now, None).is_ok());
+        assert!(matches!(
+            Account::verify_totp(&secret, &code, now + Duration::minutes(5), None),
+            Err(Error::InvalidTotpCode)
+        ));
+    }
+
+    #[test]
+    fn verify_totp_refuses_codes_of_used_steps() {
+        let secret = Account::generate_totp_secret();
+        let now = OffsetDateTime::now_utc();
+        let code = Account::totp(&secret, "shipwright", "hownow@browncow.com")
+            .unwrap()
+            .generate(now.unix_timestamp() as u64);
+
+        let step = Account::verify_totp(&secret, &code, now, None).unwrap();
+        assert_eq!(step, now.unix_timestamp() / 30);
+        assert!(Account::verify_totp(&secret, &code, now, Some(step - 1)).is_ok());
+        assert!(matches!(
+            Account::verify_totp(&secret, &code, now, Some(step)),
+            Err(Error::InvalidTotpCode)
+        ));
+        // Still within the clock drift allowed for, but already used
+        assert!(matches!(
+            Account::verify_totp(&secret, &code, now + Duration::seconds(30), Some(step)),
+            Err(Error::InvalidTotpCode)
+        ));
+    }
+
+    #[test]
+    fn recovery_codes_hash_the_same_however_they_are_typed() {
+        let codes = Account::generate_recovery_codes();
+
+        assert_eq!(codes.len(), Account::RECOVERY_CODES);
+        assert!(codes.iter().all(|code| Account::is_recovery_code(code)));
+        assert_eq!(
+            Account::hash_recovery_code(&codes[0]),
+            Account::hash_recovery_code(&format!(" {} ", codes[0].to_uppercase()))
+        );
+    }
+
+    #[test]
+    fn pkce_challenge_matches_the_rfc_example() {
+        assert_eq!(
+            Account::pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    fn claims(email_verified: bool) -> IdentityClaims {
+        IdentityClaims {
+            sub: "248289761001".to_string(),
+            email: Some("hownow@browncow.com".to_string()),
+            email_verified,
+        }
+    }
+
+    fn user() -> User {
+        User {
+            id: 7,
+            email: "hownow@browncow.com".to_string(),
+            password_hash: String::new(),
+            status: UserStatus::Confirmed,
+        }
+    }
+
+    #[test]
+    fn link_identity_prefers_a_known_identity() {
+        let identity = Identity {
+            id: 1,
+            user_id: 3,
+            provider: "google".to_string(),
+            subject: "248289761001".to_string(),
+        };
+
+        assert!(matches!(
+            Account::link_identity(Some(&identity), Some(&user()), &claims(false)),
+            Ok(IdentityLink::Existing(3))
+        ));
+    }
+
+    #[test]
+    fn link_identity_only_trusts_verified_emails() {
+        assert!(matches!(
+            Account::link_identity(None, Some(&user()), &claims(true)),
+            Ok(IdentityLink::Link(7))
+        ));
+        assert!(matches!(
+            Account::link_identity(None, Some(&user()), &claims(false)),
+            Err(Error::UnverifiedEmail)
+        ));
+        assert!(matches!(
+            Account::link_identity(None, None, &claims(false)),
+            Err(Error::UnverifiedEmail)
+        ));
+    }
+
+    #[test]
+    fn link_identity_claims_pending_users() {
+        let pending = User {
+            status: UserStatus::Pending,
+            ..user()
+        };
+
+        match Account::link_identity(None, Some(&pending), &claims(true)) {
+            Ok(IdentityLink::Claim { user_id, password }) => {
+                assert_eq!(user_id, 7);
+                assert_eq!(password.len(), 32);
+            }
+            other => panic!("expected a claim, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn link_identity_registers_new_emails() {
+        match Account::link_identity(None, None, &claims(true)) {
+            Ok(IdentityLink::Register(user)) => {
+                assert_eq!(user.email, "hownow@browncow.com");
+                assert_eq!(user.password, user.confirm_password);
+            }
+            other => panic!("expected a registration, got {other:?}"),
+        }
+    }
+
+    fn api_token(scope: ApiScope, expires_at: Option<OffsetDateTime>) -> ApiToken {
+        ApiToken {
+            id: 1,
+            user_id: 7,
+            name: "deploy script".to_string(),
+            token_hash: Account::hash_api_token(&Account::generate_api_token()),
+            scope,
+            expires_at,
+            last_used_at: None,
+            created_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    #[test]
+    fn authorize_api_token_checks_scope_and_expiry() {
+        let now = OffsetDateTime::now_utc();
+
+        let read = api_token(ApiScope::Read, None);
+        assert!(Account::authorize_api_token(&read, ApiScope::Read, now).is_ok());
+        assert!(matches!(
+            Account::authorize_api_token(&read, ApiScope::Write, now),
+            Err(Error::InsufficientScope)
+        ));
+
+        let write = api_token(ApiScope::Write, Account::api_token_expiry(30, now));
+        assert!(Account::authorize_api_token(&write, ApiScope::Read, now).is_ok());
+        assert!(matches!(
+            Account::authorize_api_token(&write, ApiScope::Write, now + Duration::days(30)),
+            Err(Error::InvalidApiToken)
+        ));
+    }
+
+    #[test]
+    fn verify_csrf_token_only_accepts_the_same_token() {
+        let token = Account::generate_csrf_token();
+
+        assert!(Account::verify_csrf_token(&token, &token));
+        assert!(!Account::verify_csrf_token(&token, &token[..31]));
+        assert!(!Account::verify_csrf_token(&token, ""));
+        assert!(!Account::verify_csrf_token(
+            &token,
+            &Account::generate_csrf_token()
+        ));
     }
 }
@@ -1,3 +1,58 @@
 pub mod account;
 
-pub use account::Account;
+pub use account::{Account, IdentityLink};
+
+/// Errors that can occur while applying the business rules of a context.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// The email or password did not match a user.
+    ///
+    /// Return `401 Unauthorized` on invalid credentials.
+    #[error("invalid credentials")]
+    InvalidCredentials,
+    /// The credentials are correct but the user has not confirmed their email yet.
+    ///
+    /// Return `403 Forbidden` on a pending account.
+    #[error("account is pending confirmation")]
+    PendingConfirmation,
+    /// Too many logins failed for the email or ip, the next attempt has to wait.
+    ///
+    /// Return `429 Too Many Requests` on a throttled login.
+    #[error("too many failed logins, try again in {retry_after} seconds")]
+    TooManyAttempts { retry_after: u64 },
+    /// The code from the authenticator app or the recovery code did not match.
+    ///
+    /// Return `401 Unauthorized` on an invalid code.
+    #[error("invalid two-factor code")]
+    InvalidTotpCode,
+    /// The stored totp secret could not be used.
+    ///
+    /// Return `500 Internal Server Error` on an invalid secret.
+    #[error("invalid totp secret: {0}")]
+    InvalidTotpSecret(String),
+    /// The provider did not return a verified email for a new identity.
+    ///
+    /// Return `403 Forbidden` on an unverified email.
+    #[error("the provider did not verify the email address")]
+    UnverifiedEmail,
+    /// The personal access token is unknown or has expired.
+    ///
+    /// Return `401 Unauthorized` on an invalid token.
+    #[error("invalid api token")]
+    InvalidApiToken,
+    /// The personal access token doesn't have the scope the request needs.
+    ///
+    /// Return `403 Forbidden` on a token with too narrow a scope.
+    #[error("the api token doesn't have the scope for this request")]
+    InsufficientScope,
+    /// The stored password hash could not be parsed.
+    ///
+    /// Return `500 Internal Server Error` on a password hash error.
+    #[error("invalid password hash")]
+    PasswordHash(#[from] argon2::password_hash::Error),
+    /// The input failed validation.
+    ///
+    /// Return `422 Unprocessable Entity` on a validation error.
+    #[error("validation failed")]
+    Validation(#[from] validator::ValidationErrors),
+}
@@ -0,0 +1,12 @@
+-- Create personal api token table, only the hash of each token is stored
+CREATE TABLE api_tokens (
+id INTEGER PRIMARY KEY NOT NULL,
+user_id INTEGER NOT NULL,
+name TEXT NOT NULL,
+token_hash TEXT UNIQUE NOT NULL,
+scope TEXT NOT NULL DEFAULT 'read',
+expires_at TIMESTAMP,
+last_used_at TIMESTAMP,
+created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
@@ -0,0 +1,42 @@
+-- Create role and permission tables, a user may do what any of their roles grants
+CREATE TABLE roles (
+id INTEGER PRIMARY KEY NOT NULL,
+name TEXT UNIQUE NOT NULL
+) ;
+
+CREATE TABLE permissions (
+id INTEGER PRIMARY KEY NOT NULL,
+name TEXT UNIQUE NOT NULL
+) ;
+
+CREATE TABLE role_permissions (
+role_id INTEGER NOT NULL,
+permission_id INTEGER NOT NULL,
+PRIMARY KEY (role_id, permission_id),
+FOREIGN KEY (role_id) REFERENCES roles (id),
+FOREIGN KEY (permission_id) REFERENCES permissions (id)
+) ;
+
+CREATE TABLE user_roles (
+user_id INTEGER NOT NULL,
+role_id INTEGER NOT NULL,
+PRIMARY KEY (user_id, role_id),
+FOREIGN KEY (user_id) REFERENCES users (id),
+FOREIGN KEY (role_id) REFERENCES roles (id)
+) ;
+
+INSERT INTO roles (name) VALUES ('admin'), ('member') ;
+
+INSERT INTO permissions (name) VALUES ('todos.write'), ('lions.write'), ('invoices.write') ;
+
+-- Admins may write everything, members only their todos
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT roles.id, permissions.id FROM roles, permissions WHERE roles.name = 'admin' ;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT roles.id, permissions.id FROM roles, permissions
+WHERE roles.name = 'member' AND permissions.name = 'todos.write' ;
+
+-- Everybody who could log in before keeps writing their todos
+INSERT INTO user_roles (user_id, role_id)
+SELECT users.id, roles.id FROM users, roles WHERE roles.name = 'member' ;
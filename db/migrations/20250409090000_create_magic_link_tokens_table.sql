@@ -0,0 +1,7 @@
+-- Create magic link token table
+CREATE TABLE magic_link_tokens (
+token_hash TEXT PRIMARY KEY NOT NULL,
+user_id INTEGER NOT NULL,
+expires_at TIMESTAMP DEFAULT (DATETIME (CURRENT_TIMESTAMP, '+15 minutes')),
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
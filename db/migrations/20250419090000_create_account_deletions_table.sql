@@ -0,0 +1,7 @@
+-- Create account deletion table, accounts are deleted once the grace period is over
+CREATE TABLE account_deletions (
+user_id INTEGER PRIMARY KEY NOT NULL,
+requested_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+delete_after TIMESTAMP NOT NULL,
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
@@ -0,0 +1,30 @@
+-- Create team tables, users join a team by accepting an invitation sent to their email
+CREATE TABLE teams (
+id INTEGER PRIMARY KEY NOT NULL,
+name TEXT NOT NULL,
+created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+) ;
+
+CREATE TABLE team_members (
+team_id INTEGER NOT NULL,
+user_id INTEGER NOT NULL,
+role TEXT NOT NULL DEFAULT 'member',
+created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+PRIMARY KEY (team_id, user_id),
+FOREIGN KEY (team_id) REFERENCES teams (id),
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
+
+CREATE INDEX team_members_user_id ON team_members (user_id) ;
+
+CREATE TABLE team_invitations (
+id INTEGER PRIMARY KEY NOT NULL,
+token_hash TEXT UNIQUE NOT NULL,
+team_id INTEGER NOT NULL,
+email TEXT NOT NULL,
+invited_by INTEGER NOT NULL,
+expires_at TIMESTAMP NOT NULL DEFAULT (DATETIME (CURRENT_TIMESTAMP, '+7 days')),
+created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+FOREIGN KEY (team_id) REFERENCES teams (id),
+FOREIGN KEY (invited_by) REFERENCES users (id)
+) ;
@@ -0,0 +1 @@
+CREATE TABLE IF NOT EXISTS "invoices" ( "id" TEXT NOT NULL, "amount" DOUBLE PRECISION, "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP, "updated_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP )
\ No newline at end of file
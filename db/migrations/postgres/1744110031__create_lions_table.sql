@@ -0,0 +1 @@
+CREATE TABLE IF NOT EXISTS "lions" ( "id" TEXT UNIQUE NOT NULL, "name" VARCHAR(256) NOT NULL, "email" VARCHAR(256) NOT NULL )
\ No newline at end of file
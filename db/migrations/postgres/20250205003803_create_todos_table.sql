@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS todos
+(
+    id BIGSERIAL PRIMARY KEY NOT NULL,
+    description TEXT NOT NULL
+)
+
@@ -0,0 +1,6 @@
+-- Matches the schema tower-sessions' PostgresStore expects.
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    data BYTEA NOT NULL,
+    expiry_date TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Create registration token table
+CREATE TABLE registration_tokens (
+register_token TEXT PRIMARY KEY NOT NULL,
+user_id BIGINT NOT NULL,
+expires_at TIMESTAMPTZ DEFAULT (CURRENT_TIMESTAMP + INTERVAL '2 hours'),
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
@@ -0,0 +1,7 @@
+-- Create password reset token table
+CREATE TABLE password_reset_tokens (
+token_hash TEXT PRIMARY KEY NOT NULL,
+user_id BIGINT NOT NULL,
+expires_at TIMESTAMPTZ DEFAULT (CURRENT_TIMESTAMP + INTERVAL '1 hours'),
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
@@ -0,0 +1,6 @@
+-- Create login throttle table, keyed by the email or ip of the failed logins
+CREATE TABLE login_throttles (
+throttle_key TEXT PRIMARY KEY NOT NULL,
+failures BIGINT NOT NULL DEFAULT 0,
+locked_until TIMESTAMPTZ NOT NULL
+) ;
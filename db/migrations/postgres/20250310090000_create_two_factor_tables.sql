@@ -0,0 +1,14 @@
+-- Create totp credential table, a user has at most one authenticator
+CREATE TABLE totp_credentials (
+user_id BIGINT PRIMARY KEY NOT NULL,
+secret TEXT NOT NULL,
+enabled BOOLEAN NOT NULL DEFAULT FALSE,
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
+
+-- Create recovery code table, only the hash of each code is stored
+CREATE TABLE recovery_codes (
+code_hash TEXT PRIMARY KEY NOT NULL,
+user_id BIGINT NOT NULL,
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
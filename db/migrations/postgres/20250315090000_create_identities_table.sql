@@ -0,0 +1,9 @@
+-- Create identity table, linking a user to their account at an OAuth2 / OpenID Connect provider
+CREATE TABLE identities (
+id BIGSERIAL PRIMARY KEY NOT NULL,
+user_id BIGINT NOT NULL,
+provider TEXT NOT NULL,
+subject TEXT NOT NULL,
+FOREIGN KEY (user_id) REFERENCES users (id),
+UNIQUE (provider, subject)
+) ;
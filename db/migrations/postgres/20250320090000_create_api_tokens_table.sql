@@ -0,0 +1,12 @@
+-- Create personal api token table, only the hash of each token is stored
+CREATE TABLE api_tokens (
+id BIGSERIAL PRIMARY KEY NOT NULL,
+user_id BIGINT NOT NULL,
+name TEXT NOT NULL,
+token_hash TEXT UNIQUE NOT NULL,
+scope TEXT NOT NULL DEFAULT 'read',
+expires_at TIMESTAMPTZ,
+last_used_at TIMESTAMPTZ,
+created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
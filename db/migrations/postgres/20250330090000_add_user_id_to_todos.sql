@@ -0,0 +1,2 @@
+-- Todos belong to the user who created them, todos created before have no owner and are hidden
+ALTER TABLE todos ADD COLUMN user_id BIGINT REFERENCES users (id) ;
@@ -0,0 +1,6 @@
+-- Record who a session belongs to and where it was last used, so users can see and end their sessions
+ALTER TABLE sessions ADD COLUMN user_id BIGINT REFERENCES users (id) ;
+ALTER TABLE sessions ADD COLUMN user_agent TEXT ;
+ALTER TABLE sessions ADD COLUMN ip_address TEXT ;
+ALTER TABLE sessions ADD COLUMN last_seen_at TIMESTAMPTZ ;
+CREATE INDEX sessions_user_id ON sessions (user_id) ;
@@ -0,0 +1,7 @@
+-- Create magic link token table
+CREATE TABLE magic_link_tokens (
+token_hash TEXT PRIMARY KEY NOT NULL,
+user_id BIGINT NOT NULL,
+expires_at TIMESTAMPTZ DEFAULT (CURRENT_TIMESTAMP + INTERVAL '15 minutes'),
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
@@ -0,0 +1,8 @@
+-- Create email change token table
+CREATE TABLE email_change_tokens (
+token_hash TEXT PRIMARY KEY NOT NULL,
+user_id BIGINT NOT NULL,
+new_email TEXT NOT NULL,
+expires_at TIMESTAMPTZ DEFAULT (CURRENT_TIMESTAMP + INTERVAL '2 hours'),
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
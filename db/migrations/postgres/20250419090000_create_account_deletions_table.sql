@@ -0,0 +1,7 @@
+-- Create account deletion table, accounts are deleted once the grace period is over
+CREATE TABLE account_deletions (
+user_id BIGINT PRIMARY KEY NOT NULL,
+requested_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+delete_after TIMESTAMPTZ NOT NULL,
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
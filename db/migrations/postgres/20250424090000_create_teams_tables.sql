@@ -0,0 +1,30 @@
+-- Create team tables, users join a team by accepting an invitation sent to their email
+CREATE TABLE teams (
+id BIGSERIAL PRIMARY KEY NOT NULL,
+name TEXT NOT NULL,
+created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+) ;
+
+CREATE TABLE team_members (
+team_id BIGINT NOT NULL,
+user_id BIGINT NOT NULL,
+role TEXT NOT NULL DEFAULT 'member',
+created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+PRIMARY KEY (team_id, user_id),
+FOREIGN KEY (team_id) REFERENCES teams (id),
+FOREIGN KEY (user_id) REFERENCES users (id)
+) ;
+
+CREATE INDEX team_members_user_id ON team_members (user_id) ;
+
+CREATE TABLE team_invitations (
+id BIGSERIAL PRIMARY KEY NOT NULL,
+token_hash TEXT UNIQUE NOT NULL,
+team_id BIGINT NOT NULL,
+email TEXT NOT NULL,
+invited_by BIGINT NOT NULL,
+expires_at TIMESTAMPTZ NOT NULL DEFAULT (CURRENT_TIMESTAMP + INTERVAL '7 days'),
+created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+FOREIGN KEY (team_id) REFERENCES teams (id),
+FOREIGN KEY (invited_by) REFERENCES users (id)
+) ;
@@ -0,0 +1,2 @@
+-- Remember the time step of the last accepted code, so a code can't be used twice
+ALTER TABLE totp_credentials ADD COLUMN last_used_step BIGINT ;
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+use crate::{Db, Error};
+
+/// A user's request to delete their account.
+///
+/// The account is kept until `delete_after`, so the user can change their mind. The deletion
+/// itself is done by a background job, see [`super::user_data::UserData::delete`].
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct AccountDeletion {
+    pub user_id: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub requested_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub delete_after: OffsetDateTime,
+}
+
+/// DeleteAccount is a changeset for asking to delete an account, the current password proves it
+/// is the owner asking.
+#[derive(Deserialize, Clone)]
+#[cfg_attr(feature = "test-helpers", derive(serde::Serialize))]
+pub struct DeleteAccount {
+    pub current_password: String,
+}
+
+impl AccountDeletion {
+    pub async fn try_get_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Option<AccountDeletion>, Error> {
+        let deletion = sqlx::query_as!(
+            AccountDeletion,
+            r#"SELECT user_id, requested_at, delete_after FROM account_deletions WHERE user_id = $1"#,
+            user_id
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(deletion)
+    }
+
+    /// Schedules the deletion of an account, asking again keeps the first date.
+    pub async fn schedule(
+        user_id: i64,
+        delete_after: OffsetDateTime,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<AccountDeletion, Error> {
+        let deletion = sqlx::query_as!(
+            AccountDeletion,
+            r#"INSERT INTO account_deletions (user_id, delete_after) VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET user_id = excluded.user_id
+            RETURNING user_id, requested_at, delete_after"#,
+            user_id,
+            delete_after
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(deletion)
+    }
+
+    /// Cancels a scheduled deletion, returns whether there was one to cancel.
+    pub async fn cancel(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<bool, Error> {
+        let result = sqlx::query!("DELETE FROM account_deletions WHERE user_id = $1", user_id)
+            .execute(executor)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The users whose grace period is over at `now`.
+    pub async fn load_due(
+        now: OffsetDateTime,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Vec<i64>, Error> {
+        let user_ids = sqlx::query_scalar!(
+            "SELECT user_id FROM account_deletions WHERE delete_after <= $1 ORDER BY user_id",
+            now
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(user_ids)
+    }
+}
@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Type, prelude::FromRow, types::time::OffsetDateTime};
+use validator::Validate;
+
+use crate::{Db, Error};
+
+/// A personal access token for calling the `/api` routes with `Authorization: Bearer`.
+///
+/// Only the hash of the token is stored, the token itself is shown once when it is created.
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub scope: ApiScope,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// What a token may do, `write` includes `read`.
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, Type)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ApiScope {
+    /// Safe requests only, e.g. `GET`.
+    Read,
+    /// Any request.
+    Write,
+}
+
+impl From<String> for ApiScope {
+    fn from(val: String) -> Self {
+        match val.as_str() {
+            "write" => ApiScope::Write,
+            _ => ApiScope::Read,
+        }
+    }
+}
+
+/// CreateApiToken is a changeset for creating a new personal access token.
+#[derive(Deserialize, Validate, Clone, Debug)]
+#[cfg_attr(feature = "test-helpers", derive(serde::Serialize))]
+pub struct CreateApiToken {
+    #[validate(length(min = 1, max = 64, message = "name must be 1 to 64 characters"))]
+    pub name: String,
+    pub scope: ApiScope,
+    /// The number of days until the token expires, `0` for a token that never expires.
+    pub expires_in_days: u32,
+}
+
+impl ApiToken {
+    pub async fn try_get_by_hash(
+        token_hash: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Option<ApiToken>, Error> {
+        let token = sqlx::query_as!(
+            ApiToken,
+            r#"SELECT id as "id!", user_id, name, token_hash, scope, expires_at, last_used_at, created_at
+            FROM api_tokens WHERE token_hash = $1"#,
+            token_hash
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(token)
+    }
+
+    pub async fn load_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Vec<ApiToken>, Error> {
+        let tokens = sqlx::query_as!(
+            ApiToken,
+            r#"SELECT id as "id!", user_id, name, token_hash, scope, expires_at, last_used_at, created_at
+            FROM api_tokens WHERE user_id = $1 ORDER BY id"#,
+            user_id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(tokens)
+    }
+
+    pub async fn create(
+        user_id: i64,
+        token: &CreateApiToken,
+        token_hash: &str,
+        expires_at: Option<OffsetDateTime>,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<ApiToken, Error> {
+        token.validate()?;
+
+        let token = sqlx::query_as!(
+            ApiToken,
+            r#"INSERT INTO api_tokens (user_id, name, token_hash, scope, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id as "id!", user_id, name, token_hash, scope, expires_at, last_used_at, created_at"#,
+            user_id,
+            token.name,
+            token_hash,
+            token.scope as _,
+            expires_at
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Records when the token was last used, so unused tokens can be spotted and revoked.
+    pub async fn touch(
+        id: i64,
+        now: OffsetDateTime,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            "UPDATE api_tokens SET last_used_at = $1 WHERE id = $2",
+            now,
+            id
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Revokes one of the user's tokens, returns whether there was one to revoke.
+    pub async fn delete_for_user(
+        id: i64,
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            "DELETE FROM api_tokens WHERE id = $1 AND user_id = $2",
+            id,
+            user_id
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Revokes every token of the user, returns how many were revoked.
+    pub async fn delete_all_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        let result = sqlx::query!("DELETE FROM api_tokens WHERE user_id = $1", user_id)
+            .execute(executor)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,62 @@
+use serde::Deserialize;
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+use crate::{Db, Error, single_use_token};
+
+const TABLE: &str = "email_change_tokens";
+
+/// A new email waiting for its owner to confirm it, the user keeps their old email until then.
+///
+/// Whoever has the link can move the account to the new email, see [`single_use_token`].
+#[derive(Clone, FromRow)]
+pub struct EmailChangeToken {
+    pub token_hash: String,
+    pub user_id: i64,
+    pub new_email: String,
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+/// ConfirmEmailChange is a changeset for confirming a new email with the token of a link.
+#[derive(Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "test-helpers", derive(serde::Serialize))]
+pub struct ConfirmEmailChange {
+    pub change_token: String,
+}
+
+impl EmailChangeToken {
+    /// Consumes a change token, returning the change it was issued for.
+    pub async fn try_consume(
+        confirm: &ConfirmEmailChange,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Option<EmailChangeToken>, Error> {
+        single_use_token::consume(TABLE, &confirm.change_token, executor).await
+    }
+
+    /// Issues a change token for a new email, returning the token to send to it.
+    pub async fn create(
+        user_id: i64,
+        new_email: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<String, Error> {
+        let change_token = single_use_token::generate();
+        let token_hash = single_use_token::hash(&change_token);
+        sqlx::query!(
+            r#"INSERT INTO email_change_tokens (token_hash, user_id, new_email) VALUES ($1, $2, $3)"#,
+            token_hash,
+            user_id,
+            new_email
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(change_token)
+    }
+
+    /// Deletes every token issued to a user, so only the latest link works.
+    pub async fn delete_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        single_use_token::delete_for_user(TABLE, user_id, executor).await
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use crate::{Db, Error, ResultExt};
+
+/// A user's account at an OAuth2 / OpenID Connect provider.
+///
+/// The provider's subject identifies the account, emails can change at the provider.
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct Identity {
+    pub id: i64,
+    pub user_id: i64,
+    pub provider: String,
+    pub subject: String,
+}
+
+/// IdentityClaims are the standard claims a provider returns from its userinfo endpoint.
+#[derive(Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "test-helpers", derive(serde::Serialize))]
+pub struct IdentityClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+}
+
+impl Identity {
+    pub async fn try_get_by_subject(
+        provider: &str,
+        subject: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Option<Identity>, Error> {
+        let identity = sqlx::query_as!(
+            Identity,
+            r#"SELECT id as "id!", user_id, provider, subject FROM identities
+            WHERE provider = $1 AND subject = $2"#,
+            provider,
+            subject
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(identity)
+    }
+
+    pub async fn create(
+        user_id: i64,
+        provider: &str,
+        subject: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Identity, Error> {
+        let identity = sqlx::query_as!(
+            Identity,
+            r#"INSERT INTO identities (user_id, provider, subject) VALUES ($1, $2, $3)
+            RETURNING id as "id!", user_id, provider, subject"#,
+            user_id,
+            provider,
+            subject
+        )
+        .fetch_one(executor)
+        .await
+        .map_constraint_err()?;
+
+        Ok(identity)
+    }
+
+    pub async fn load_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Vec<Identity>, Error> {
+        let identities = sqlx::query_as!(
+            Identity,
+            r#"SELECT id as "id!", user_id, provider, subject FROM identities
+            WHERE user_id = $1 ORDER BY id"#,
+            user_id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(identities)
+    }
+
+    /// Unlinks every provider account of a user.
+    pub async fn delete_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        let deleted = sqlx::query!(r#"DELETE FROM identities WHERE user_id = $1"#, user_id)
+            .execute(executor)
+            .await?
+            .rows_affected();
+
+        Ok(deleted)
+    }
+}
@@ -1,12 +1,11 @@
 #[cfg(feature = "test-helpers")]
 use fake::Dummy;
 
-use crate::{Entity, Error, transaction};
-use async_trait::async_trait;
+use crate::Entity;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
-use sqlx::{FromRow, Sqlite, SqlitePool, types::time::OffsetDateTime};
-use uuid::Uuid;
+use sqlx::{FromRow, types::time::OffsetDateTime};
 use validator::Validate;
 
 /// A struct which maps the fields of an invoice with native Sqlite types.
@@ -17,17 +16,24 @@ use validator::Validate;
 /// ```
 /// let invoices = sqlx::query_as!(
 ///     Invoice,
-///     r#"SELECT * FROM invoices where id = ?"#,
+///     r#"SELECT * FROM invoices where id = $1"#,
 ///     id
 ///     )
 ///     .fetch_all(&pool)
 ///     .await?;
 /// ```
-#[derive(Serialize, Debug, Deserialize, FromRow)]
+#[derive(Serialize, Debug, Deserialize, FromRow, Entity, JsonSchema)]
+#[entity(table = "invoices", primary_key = "id", changeset = InvoiceChangeset)]
 pub struct Invoice {
     pub id: String,
     pub amount: Option<f64>,
+    /// Timestamps serialize as `[year, ordinal, hour, minute, second, nanosecond, offset hours,
+    /// offset minutes, offset seconds]`.
+    #[entity(read_only)]
+    #[schemars(with = "Option<(i32, u16, u8, u8, u8, u32, i8, i8, i8)>")]
     pub created_at: Option<OffsetDateTime>,
+    #[entity(read_only)]
+    #[schemars(with = "Option<(i32, u16, u8, u8, u8, u32, i8, i8, i8)>")]
     pub updated_at: Option<OffsetDateTime>,
 }
 
@@ -40,145 +46,9 @@ pub struct Invoice {
 /// ```
 /// let invoice_changeset: InvoiceChangeset = Faker.fake();
 /// ```
-#[derive(Deserialize, Validate, Clone)]
+#[derive(Deserialize, Validate, Clone, JsonSchema)]
 #[cfg_attr(feature = "test-helpers", derive(Serialize, Dummy))]
 pub struct InvoiceChangeset {
     #[cfg_attr(feature = "test-helpers", dummy(faker = "1.00..100.00"))]
     pub amount: Option<f64>,
 }
-
-/// The Entity trait implements all basic CRUD operations for the Invoice.
-///
-/// This allows us to GET | POST | PUT | DELETE invoices in our controllers.
-///
-/// ```
-/// let invoice = Invoice::load(1, &pool).await?;
-/// ```
-#[async_trait]
-impl Entity for Invoice {
-    type Id = String;
-
-    type Record<'a> = Invoice;
-
-    type Changeset = InvoiceChangeset;
-
-    async fn load_all<'a>(
-        executor: impl sqlx::Executor<'_, Database = Sqlite>,
-    ) -> Result<Vec<Invoice>, Error> {
-        let invoices = sqlx::query_as!(
-            Invoice,
-            r#"select id, amount, created_at, updated_at from invoices"#
-        )
-        .fetch_all(executor)
-        .await?;
-
-        Ok(invoices)
-    }
-
-    async fn load<'a>(
-        id: Self::Id,
-        executor: impl sqlx::Executor<'_, Database = Sqlite>,
-    ) -> Result<Invoice, Error> {
-        let invoice = sqlx::query_as!(
-            Invoice,
-            r#"select id, amount, created_at, updated_at from invoices where id = ?"#,
-            id
-        )
-        .fetch_optional(executor)
-        .await?
-        .ok_or(Error::NoRecordFound)?;
-
-        Ok(invoice)
-    }
-
-    async fn create<'a>(
-        invoice: InvoiceChangeset,
-        executor: impl sqlx::Executor<'_, Database = Sqlite>,
-    ) -> Result<Invoice, Error> {
-        invoice.validate()?;
-
-        let id = Uuid::now_v7().to_string();
-
-        let invoice  = sqlx::query_as!(
-            Invoice,
-            r#"insert into invoices (id, amount) values (?, ?) returning id, amount, created_at, updated_at"#,
-            id,
-            invoice.amount
-            )
-            .fetch_one(executor)
-            .await?;
-
-        Ok(invoice)
-    }
-
-    async fn create_batch(
-        invoices: Vec<InvoiceChangeset>,
-        pool: &SqlitePool,
-    ) -> Result<Vec<Invoice>, Error> {
-        let mut tx = transaction(pool).await?;
-
-        let mut results: Vec<Invoice> = vec![];
-
-        for invoice in invoices {
-            invoice.validate()?;
-
-            let result = Invoice::create(invoice, &mut *tx).await?;
-            results.push(result);
-        }
-
-        tx.commit().await?;
-
-        Ok(results)
-    }
-
-    async fn update<'a>(
-        id: Self::Id,
-        invoice: InvoiceChangeset,
-        executor: impl sqlx::Executor<'_, Database = Sqlite>,
-    ) -> Result<Invoice, Error> {
-        invoice.validate()?;
-
-        let invoice = sqlx::query_as!(
-            Invoice,
-            r#"update invoices set (amount) = (?) where id = ? returning id, amount, created_at, updated_at"#,
-            invoice.amount,
-            id
-        )
-        .fetch_optional(executor)
-        .await?
-        .ok_or(Error::NoRecordFound)?;
-
-        Ok(invoice)
-    }
-
-    async fn delete<'a>(
-        id: Self::Id,
-        executor: impl sqlx::Executor<'_, Database = Sqlite>,
-    ) -> Result<Invoice, Error> {
-        let invoice = sqlx::query_as!(
-            Invoice,
-            r#"delete from invoices where id = ? returning id, amount, created_at, updated_at"#,
-            id
-        )
-        .fetch_optional(executor)
-        .await?
-        .ok_or(Error::NoRecordFound)?;
-
-        Ok(invoice)
-    }
-
-    async fn delete_batch(ids: Vec<Self::Id>, pool: &SqlitePool) -> Result<Vec<Invoice>, Error> {
-        let mut tx = transaction(pool).await?;
-
-        let mut results: Vec<Invoice> = vec![];
-
-        for id in ids {
-            let result = Self::delete(id, &mut *tx).await?;
-            results.push(result);
-        }
-
-        tx.commit().await?;
-
-        Ok(results)
-    }
-}
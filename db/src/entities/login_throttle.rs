@@ -0,0 +1,80 @@
+use std::net::IpAddr;
+
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+use crate::{Db, Error};
+
+/// The failed logins of one email or ip.
+#[derive(Clone, Debug, FromRow)]
+pub struct LoginThrottle {
+    pub throttle_key: String,
+    pub failures: i64,
+    pub locked_until: OffsetDateTime,
+}
+
+impl LoginThrottle {
+    /// The key failed logins for an email are counted under.
+    pub fn email_key(email: &str) -> String {
+        format!("email:{}", email.trim().to_lowercase())
+    }
+
+    /// The key failed logins from an ip are counted under.
+    pub fn ip_key(ip: IpAddr) -> String {
+        format!("ip:{ip}")
+    }
+
+    pub async fn try_get(
+        throttle_key: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Option<LoginThrottle>, Error> {
+        let throttle = sqlx::query_as!(
+            LoginThrottle,
+            r#"SELECT throttle_key as "throttle_key!", failures, locked_until
+            FROM login_throttles WHERE throttle_key = $1"#,
+            throttle_key
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(throttle)
+    }
+
+    /// Stores the failure count and when the next login may be attempted.
+    pub async fn upsert(
+        throttle_key: &str,
+        failures: i64,
+        locked_until: OffsetDateTime,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<LoginThrottle, Error> {
+        let throttle = sqlx::query_as!(
+            LoginThrottle,
+            r#"INSERT INTO login_throttles (throttle_key, failures, locked_until)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (throttle_key)
+            DO UPDATE SET failures = excluded.failures, locked_until = excluded.locked_until
+            RETURNING throttle_key as "throttle_key!", failures, locked_until"#,
+            throttle_key,
+            failures,
+            locked_until
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(throttle)
+    }
+
+    /// Forgets the failed logins, e.g. after a successful login.
+    pub async fn clear(
+        throttle_key: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            r#"DELETE FROM login_throttles WHERE throttle_key = $1"#,
+            throttle_key
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use serde::Deserialize;
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+use crate::{Db, Error, single_use_token};
+
+const TABLE: &str = "magic_link_tokens";
+
+/// A single use link that logs a user in without their password, see [`single_use_token`].
+#[derive(Clone, FromRow)]
+pub struct MagicLinkToken {
+    pub token_hash: String,
+    pub user_id: i64,
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+/// RequestMagicLink is a changeset for asking for a sign in link.
+#[derive(Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "test-helpers", derive(serde::Serialize))]
+pub struct RequestMagicLink {
+    pub email: String,
+}
+
+/// MagicLinkLogin is a changeset for signing in with the token of a link.
+#[derive(Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "test-helpers", derive(serde::Serialize))]
+pub struct MagicLinkLogin {
+    pub login_token: String,
+}
+
+impl MagicLinkToken {
+    /// Consumes a login token, returning the id of the user it was issued to.
+    pub async fn try_consume(
+        login: &MagicLinkLogin,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Option<i64>, Error> {
+        let token: Option<MagicLinkToken> =
+            single_use_token::consume(TABLE, &login.login_token, executor).await?;
+
+        Ok(token.map(|token| token.user_id))
+    }
+
+    /// Issues a login token to a user, returning the token to send them.
+    pub async fn create(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<String, Error> {
+        let login_token = single_use_token::generate();
+        let token_hash = single_use_token::hash(&login_token);
+        sqlx::query!(
+            r#"INSERT INTO magic_link_tokens (token_hash, user_id) VALUES ($1, $2)"#,
+            token_hash,
+            user_id
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(login_token)
+    }
+
+    /// Deletes every token issued to a user, so only the latest link works.
+    pub async fn delete_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        single_use_token::delete_for_user(TABLE, user_id, executor).await
+    }
+}
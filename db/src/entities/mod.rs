@@ -1,6 +1,18 @@
+pub mod account_deletion;
+pub mod api_token;
+pub mod identity;
 pub mod invoices;
+pub mod password_reset_token;
 pub mod register_token;
+pub mod role;
 pub mod session;
 pub mod todo;
+pub mod two_factor;
 pub mod user;
+pub mod user_data;
 pub mod lions;
+pub mod login_throttle;
+pub mod magic_link_token;
+pub mod email_change_token;
+pub mod team;
+pub mod team_invitation;
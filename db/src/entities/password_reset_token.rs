@@ -0,0 +1,76 @@
+use serde::Deserialize;
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+use validator::Validate;
+
+use crate::{Db, Error, single_use_token};
+
+const TABLE: &str = "password_reset_tokens";
+
+/// A link to choose a new password, see [`single_use_token`].
+#[derive(Clone, FromRow)]
+pub struct PasswordResetToken {
+    pub token_hash: String,
+    pub user_id: i64,
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+/// ForgotPassword is a changeset for requesting a password reset link.
+#[derive(Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "test-helpers", derive(serde::Serialize))]
+pub struct ForgotPassword {
+    pub email: String,
+}
+
+/// ResetPassword is a changeset for choosing a new password with a reset token.
+#[derive(Deserialize, Validate, Clone, Debug)]
+#[cfg_attr(feature = "test-helpers", derive(serde::Serialize))]
+pub struct ResetPassword {
+    #[validate(length(min = 1, message = "reset token is missing"))]
+    pub reset_token: String,
+    #[validate(length(min = 8, message = "password must be at least 8 characters"))]
+    pub password: String,
+    #[validate(must_match(other = "password", message = "passwords do not match"))]
+    pub confirm_password: String,
+}
+
+impl PasswordResetToken {
+    /// Consumes a reset token, returning the id of the user it was issued to.
+    ///
+    /// The new password is validated first so a typo doesn't burn the token.
+    pub async fn try_consume(
+        reset_password: &ResetPassword,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Option<i64>, Error> {
+        reset_password.validate()?;
+        let token: Option<PasswordResetToken> =
+            single_use_token::consume(TABLE, &reset_password.reset_token, executor).await?;
+
+        Ok(token.map(|token| token.user_id))
+    }
+
+    /// Issues a reset token to a user, returning the token to send them.
+    pub async fn create(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<String, Error> {
+        let reset_token = single_use_token::generate();
+        let token_hash = single_use_token::hash(&reset_token);
+        sqlx::query!(
+            r#"INSERT INTO password_reset_tokens (token_hash, user_id) VALUES ($1, $2)"#,
+            token_hash,
+            user_id
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(reset_token)
+    }
+
+    /// Deletes every token issued to a user, so only the latest link works.
+    pub async fn delete_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        single_use_token::delete_for_user(TABLE, user_id, executor).await
+    }
+}
@@ -3,10 +3,10 @@ use fake::Dummy;
 
 use rand::Rng as _;
 use serde::Deserialize;
-use sqlx::{Sqlite, prelude::FromRow, types::time::OffsetDateTime};
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
 use validator::Validate;
 
-use crate::Error;
+use crate::{Db, Error};
 
 #[derive(Clone, FromRow)]
 pub struct RegisterToken {
@@ -24,14 +24,27 @@ pub struct RegisterTokenValidate {
     pub register_token: String,
 }
 
+/// ResendRegisterToken is a changeset for requesting a fresh confirmation code.
+#[derive(Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "test-helpers", derive(serde::Serialize))]
+pub struct ResendRegisterToken {
+    pub email: String,
+}
+
 impl RegisterToken {
-    pub async fn try_get_user_id_by_register_token(
+    /// Consumes a register token, returning the id of the user it was issued to.
+    ///
+    /// The token is deleted so it can only be used once. Expired tokens are never
+    /// matched and are left for [`RegisterToken::delete_expired`] to clean up.
+    pub async fn try_consume(
         register_token: RegisterTokenValidate,
-        executor: impl sqlx::Executor<'_, Database = Sqlite>,
+        executor: impl sqlx::Executor<'_, Database = Db>,
     ) -> Result<Option<i64>, Error> {
         register_token.validate()?;
         let maybe_user_id = sqlx::query!(
-            r#"SELECT user_id FROM registration_tokens WHERE register_token = ?
+            r#"DELETE FROM registration_tokens
+            WHERE register_token = $1 AND expires_at > CURRENT_TIMESTAMP
+            RETURNING user_id
 
 "#,
             register_token.register_token
@@ -45,7 +58,7 @@ impl RegisterToken {
 
     pub async fn create<'a>(
         user_id: i64,
-        executor: impl sqlx::Executor<'_, Database = Sqlite>,
+        executor: impl sqlx::Executor<'_, Database = Db>,
     ) -> Result<RegisterToken, Error> {
         let rand_token = generate_register_token();
         let register_token = sqlx::query_as!(
@@ -63,6 +76,37 @@ impl RegisterToken {
 
         Ok(register_token)
     }
+
+    /// Deletes every token issued to a user, e.g. before sending them a new one.
+    pub async fn delete_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        let deleted = sqlx::query!(
+            r#"DELETE FROM registration_tokens WHERE user_id = $1"#,
+            user_id
+        )
+        .execute(executor)
+        .await?
+        .rows_affected();
+
+        Ok(deleted)
+    }
+
+    /// Deletes every token that has expired, returning how many were removed.
+    pub async fn delete_expired(
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        let deleted = sqlx::query!(
+            r#"DELETE FROM registration_tokens
+            WHERE expires_at IS NULL OR expires_at <= CURRENT_TIMESTAMP"#
+        )
+        .execute(executor)
+        .await?
+        .rows_affected();
+
+        Ok(deleted)
+    }
 }
 
 fn generate_register_token() -> String {
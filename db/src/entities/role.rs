@@ -0,0 +1,110 @@
+use serde::Serialize;
+use sqlx::prelude::FromRow;
+
+use crate::{Db, Error, ResultExt};
+
+/// A named set of permissions, users are granted roles rather than single permissions.
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct Role {
+    pub id: i64,
+    pub name: String,
+}
+
+/// Permission is the name of something a user may do, e.g. `todos.write`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(transparent)]
+pub struct Permission(pub String);
+
+impl From<&str> for Permission {
+    fn from(name: &str) -> Self {
+        Self(name.to_string())
+    }
+}
+
+impl From<String> for Permission {
+    fn from(name: String) -> Self {
+        Self(name)
+    }
+}
+
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Role {
+    /// Full access to every resource.
+    pub const ADMIN: &'static str = "admin";
+
+    /// The role every new user is given.
+    pub const MEMBER: &'static str = "member";
+
+    /// Grants the role with the given name to a user, granting it twice is a no-op.
+    pub async fn assign(
+        user_id: i64,
+        name: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            r#"INSERT INTO user_roles (user_id, role_id)
+            SELECT $1, id FROM roles WHERE name = $2
+            ON CONFLICT DO NOTHING"#,
+            user_id,
+            name
+        )
+        .execute(executor)
+        .await
+        .map_constraint_err()?;
+
+        Ok(())
+    }
+
+    /// Loads every permission granted by the roles of a user.
+    pub async fn load_permissions_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Vec<Permission>, Error> {
+        let permissions = sqlx::query_scalar!(
+            r#"SELECT DISTINCT permissions.name FROM permissions
+            JOIN role_permissions ON role_permissions.permission_id = permissions.id
+            JOIN user_roles ON user_roles.role_id = role_permissions.role_id
+            WHERE user_roles.user_id = $1"#,
+            user_id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(permissions.into_iter().map(Permission).collect())
+    }
+
+    /// Loads the names of the roles granted to a user.
+    pub async fn load_names_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Vec<String>, Error> {
+        let names = sqlx::query_scalar!(
+            r#"SELECT roles.name FROM roles
+            JOIN user_roles ON user_roles.role_id = roles.id
+            WHERE user_roles.user_id = $1
+            ORDER BY roles.name"#,
+            user_id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(names)
+    }
+
+    /// Takes every role away from a user.
+    pub async fn revoke_all(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<(), Error> {
+        sqlx::query!(r#"DELETE FROM user_roles WHERE user_id = $1"#, user_id)
+            .execute(executor)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -1,8 +1,123 @@
-use sqlx::prelude::FromRow;
+use serde::Serialize;
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+use crate::{Db, Error};
 
 #[derive(Clone, FromRow, Debug)]
 pub struct Session {
     pub id: String,
     pub data: Vec<u8>,
+    /// Unix timestamp in SQLite, `timestamptz` in Postgres as required by the session store.
+    #[cfg(not(feature = "postgres"))]
     pub expiry_date: i64,
+    #[cfg(feature = "postgres")]
+    pub expiry_date: sqlx::types::time::OffsetDateTime,
+    /// The logged in user, `None` for visitors.
+    pub user_id: Option<i64>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub last_seen_at: Option<OffsetDateTime>,
+}
+
+/// A logged in session of a user as shown on their account page.
+///
+/// The id is the secret the session cookie holds, so it is never serialized.
+#[derive(Clone, FromRow, Debug, Serialize)]
+pub struct ActiveSession {
+    #[serde(skip)]
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_seen_at: Option<OffsetDateTime>,
+}
+
+impl Session {
+    /// Records the user of a session and where it was used from.
+    ///
+    /// Sessions seen less than `since` ago are left alone, so a busy session isn't written to on
+    /// every request.
+    pub async fn track(
+        id: &str,
+        user_id: i64,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+        now: OffsetDateTime,
+        since: OffsetDateTime,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            r#"UPDATE sessions SET user_id = $1, user_agent = $2, ip_address = $3, last_seen_at = $4
+            WHERE id = $5 AND (user_id IS NULL OR user_id <> $1 OR last_seen_at IS NULL OR last_seen_at < $6)"#,
+            user_id,
+            user_agent,
+            ip_address,
+            now,
+            id,
+            since
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Loads the sessions a user is logged in with, most recently used first.
+    ///
+    /// Expired sessions stay in the table until the store deletes them, they are left out.
+    pub async fn load_active_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Vec<ActiveSession>, Error> {
+        #[cfg(not(feature = "postgres"))]
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        #[cfg(feature = "postgres")]
+        let now = OffsetDateTime::now_utc();
+
+        let sessions = sqlx::query_as!(
+            ActiveSession,
+            r#"SELECT id, user_agent, ip_address, last_seen_at FROM sessions
+            WHERE user_id = $1 AND expiry_date > $2 ORDER BY last_seen_at DESC"#,
+            user_id,
+            now
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    /// Ends one of the user's sessions, returns whether there was one to end.
+    pub async fn delete_for_user(
+        id: &str,
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            "DELETE FROM sessions WHERE id = $1 AND user_id = $2",
+            id,
+            user_id
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Ends every session of the user but `except`, returns how many were ended.
+    pub async fn delete_all_for_user(
+        user_id: i64,
+        except: Option<&str>,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        let result = sqlx::query!(
+            "DELETE FROM sessions WHERE user_id = $1 AND (CAST($2 AS TEXT) IS NULL OR id <> $2)",
+            user_id,
+            except
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
 }
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Type, types::time::OffsetDateTime};
+use validator::Validate;
+
+use crate::{Db, Entity, Error, ResultExt};
+
+/// A group of users working together, members join by accepting an invitation.
+#[derive(Serialize, Debug, Deserialize, FromRow, Entity)]
+#[entity(table = "teams", primary_key = "id", changeset = CreateTeam)]
+pub struct Team {
+    pub id: i64,
+    pub name: String,
+    #[entity(read_only)]
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub created_at: Option<OffsetDateTime>,
+}
+
+/// CreateTeam is a changeset for creating or renaming a team.
+#[derive(Deserialize, Validate, Clone, Debug)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct CreateTeam {
+    #[validate(length(min = 1, max = 64, message = "name must be 1 to 64 characters"))]
+    pub name: String,
+}
+
+/// What a member may do in a team, owners invite and remove members.
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, Type)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum TeamRole {
+    Owner,
+    Member,
+}
+
+impl From<String> for TeamRole {
+    fn from(val: String) -> Self {
+        match val.as_str() {
+            "owner" => TeamRole::Owner,
+            _ => TeamRole::Member,
+        }
+    }
+}
+
+/// A team as one of its members sees it, with their role in it.
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct Membership {
+    pub team_id: i64,
+    pub name: String,
+    pub role: TeamRole,
+}
+
+/// A member of a team as listed on the team's page.
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct TeamMember {
+    pub user_id: i64,
+    pub email: String,
+    pub role: TeamRole,
+}
+
+impl Membership {
+    pub fn is_owner(&self) -> bool {
+        self.role == TeamRole::Owner
+    }
+}
+
+impl Team {
+    /// Adds a user to a team, joining a team twice keeps the first role.
+    pub async fn add_member(
+        team_id: i64,
+        user_id: i64,
+        role: TeamRole,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            r#"INSERT INTO team_members (team_id, user_id, role) VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING"#,
+            team_id,
+            user_id,
+            role as _
+        )
+        .execute(executor)
+        .await
+        .map_constraint_err()?;
+
+        Ok(())
+    }
+
+    /// The membership of a user in a team, `None` if they aren't a member.
+    ///
+    /// Pages of a team treat everyone who isn't a member as if the team didn't exist.
+    pub async fn try_get_membership(
+        team_id: i64,
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Option<Membership>, Error> {
+        let membership = sqlx::query_as!(
+            Membership,
+            r#"SELECT teams.id as "team_id!", teams.name, team_members.role FROM teams
+            JOIN team_members ON team_members.team_id = teams.id
+            WHERE teams.id = $1 AND team_members.user_id = $2"#,
+            team_id,
+            user_id
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(membership)
+    }
+
+    /// The teams a user is a member of.
+    pub async fn load_memberships(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Vec<Membership>, Error> {
+        let memberships = sqlx::query_as!(
+            Membership,
+            r#"SELECT teams.id as "team_id!", teams.name, team_members.role FROM teams
+            JOIN team_members ON team_members.team_id = teams.id
+            WHERE team_members.user_id = $1
+            ORDER BY teams.name"#,
+            user_id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(memberships)
+    }
+
+    pub async fn load_members(
+        team_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Vec<TeamMember>, Error> {
+        let members = sqlx::query_as!(
+            TeamMember,
+            r#"SELECT users.id as "user_id!", users.email, team_members.role FROM team_members
+            JOIN users ON users.id = team_members.user_id
+            WHERE team_members.team_id = $1
+            ORDER BY team_members.created_at, users.id"#,
+            team_id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(members)
+    }
+
+    /// Takes a user out of every team they are a member of.
+    pub async fn leave_all(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        let left = sqlx::query!("DELETE FROM team_members WHERE user_id = $1", user_id)
+            .execute(executor)
+            .await?
+            .rows_affected();
+
+        Ok(left)
+    }
+
+    /// Deletes teams without members, e.g. after their last member deleted their account.
+    ///
+    /// Their invitations have to be deleted first, see
+    /// [`super::team_invitation::TeamInvitation::delete_for_empty_teams`].
+    pub async fn delete_empty(
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        let deleted =
+            sqlx::query!("DELETE FROM teams WHERE id NOT IN (SELECT team_id FROM team_members)")
+                .execute(executor)
+                .await?
+                .rows_affected();
+
+        Ok(deleted)
+    }
+}
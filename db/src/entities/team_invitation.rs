@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+use validator::Validate;
+
+use crate::{Db, Error, single_use_token};
+
+const TABLE: &str = "team_invitations";
+
+/// An invitation to join a team, sent as a link to the invitee's email.
+///
+/// Unlike a [`super::register_token::RegisterToken`] it isn't tied to a user, the invitee may not
+/// have an account yet. Whoever accepts it has to be signed in with the email it was sent to.
+/// Only the hash of its token is stored, see [`single_use_token`].
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct TeamInvitation {
+    pub id: i64,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub team_id: i64,
+    pub email: String,
+    pub invited_by: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// InviteMember is a changeset for inviting someone to a team by email.
+#[derive(Deserialize, Validate, Clone, Debug)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct InviteMember {
+    #[validate(email(message = "must be a valid email address"))]
+    pub email: String,
+}
+
+/// AcceptInvitation is a changeset for joining a team with the token of an invitation link.
+#[derive(Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct AcceptInvitation {
+    pub invite_token: String,
+}
+
+impl TeamInvitation {
+    /// Looks up an invitation without using it up, expired invitations are never matched.
+    pub async fn try_get_valid(
+        invite_token: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Option<TeamInvitation>, Error> {
+        let token_hash = single_use_token::hash(invite_token);
+        let invitation = sqlx::query_as!(
+            TeamInvitation,
+            r#"SELECT id as "id!", token_hash, team_id, email, invited_by,
+            expires_at as "expires_at!", created_at as "created_at!"
+            FROM team_invitations
+            WHERE token_hash = $1 AND expires_at > CURRENT_TIMESTAMP"#,
+            token_hash
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(invitation)
+    }
+
+    /// Consumes an invitation, returning it if it was still valid.
+    pub async fn try_consume(
+        accept: &AcceptInvitation,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Option<TeamInvitation>, Error> {
+        single_use_token::consume(TABLE, &accept.invite_token, executor).await
+    }
+
+    /// Invites an email to a team, returning the invitation and the token to send with it.
+    pub async fn create(
+        team_id: i64,
+        invite: &InviteMember,
+        invited_by: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<(TeamInvitation, String), Error> {
+        invite.validate()?;
+        let invite_token = single_use_token::generate();
+        let token_hash = single_use_token::hash(&invite_token);
+        let invitation = sqlx::query_as!(
+            TeamInvitation,
+            r#"INSERT INTO team_invitations (token_hash, team_id, email, invited_by) VALUES (
+                $1, $2, $3, $4
+            ) RETURNING id as "id!", token_hash, team_id, email, invited_by,
+            expires_at as "expires_at!", created_at as "created_at!"
+            "#,
+            token_hash,
+            team_id,
+            invite.email,
+            invited_by
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok((invitation, invite_token))
+    }
+
+    /// The invitations of a team nobody has accepted yet.
+    pub async fn load_pending_for_team(
+        team_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Vec<TeamInvitation>, Error> {
+        let invitations = sqlx::query_as!(
+            TeamInvitation,
+            r#"SELECT id as "id!", token_hash, team_id, email, invited_by,
+            expires_at as "expires_at!", created_at as "created_at!"
+            FROM team_invitations
+            WHERE team_id = $1 AND expires_at > CURRENT_TIMESTAMP
+            ORDER BY created_at, id"#,
+            team_id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(invitations)
+    }
+
+    /// Revokes an invitation of a team, the link stops working. Returns whether there was one.
+    pub async fn revoke(
+        team_id: i64,
+        id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<bool, Error> {
+        let revoked = sqlx::query!(
+            "DELETE FROM team_invitations WHERE team_id = $1 AND id = $2",
+            team_id,
+            id
+        )
+        .execute(executor)
+        .await?
+        .rows_affected();
+
+        Ok(revoked > 0)
+    }
+
+    /// Deletes the invitations of a team sent to an email, so only the latest link works.
+    pub async fn delete_for_email(
+        team_id: i64,
+        email: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        let deleted = sqlx::query!(
+            "DELETE FROM team_invitations WHERE team_id = $1 AND LOWER(email) = LOWER($2)",
+            team_id,
+            email
+        )
+        .execute(executor)
+        .await?
+        .rows_affected();
+
+        Ok(deleted)
+    }
+
+    /// Deletes every invitation a user sent.
+    pub async fn delete_sent_by(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        let deleted = sqlx::query!(
+            "DELETE FROM team_invitations WHERE invited_by = $1",
+            user_id
+        )
+        .execute(executor)
+        .await?
+        .rows_affected();
+
+        Ok(deleted)
+    }
+
+    /// Deletes the invitations of teams without members, before the teams themselves are deleted.
+    pub async fn delete_for_empty_teams(
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        let deleted = sqlx::query!(
+            "DELETE FROM team_invitations WHERE team_id NOT IN (SELECT team_id FROM team_members)"
+        )
+        .execute(executor)
+        .await?
+        .rows_affected();
+
+        Ok(deleted)
+    }
+}
@@ -0,0 +1,186 @@
+use serde::Deserialize;
+use sqlx::prelude::FromRow;
+
+use crate::{Db, Error};
+
+/// The authenticator app of a user.
+///
+/// The secret is stored as soon as enrolment starts, it is only used at login once the user has
+/// proven their app works and the credential is `enabled`.
+#[derive(Clone, FromRow)]
+pub struct TotpCredential {
+    pub user_id: i64,
+    pub secret: String,
+    pub enabled: bool,
+    /// The time step of the last code that was accepted, a code can only be used once.
+    pub last_used_step: Option<i64>,
+}
+
+// Here we've implemented `Debug` manually to avoid accidentally logging the secret.
+impl std::fmt::Debug for TotpCredential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TotpCredential")
+            .field("user_id", &self.user_id)
+            .field("secret", &"[redacted]")
+            .field("enabled", &self.enabled)
+            .field("last_used_step", &self.last_used_step)
+            .finish()
+    }
+}
+
+/// TotpCode is a changeset for submitting a code from an authenticator app or a recovery code.
+#[derive(Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "test-helpers", derive(serde::Serialize))]
+pub struct TotpCode {
+    pub code: String,
+}
+
+impl TotpCredential {
+    pub async fn try_get_by_user_id(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Option<TotpCredential>, Error> {
+        let credential = sqlx::query_as!(
+            TotpCredential,
+            r#"SELECT user_id, secret, enabled, last_used_step FROM totp_credentials WHERE user_id = $1"#,
+            user_id
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(credential)
+    }
+
+    /// Stores a new secret for a user who is enrolling.
+    ///
+    /// Returns `None` when the user already has an enabled credential, which is left untouched.
+    pub async fn try_start_enrolment(
+        user_id: i64,
+        secret: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<Option<TotpCredential>, Error> {
+        let credential = sqlx::query_as!(
+            TotpCredential,
+            r#"INSERT INTO totp_credentials (user_id, secret, enabled) VALUES ($1, $2, FALSE)
+            ON CONFLICT (user_id)
+            DO UPDATE SET secret = excluded.secret WHERE totp_credentials.enabled = FALSE
+            RETURNING user_id, secret, enabled, last_used_step"#,
+            user_id,
+            secret
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(credential)
+    }
+
+    pub async fn enable(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<TotpCredential, Error> {
+        let credential = sqlx::query_as!(
+            TotpCredential,
+            r#"UPDATE totp_credentials SET enabled = TRUE WHERE user_id = $1
+            RETURNING user_id, secret, enabled, last_used_step"#,
+            user_id
+        )
+        .fetch_optional(executor)
+        .await?
+        .ok_or(Error::NoRecordFound)?;
+
+        Ok(credential)
+    }
+
+    /// Records the time step of a code that was just accepted.
+    ///
+    /// Returns `false` when a code of this or a later step was used in the meantime, e.g. by a
+    /// request racing this one, so the code must be refused.
+    pub async fn try_use_step(
+        user_id: i64,
+        step: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<bool, Error> {
+        let updated = sqlx::query!(
+            r#"UPDATE totp_credentials SET last_used_step = $1
+            WHERE user_id = $2 AND (last_used_step IS NULL OR last_used_step < $1)"#,
+            step,
+            user_id
+        )
+        .execute(executor)
+        .await?
+        .rows_affected();
+
+        Ok(updated > 0)
+    }
+
+    pub async fn delete(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            r#"DELETE FROM totp_credentials WHERE user_id = $1"#,
+            user_id
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// A single use code that stands in for the authenticator app, only its hash is stored.
+#[derive(Clone, Debug, FromRow)]
+pub struct RecoveryCode {
+    pub code_hash: String,
+    pub user_id: i64,
+}
+
+impl RecoveryCode {
+    pub async fn create(
+        user_id: i64,
+        code_hash: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<RecoveryCode, Error> {
+        let code = sqlx::query_as!(
+            RecoveryCode,
+            r#"INSERT INTO recovery_codes (code_hash, user_id) VALUES ($1, $2)
+            RETURNING code_hash, user_id"#,
+            code_hash,
+            user_id
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(code)
+    }
+
+    /// Deletes the matching code, returning whether there was one.
+    pub async fn try_consume(
+        user_id: i64,
+        code_hash: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<bool, Error> {
+        let consumed = sqlx::query!(
+            r#"DELETE FROM recovery_codes WHERE user_id = $1 AND code_hash = $2"#,
+            user_id,
+            code_hash
+        )
+        .execute(executor)
+        .await?
+        .rows_affected();
+
+        Ok(consumed > 0)
+    }
+
+    pub async fn delete_for_user(
+        user_id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<u64, Error> {
+        let deleted = sqlx::query!(r#"DELETE FROM recovery_codes WHERE user_id = $1"#, user_id)
+            .execute(executor)
+            .await?
+            .rows_affected();
+
+        Ok(deleted)
+    }
+}
@@ -4,7 +4,7 @@ use argon2::{
 };
 use axum_login::AuthUser;
 use serde::{Deserialize, Serialize};
-use sqlx::{Sqlite, Type, prelude::FromRow};
+use sqlx::{Type, prelude::FromRow};
 use validator::Validate;
 
 #[cfg(feature = "test-helpers")]
@@ -13,7 +13,7 @@ use fake::{
     faker::internet::{en::Password, en::SafeEmail},
 };
 
-use crate::{Error, ResultExt};
+use crate::{Db, Error, ResultExt};
 
 #[derive(Clone, FromRow, Deserialize, Serialize)]
 pub struct User {
@@ -23,8 +23,8 @@ pub struct User {
     pub status: UserStatus,
 }
 
-#[derive(Clone, Deserialize, Serialize, Debug, Type)]
-#[sqlx(type_name = "TEXT")]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, Type)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
 pub enum UserStatus {
     Confirmed,
     Pending,
@@ -109,6 +109,27 @@ pub struct UserCredentials {
     pub next: Option<String>,
 }
 
+/// ChangeEmail is a changeset for moving an account to a new email, the current password
+/// proves it is the owner asking.
+#[derive(Deserialize, Validate, Clone)]
+#[cfg_attr(feature = "test-helpers", derive(serde::Serialize))]
+pub struct ChangeEmail {
+    #[validate(email(message = "Must be a valid email address"))]
+    pub email: String,
+    pub current_password: String,
+}
+
+/// ChangePassword is a changeset for choosing a new password while logged in.
+#[derive(Deserialize, Validate, Clone)]
+#[cfg_attr(feature = "test-helpers", derive(serde::Serialize))]
+pub struct ChangePassword {
+    pub current_password: String,
+    #[validate(length(min = 8, message = "password must be at least 8 characters"))]
+    pub password: String,
+    #[validate(must_match(other = "password", message = "passwords do not match"))]
+    pub confirm_password: String,
+}
+
 /// ------------------------------------------------------------------------
 /// Authentication specific implementations for axum_login.
 /// ------------------------------------------------------------------------
@@ -131,11 +152,11 @@ impl AuthUser for User {
 impl User {
     pub async fn try_get_by_email(
         email: &str,
-        executor: impl sqlx::Executor<'_, Database = Sqlite>,
+        executor: impl sqlx::Executor<'_, Database = Db>,
     ) -> Result<Option<User>, Error> {
         let user = sqlx::query_as!(
             User,
-            r#"select * from users where email = ?
+            r#"select * from users where email = $1
 
 "#,
             email
@@ -147,11 +168,11 @@ impl User {
 
     pub async fn try_get_by_id(
         id: &i64,
-        executor: impl sqlx::Executor<'_, Database = Sqlite>,
+        executor: impl sqlx::Executor<'_, Database = Db>,
     ) -> Result<Option<User>, Error> {
         let user = sqlx::query_as!(
             User,
-            r#"select * from users where id = ?
+            r#"select * from users where id = $1
 
 "#,
             id
@@ -164,7 +185,7 @@ impl User {
 
     pub async fn create(
         user: RegisterUser,
-        executor: impl sqlx::Executor<'_, Database = Sqlite>,
+        executor: impl sqlx::Executor<'_, Database = Db>,
     ) -> Result<User, Error> {
         user.validate()?;
 
@@ -174,13 +195,13 @@ impl User {
             User,
             r#"
             insert into users (email, password_hash, status)
-            values (?, ?, ?)
+            values ($1, $2, $3)
             returning *
 
 "#,
             user.email,
             password_hash,
-            UserStatus::Pending
+            UserStatus::Pending as _
         )
         .fetch_one(executor)
         .await
@@ -192,14 +213,82 @@ impl User {
     pub async fn update_status(
         id: i64,
         status: UserStatus,
-        executor: impl sqlx::Executor<'_, Database = Sqlite>,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<User, Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"update users set status = ($1) where id = ($2) returning *
+
+"#,
+            status as _,
+            id
+        )
+        .fetch_optional(executor)
+        .await?
+        .ok_or(Error::NoRecordFound)?;
+
+        Ok(user)
+    }
+
+    /// Moves the user to a new email, which must not belong to anyone else.
+    pub async fn update_email(
+        id: i64,
+        email: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<User, Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"update users set email = ($1) where id = ($2) returning *
+
+"#,
+            email,
+            id
+        )
+        .fetch_optional(executor)
+        .await
+        .map_constraint_err()?
+        .ok_or(Error::NoRecordFound)?;
+
+        Ok(user)
+    }
+
+    /// Hashes and stores a new password for the user.
+    ///
+    /// Existing sessions stop working because the session auth hash is the password hash.
+    pub async fn update_password(
+        id: i64,
+        password: &str,
+        executor: impl sqlx::Executor<'_, Database = Db>,
+    ) -> Result<User, Error> {
+        let password_hash = generate_password_hash(password)?;
+
+        let user = sqlx::query_as!(
+            User,
+            r#"update users set password_hash = ($1) where id = ($2) returning *
+
+"#,
+            password_hash,
+            id
+        )
+        .fetch_optional(executor)
+        .await?
+        .ok_or(Error::NoRecordFound)?;
+
+        Ok(user)
+    }
+
+    /// Deletes the user row only, records linked to the user have to be deleted first.
+    ///
+    /// Use [`super::user_data::UserData::delete`] to delete an account with everything in it.
+    pub async fn delete(
+        id: i64,
+        executor: impl sqlx::Executor<'_, Database = Db>,
     ) -> Result<User, Error> {
         let user = sqlx::query_as!(
             User,
-            r#"update users set status = (?) where id = (?) returning *
+            r#"delete from users where id = ($1) returning *
 
 "#,
-            status,
             id
         )
         .fetch_optional(executor)
@@ -0,0 +1,118 @@
+//! Everything stored about a user, for exporting and deleting their account.
+//!
+//! Every table linked to a user shows up here twice, in [`UserData::load`] and in
+//! [`UserData::delete`], so a new one has to be added to both.
+
+use serde::Serialize;
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    DbPool, Error, OwnedEntity as _,
+    entities::{
+        account_deletion::AccountDeletion,
+        api_token::ApiToken,
+        email_change_token::EmailChangeToken,
+        identity::Identity,
+        login_throttle::LoginThrottle,
+        magic_link_token::MagicLinkToken,
+        password_reset_token::PasswordResetToken,
+        register_token::RegisterToken,
+        role::Role,
+        session::{ActiveSession, Session},
+        team::{Membership, Team},
+        team_invitation::TeamInvitation,
+        todo::Todo,
+        two_factor::{RecoveryCode, TotpCredential},
+        user::{User, UserStatus},
+    },
+    transaction,
+};
+
+/// The export of an account, serialized as the JSON archive users download.
+///
+/// Secrets are left out: password and token hashes, session ids and the totp secret.
+#[derive(Debug, Serialize)]
+pub struct UserData {
+    #[serde(with = "time::serde::rfc3339")]
+    pub exported_at: OffsetDateTime,
+    pub account: AccountData,
+    pub todos: Vec<Todo>,
+    pub identities: Vec<Identity>,
+    pub api_tokens: Vec<ApiToken>,
+    pub sessions: Vec<ActiveSession>,
+    pub teams: Vec<Membership>,
+}
+
+/// The account itself, see [`UserData`].
+#[derive(Debug, Serialize)]
+pub struct AccountData {
+    pub id: i64,
+    pub email: String,
+    pub status: UserStatus,
+    pub roles: Vec<String>,
+    pub two_factor_enabled: bool,
+    pub deletion: Option<AccountDeletion>,
+}
+
+impl UserData {
+    /// Loads everything linked to the user.
+    pub async fn load(user_id: i64, db_pool: &DbPool) -> Result<UserData, Error> {
+        let user = User::try_get_by_id(&user_id, db_pool)
+            .await?
+            .ok_or(Error::NoRecordFound)?;
+
+        let account = AccountData {
+            id: user.id,
+            email: user.email,
+            status: user.status,
+            roles: Role::load_names_for_user(user_id, db_pool).await?,
+            two_factor_enabled: TotpCredential::try_get_by_user_id(user_id, db_pool)
+                .await?
+                .is_some_and(|credential| credential.enabled),
+            deletion: AccountDeletion::try_get_for_user(user_id, db_pool).await?,
+        };
+
+        Ok(UserData {
+            exported_at: OffsetDateTime::now_utc(),
+            account,
+            todos: Todo::load_all_for_owner(user_id, db_pool).await?,
+            identities: Identity::load_for_user(user_id, db_pool).await?,
+            api_tokens: ApiToken::load_for_user(user_id, db_pool).await?,
+            sessions: Session::load_active_for_user(user_id, db_pool).await?,
+            teams: Team::load_memberships(user_id, db_pool).await?,
+        })
+    }
+
+    /// Deletes the user and everything linked to them in one transaction.
+    ///
+    /// Records owned through an [`crate::OwnedEntity`] are deleted with
+    /// [`crate::OwnedEntity::delete_all_for_owner`]. Deleting the sessions signs the user out
+    /// everywhere. Teams the user was the last member of are deleted with them.
+    pub async fn delete(user_id: i64, db_pool: &DbPool) -> Result<User, Error> {
+        let mut tx = transaction(db_pool).await?;
+
+        Todo::delete_all_for_owner(user_id, &mut *tx).await?;
+        Session::delete_all_for_user(user_id, None, &mut *tx).await?;
+        ApiToken::delete_all_for_user(user_id, &mut *tx).await?;
+        Identity::delete_for_user(user_id, &mut *tx).await?;
+        RecoveryCode::delete_for_user(user_id, &mut *tx).await?;
+        TotpCredential::delete(user_id, &mut *tx).await?;
+        Role::revoke_all(user_id, &mut *tx).await?;
+        RegisterToken::delete_for_user(user_id, &mut *tx).await?;
+        PasswordResetToken::delete_for_user(user_id, &mut *tx).await?;
+        MagicLinkToken::delete_for_user(user_id, &mut *tx).await?;
+        EmailChangeToken::delete_for_user(user_id, &mut *tx).await?;
+        AccountDeletion::cancel(user_id, &mut *tx).await?;
+        TeamInvitation::delete_sent_by(user_id, &mut *tx).await?;
+        Team::leave_all(user_id, &mut *tx).await?;
+        TeamInvitation::delete_for_empty_teams(&mut *tx).await?;
+        Team::delete_empty(&mut *tx).await?;
+
+        let user = User::delete(user_id, &mut *tx).await?;
+        LoginThrottle::clear(&LoginThrottle::email_key(&user.email), &mut *tx).await?;
+
+        tx.commit().await?;
+
+        Ok(user)
+    }
+}
@@ -0,0 +1,513 @@
+//! Paginated, sorted and filtered queries, see [`crate::Entity::load_page`].
+//!
+//! A [`PageRequest`] deserializes straight from a query string so controllers can extract it with
+//! `Query<PageRequest>`:
+//!
+//! ```text
+//! /todos?page=2&per_page=50&sort=-id&description=buy%20milk
+//! ```
+//!
+//! * `page` and `per_page` select a page of records using limit/offset and return a total count.
+//! * `cursor` switches to keyset pagination over the primary key instead. Pass an empty cursor to
+//!   fetch the first page and the returned [`PageMeta::next_cursor`] to fetch the following ones.
+//! * `sort` names the column to order by, prefixed with `-` to sort in descending order.
+//! * Any other parameter is an equality filter on the column of the same name.
+//!
+//! Columns are checked against [`crate::Entity::COLUMNS`] before they end up in a query.
+use std::{collections::BTreeMap, collections::HashMap, fmt, str::FromStr};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, Serializer};
+use sqlx::{Decode, Encode, FromRow, QueryBuilder, Row as _, Type};
+
+use crate::{Db, DbPool, DbRow, Entity, Error};
+
+/// The number of records on a page when `per_page` is not given.
+pub const DEFAULT_PER_PAGE: u32 = 20;
+/// The largest page that can be requested, larger values are capped.
+pub const MAX_PER_PAGE: u32 = 100;
+
+/// The direction of a [`Sort`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl fmt::Display for SortDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortDirection::Asc => write!(f, "asc"),
+            SortDirection::Desc => write!(f, "desc"),
+        }
+    }
+}
+
+/// A column to order records by, written as `column` or `-column` in a query string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sort {
+    pub column: String,
+    pub direction: SortDirection,
+}
+
+impl Sort {
+    pub fn asc(column: &str) -> Self {
+        Self {
+            column: column.to_string(),
+            direction: SortDirection::Asc,
+        }
+    }
+
+    pub fn desc(column: &str) -> Self {
+        Self {
+            column: column.to_string(),
+            direction: SortDirection::Desc,
+        }
+    }
+}
+
+impl FromStr for Sort {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let sort = match s.strip_prefix('-') {
+            Some(column) => Sort::desc(column),
+            None => Sort::asc(s),
+        };
+
+        if sort.column.is_empty() {
+            return Err(Error::InvalidPageRequest("sort needs a column".to_string()));
+        }
+
+        Ok(sort)
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.direction {
+            SortDirection::Asc => write!(f, "{}", self.column),
+            SortDirection::Desc => write!(f, "-{}", self.column),
+        }
+    }
+}
+
+/// Serialized in its query string form so templates can build links with it.
+impl Serialize for Sort {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The page of records requested by a client, usually extracted from the query string.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "HashMap<String, String>")]
+pub struct PageRequest {
+    /// The 1-based page number, ignored when paginating by `cursor`.
+    pub page: u32,
+    pub per_page: u32,
+    /// The primary key of the last record on the previous page, empty for the first page.
+    pub cursor: Option<String>,
+    pub sort: Option<Sort>,
+    /// Column name to value equality filters.
+    pub filters: BTreeMap<String, String>,
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self {
+            page: 1,
+            per_page: DEFAULT_PER_PAGE,
+            cursor: None,
+            sort: None,
+            filters: BTreeMap::new(),
+        }
+    }
+}
+
+impl TryFrom<HashMap<String, String>> for PageRequest {
+    type Error = Error;
+
+    fn try_from(params: HashMap<String, String>) -> Result<Self, Self::Error> {
+        let mut request = PageRequest::default();
+
+        for (key, value) in params {
+            match key.as_str() {
+                "page" => request.page = parse_positive(&key, &value)?,
+                "per_page" => request.per_page = parse_positive(&key, &value)?.min(MAX_PER_PAGE),
+                "cursor" => request.cursor = Some(value),
+                "sort" if value.is_empty() => {}
+                "sort" => request.sort = Some(value.parse()?),
+                // Empty filter inputs in a form are submitted as `column=`, skip them.
+                _ if value.is_empty() => {}
+                _ => {
+                    request.filters.insert(key, value);
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+fn parse_positive(key: &str, value: &str) -> Result<u32, Error> {
+    match value.parse::<u32>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(Error::InvalidPageRequest(format!(
+            "{key} must be a positive number"
+        ))),
+    }
+}
+
+impl PageRequest {
+    /// Makes sure only known columns are sorted and filtered on.
+    fn check_columns(&self, columns: &[&str]) -> Result<(), Error> {
+        let unknown = self
+            .sort
+            .iter()
+            .map(|sort| &sort.column)
+            .chain(self.filters.keys())
+            .find(|column| !columns.contains(&column.as_str()));
+
+        match unknown {
+            Some(column) => Err(Error::InvalidPageRequest(format!(
+                "unknown column: {column}"
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A page of records returned by [`crate::Entity::load_page`].
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(rename = "{T}Page")]
+pub struct Page<T> {
+    pub records: Vec<T>,
+    #[serde(flatten)]
+    pub meta: PageMeta,
+}
+
+/// Everything about a [`Page`] apart from its records, e.g. to render pagination links.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PageMeta {
+    pub per_page: u32,
+    /// The column the records are ordered by, prefixed with `-` in descending order.
+    #[schemars(with = "Option<String>")]
+    pub sort: Option<Sort>,
+    pub filters: BTreeMap<String, String>,
+    /// Set when paginating by page number.
+    pub page: Option<u32>,
+    pub total: Option<i64>,
+    pub total_pages: Option<i64>,
+    pub previous_page: Option<u32>,
+    pub next_page: Option<u32>,
+    /// Set when paginating by cursor and there are more records to load.
+    pub next_cursor: Option<String>,
+}
+
+impl PageMeta {
+    fn new(request: &PageRequest) -> Self {
+        Self {
+            per_page: request.per_page,
+            sort: request.sort.clone(),
+            filters: request.filters.clone(),
+            page: None,
+            total: None,
+            total_pages: None,
+            previous_page: None,
+            next_page: None,
+            next_cursor: None,
+        }
+    }
+}
+
+/// Restricts a page to the records of one user, see [`crate::OwnedEntity::load_page_for_owner`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Owner {
+    pub column: &'static str,
+    pub id: i64,
+}
+
+/// Loads a page of `E`s, see [`crate::Entity::load_page`].
+pub(crate) async fn load_page<E, R>(
+    request: &PageRequest,
+    owner: Option<Owner>,
+    db_pool: &DbPool,
+) -> Result<Page<R>, Error>
+where
+    E: Entity + ?Sized,
+    E::Id: FromStr + fmt::Display + for<'r> Decode<'r, Db> + for<'q> Encode<'q, Db> + Type<Db>,
+    E::Id: Send + 'static,
+    R: for<'r> FromRow<'r, DbRow> + Send + Unpin,
+{
+    request.check_columns(E::COLUMNS)?;
+
+    match request.cursor.as_deref() {
+        Some(cursor) => load_cursor_page::<E, R>(request, cursor, owner, db_pool).await,
+        None => load_numbered_page::<E, R>(request, owner, db_pool).await,
+    }
+}
+
+async fn load_numbered_page<E, R>(
+    request: &PageRequest,
+    owner: Option<Owner>,
+    db_pool: &DbPool,
+) -> Result<Page<R>, Error>
+where
+    E: Entity + ?Sized,
+    R: for<'r> FromRow<'r, DbRow> + Send + Unpin,
+{
+    let mut count = QueryBuilder::<Db>::new(format!("select count(*) from {}", E::TABLE));
+    push_filters(&mut count, owner, &request.filters);
+    let total: i64 = count.build_query_scalar().fetch_one(db_pool).await?;
+
+    let sort = request
+        .sort
+        .clone()
+        .unwrap_or_else(|| Sort::asc(E::PRIMARY_KEY));
+
+    let mut select = select(E::TABLE, E::COLUMNS);
+    push_filters(&mut select, owner, &request.filters);
+    select.push(format!(" order by {} {}", sort.column, sort.direction));
+    if sort.column != E::PRIMARY_KEY {
+        // Break ties so rows with equal values don't move between pages.
+        select.push(format!(", {}", E::PRIMARY_KEY));
+    }
+    select
+        .push(" limit ")
+        .push_bind(i64::from(request.per_page))
+        .push(" offset ")
+        .push_bind(i64::from(request.page - 1) * i64::from(request.per_page));
+
+    let records = select.build_query_as::<R>().fetch_all(db_pool).await?;
+
+    let total_pages = (total + i64::from(request.per_page) - 1) / i64::from(request.per_page);
+
+    Ok(Page {
+        records,
+        meta: PageMeta {
+            page: Some(request.page),
+            total: Some(total),
+            total_pages: Some(total_pages),
+            previous_page: (request.page > 1).then(|| request.page - 1),
+            next_page: (i64::from(request.page) < total_pages).then(|| request.page + 1),
+            ..PageMeta::new(request)
+        },
+    })
+}
+
+async fn load_cursor_page<E, R>(
+    request: &PageRequest,
+    cursor: &str,
+    owner: Option<Owner>,
+    db_pool: &DbPool,
+) -> Result<Page<R>, Error>
+where
+    E: Entity + ?Sized,
+    E::Id: FromStr + fmt::Display + for<'r> Decode<'r, Db> + for<'q> Encode<'q, Db> + Type<Db>,
+    E::Id: Send + 'static,
+    R: for<'r> FromRow<'r, DbRow> + Send + Unpin,
+{
+    let direction = match &request.sort {
+        None => SortDirection::Asc,
+        Some(sort) if sort.column == E::PRIMARY_KEY => sort.direction,
+        Some(_) => {
+            return Err(Error::InvalidPageRequest(format!(
+                "cursor pagination can only be sorted by {}",
+                E::PRIMARY_KEY
+            )));
+        }
+    };
+
+    let mut select = select(E::TABLE, E::COLUMNS);
+    let filtered = push_filters(&mut select, owner, &request.filters);
+    if !cursor.is_empty() {
+        let after: E::Id = cursor
+            .parse()
+            .map_err(|_| Error::InvalidPageRequest(format!("invalid cursor: {cursor}")))?;
+
+        select.push(if filtered { " and " } else { " where " });
+        select.push(E::PRIMARY_KEY);
+        select.push(match direction {
+            SortDirection::Asc => " > ",
+            SortDirection::Desc => " < ",
+        });
+        select.push_bind(after);
+    }
+    // Fetch one extra row to find out whether there is a next page.
+    select
+        .push(format!(" order by {} {}", E::PRIMARY_KEY, direction))
+        .push(" limit ")
+        .push_bind(i64::from(request.per_page) + 1);
+
+    let mut rows = select.build().fetch_all(db_pool).await?;
+
+    let next_cursor = if rows.len() > request.per_page as usize {
+        rows.truncate(request.per_page as usize);
+        rows.last()
+            .map(|row| row.try_get::<E::Id, _>(E::PRIMARY_KEY))
+            .transpose()?
+            .map(|id| id.to_string())
+    } else {
+        None
+    };
+
+    let records = rows.iter().map(R::from_row).collect::<Result<_, _>>()?;
+
+    Ok(Page {
+        records,
+        meta: PageMeta {
+            next_cursor,
+            ..PageMeta::new(request)
+        },
+    })
+}
+
+fn select<'args>(table: &str, columns: &[&str]) -> QueryBuilder<'args, Db> {
+    QueryBuilder::new(format!("select {} from {}", columns.join(", "), table))
+}
+
+/// Filters compare the column as text, the way the values arrive in the query string.
+///
+/// The owner is always applied, whatever the filters. Returns whether a `where` clause was added.
+fn push_filters(
+    query: &mut QueryBuilder<'_, Db>,
+    owner: Option<Owner>,
+    filters: &BTreeMap<String, String>,
+) -> bool {
+    let mut clause = " where ";
+    if let Some(owner) = owner {
+        query.push(format!("{clause}{} = ", owner.column));
+        query.push_bind(owner.id);
+        clause = " and ";
+    }
+    for (column, value) in filters {
+        query.push(format!("{clause}cast({column} as text) = "));
+        query.push_bind(value.clone());
+        clause = " and ";
+    }
+
+    clause == " and "
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        MIGRATOR,
+        entities::todo::{Todo, TodoChangeset},
+    };
+
+    fn page_request(params: &[(&str, &str)]) -> Result<PageRequest, Error> {
+        let params = params
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        PageRequest::try_from(params)
+    }
+
+    async fn create_todos(descriptions: &[&str], pool: &DbPool) {
+        let todos = descriptions
+            .iter()
+            .map(|description| TodoChangeset {
+                description: description.to_string(),
+            })
+            .collect();
+
+        Todo::create_batch(todos, pool).await.unwrap();
+    }
+
+    fn descriptions(page: &Page<Todo>) -> Vec<&str> {
+        page.records
+            .iter()
+            .map(|todo| todo.description.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn page_request_is_parsed_from_query_params() {
+        let request = page_request(&[
+            ("page", "3"),
+            ("per_page", "500"),
+            ("sort", "-description"),
+            ("description", "buy milk"),
+            ("id", ""),
+        ])
+        .unwrap();
+
+        assert_eq!(request.page, 3);
+        assert_eq!(request.per_page, MAX_PER_PAGE);
+        assert_eq!(request.sort, Some(Sort::desc("description")));
+        assert_eq!(
+            request.filters,
+            BTreeMap::from([("description".to_string(), "buy milk".to_string())])
+        );
+        assert_eq!(page_request(&[]).unwrap(), PageRequest::default());
+    }
+
+    #[test]
+    fn page_request_rejects_invalid_numbers() {
+        assert!(page_request(&[("page", "0")]).is_err());
+        assert!(page_request(&[("per_page", "lots")]).is_err());
+        assert!(page_request(&[("sort", "-")]).is_err());
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn load_page_returns_the_requested_page_and_total(pool: DbPool) {
+        create_todos(&["a", "b", "c", "d", "e"], &pool).await;
+
+        let request = page_request(&[("page", "2"), ("per_page", "2")]).unwrap();
+        let page = Todo::load_page(&request, &pool).await.unwrap();
+
+        assert_eq!(descriptions(&page), vec!["c", "d"]);
+        assert_eq!(page.meta.total, Some(5));
+        assert_eq!(page.meta.total_pages, Some(3));
+        assert_eq!(page.meta.previous_page, Some(1));
+        assert_eq!(page.meta.next_page, Some(3));
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn load_page_sorts_and_filters(pool: DbPool) {
+        create_todos(&["b", "a", "c", "a"], &pool).await;
+
+        let request = page_request(&[("sort", "-description")]).unwrap();
+        let page = Todo::load_page(&request, &pool).await.unwrap();
+        assert_eq!(descriptions(&page), vec!["c", "b", "a", "a"]);
+
+        let request = page_request(&[("description", "a")]).unwrap();
+        let page = Todo::load_page(&request, &pool).await.unwrap();
+        assert_eq!(descriptions(&page), vec!["a", "a"]);
+        assert_eq!(page.meta.total, Some(2));
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn load_page_follows_cursors_to_the_last_page(pool: DbPool) {
+        create_todos(&["a", "b", "c"], &pool).await;
+
+        let request = page_request(&[("cursor", ""), ("per_page", "2")]).unwrap();
+        let page = Todo::load_page(&request, &pool).await.unwrap();
+        assert_eq!(descriptions(&page), vec!["a", "b"]);
+
+        let cursor = page.meta.next_cursor.expect("there should be a next page");
+        let request = page_request(&[("cursor", &cursor), ("per_page", "2")]).unwrap();
+        let page = Todo::load_page(&request, &pool).await.unwrap();
+        assert_eq!(descriptions(&page), vec!["c"]);
+        assert_eq!(page.meta.next_cursor, None);
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn load_page_rejects_unknown_columns(pool: DbPool) {
+        for params in [[("sort", "password")], [("password", "secret")]] {
+            let request = page_request(&params).unwrap();
+
+            match Todo::load_page(&request, &pool).await {
+                Err(Error::InvalidPageRequest(reason)) => {
+                    assert_eq!(reason, "unknown column: password")
+                }
+                other => panic!("expected an invalid page request, got {:?}", other.err()),
+            }
+        }
+    }
+}
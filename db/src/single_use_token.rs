@@ -0,0 +1,68 @@
+//! Tokens that are emailed to a user as a link, e.g. to reset their password or to sign in.
+//!
+//! A token is as good as a password while it lasts, so only its SHA-256 hash is stored and a
+//! leaked copy of the database can't be used to follow the links. The tables of password reset,
+//! magic link, email change and team invitation tokens have a `token_hash` and an `expires_at`
+//! column: a token is deleted when it is used so the link works once, and expired tokens are
+//! never matched. Registration codes are typed rather than clicked and don't go through here.
+use rand::Rng as _;
+use sha2::{Digest as _, Sha256};
+use sqlx::FromRow;
+
+use crate::{Db, DbRow, Error};
+
+/// Links are clicked rather than typed, so tokens can be long.
+const TOKEN_LENGTH: usize = 32;
+
+/// Generates a new random token to send to a user, store it with [`hash`].
+pub fn generate() -> String {
+    rand::rng()
+        .sample_iter(rand::distr::Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// The hash a token is stored and looked up by.
+pub fn hash(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Deletes the unexpired token of `table` matching `token`, returning its row.
+pub async fn consume<T>(
+    table: &'static str,
+    token: &str,
+    executor: impl sqlx::Executor<'_, Database = Db>,
+) -> Result<Option<T>, Error>
+where
+    T: for<'r> FromRow<'r, DbRow> + Send + Unpin,
+{
+    let sql = format!(
+        "DELETE FROM {table} WHERE token_hash = $1 AND expires_at > CURRENT_TIMESTAMP RETURNING *"
+    );
+    let row = sqlx::query_as::<_, T>(&sql)
+        .bind(hash(token))
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(row)
+}
+
+/// Deletes every token of `table` issued to a user, so only the latest link works.
+pub async fn delete_for_user(
+    table: &'static str,
+    user_id: i64,
+    executor: impl sqlx::Executor<'_, Database = Db>,
+) -> Result<u64, Error> {
+    let sql = format!("DELETE FROM {table} WHERE user_id = $1");
+    let deleted = sqlx::query(&sql)
+        .bind(user_id)
+        .execute(executor)
+        .await?
+        .rows_affected();
+
+    Ok(deleted)
+}
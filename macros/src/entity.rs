@@ -0,0 +1,662 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, Ident, LitStr, Path, Type, spanned::Spanned as _};
+
+/// The `#[entity(...)]` attributes on the struct.
+struct EntityAttrs {
+    table: String,
+    primary_key: String,
+    changeset: Path,
+    /// The column holding the id of the user owning a record, see `shipwright_db::OwnedEntity`.
+    owner: Option<String>,
+}
+
+impl EntityAttrs {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut table = None;
+        let mut primary_key = None;
+        let mut changeset = None;
+        let mut owner = None;
+
+        for attr in input.attrs.iter().filter(|a| a.path().is_ident("entity")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("table") {
+                    table = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("primary_key") {
+                    primary_key = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("changeset") {
+                    changeset = Some(meta.value()?.parse::<Path>()?);
+                } else if meta.path.is_ident("owner") {
+                    owner = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else {
+                    return Err(meta.error(
+                        "unknown entity attribute, expected `table`, `primary_key`, `changeset` or `owner`",
+                    ));
+                }
+                Ok(())
+            })?;
+        }
+
+        let table = table.ok_or_else(|| {
+            syn::Error::new(
+                input.ident.span(),
+                "missing the table name, add #[entity(table = \"...\")]",
+            )
+        })?;
+
+        Ok(Self {
+            table,
+            primary_key: primary_key.unwrap_or_else(|| "id".to_string()),
+            changeset: changeset
+                .unwrap_or_else(|| format_ident!("{}Changeset", input.ident).into()),
+            owner,
+        })
+    }
+}
+
+/// A field of the struct and the column it maps to.
+struct Column {
+    ident: Ident,
+    ty: Type,
+    read_only: bool,
+}
+
+impl Column {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut read_only = false;
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("entity")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("read_only") {
+                    read_only = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown entity attribute, expected `read_only`"))
+                }
+            })?;
+        }
+
+        Ok(Self {
+            ident: field.ident.clone().expect("named fields have an ident"),
+            ty: field.ty.clone(),
+            read_only,
+        })
+    }
+}
+
+/// The SQL of every query the derived implementation runs.
+#[derive(Debug, PartialEq)]
+struct Queries {
+    load_all: String,
+    load: String,
+    create: String,
+    update: String,
+    delete: String,
+}
+
+impl Queries {
+    /// `generated_key` is set when the primary key is bound on insert rather than left for the
+    /// database to generate.
+    fn new(
+        table: &str,
+        primary_key: &str,
+        columns: &[String],
+        writable: &[String],
+        generated_key: bool,
+    ) -> Self {
+        let selected = selected(primary_key, columns);
+        let create = insert(table, primary_key, None, writable, generated_key, &selected);
+        let assignments = assignments(writable);
+
+        Self {
+            load_all: format!("select {selected} from {table}"),
+            load: format!("select {selected} from {table} where {primary_key} = $1"),
+            create,
+            update: format!(
+                "update {table} set {assignments} where {primary_key} = ${} returning {selected}",
+                writable.len() + 1
+            ),
+            delete: format!("delete from {table} where {primary_key} = $1 returning {selected}"),
+        }
+    }
+}
+
+/// The SQL of the queries scoped to the owner of a record, see `shipwright_db::OwnedEntity`.
+///
+/// The owner is always bound right after the primary key, or first when inserting.
+#[derive(Debug, PartialEq)]
+struct OwnedQueries {
+    load_all: String,
+    load: String,
+    create: String,
+    update: String,
+    delete: String,
+    delete_all: String,
+}
+
+impl OwnedQueries {
+    fn new(
+        table: &str,
+        primary_key: &str,
+        owner: &str,
+        columns: &[String],
+        writable: &[String],
+        generated_key: bool,
+    ) -> Self {
+        let selected = selected(primary_key, columns);
+        let create = insert(
+            table,
+            primary_key,
+            Some(owner),
+            writable,
+            generated_key,
+            &selected,
+        );
+        let assignments = assignments(writable);
+
+        Self {
+            load_all: format!(
+                "select {selected} from {table} where {owner} = $1 order by {primary_key}"
+            ),
+            load: format!(
+                "select {selected} from {table} where {primary_key} = $1 and {owner} = $2"
+            ),
+            create,
+            update: format!(
+                "update {table} set {assignments} where {primary_key} = ${} and {owner} = ${} returning {selected}",
+                writable.len() + 1,
+                writable.len() + 2
+            ),
+            delete: format!(
+                "delete from {table} where {primary_key} = $1 and {owner} = $2 returning {selected}"
+            ),
+            delete_all: format!("delete from {table} where {owner} = $1"),
+        }
+    }
+}
+
+fn selected(primary_key: &str, columns: &[String]) -> String {
+    // Primary keys are never null, tell sqlx so even if the column isn't declared `not null`.
+    columns
+        .iter()
+        .map(|column| match column == primary_key {
+            true => format!(r#"{column} as "{column}!""#),
+            false => column.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn insert(
+    table: &str,
+    primary_key: &str,
+    owner: Option<&str>,
+    writable: &[String],
+    generated_key: bool,
+    selected: &str,
+) -> String {
+    let inserted = generated_key
+        .then_some(primary_key)
+        .into_iter()
+        .chain(owner)
+        .chain(writable.iter().map(String::as_str))
+        .collect::<Vec<_>>();
+
+    format!(
+        "insert into {table} ({}) values ({}) returning {selected}",
+        inserted.join(", "),
+        placeholders(1..=inserted.len()).join(", "),
+    )
+}
+
+fn assignments(writable: &[String]) -> String {
+    writable
+        .iter()
+        .zip(placeholders(1..=writable.len()))
+        .map(|(column, placeholder)| format!("{column} = {placeholder}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn placeholders(range: std::ops::RangeInclusive<usize>) -> Vec<String> {
+    range.map(|n| format!("${n}")).collect()
+}
+
+fn is_string(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "String"),
+        _ => false,
+    }
+}
+
+pub(crate) fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let attrs = EntityAttrs::parse(&input)?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "Entity can only be derived for structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "Entity can only be derived for structs",
+            ));
+        }
+    };
+    let columns = fields
+        .iter()
+        .map(Column::parse)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let primary_key = columns
+        .iter()
+        .find(|column| column.ident == attrs.primary_key)
+        .ok_or_else(|| {
+            syn::Error::new(
+                input.ident.span(),
+                format!(
+                    "no field named `{}`, set the primary key with #[entity(primary_key = \"...\")]",
+                    attrs.primary_key
+                ),
+            )
+        })?;
+    if let Some(owner) = &attrs.owner
+        && !columns.iter().any(|column| column.ident == owner)
+    {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            format!("no field named `{owner}` to hold the owner of a record"),
+        ));
+    }
+    // The owner is only ever set from the user a record is created for, never from the changeset.
+    let writable = columns
+        .iter()
+        .filter(|column| column.ident != attrs.primary_key && !column.read_only)
+        .filter(|column| {
+            attrs
+                .owner
+                .as_ref()
+                .is_none_or(|owner| column.ident != owner)
+        })
+        .map(|column| &column.ident)
+        .collect::<Vec<_>>();
+
+    if writable.is_empty() {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "Entity needs at least one field that is written from the changeset",
+        ));
+    }
+
+    let generated_key = is_string(&primary_key.ty);
+    let column_names = columns
+        .iter()
+        .map(|column| column.ident.to_string())
+        .collect::<Vec<_>>();
+    let writable_names = writable.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+    let Queries {
+        load_all,
+        load,
+        create,
+        update,
+        delete,
+    } = Queries::new(
+        &attrs.table,
+        &attrs.primary_key,
+        &column_names,
+        &writable_names,
+        generated_key,
+    );
+
+    let name = &input.ident;
+    let id_ty = &primary_key.ty;
+    let changeset = &attrs.changeset;
+    let table = &attrs.table;
+    let primary_key = &attrs.primary_key;
+
+    let (generate_key, key_arg) = match generated_key {
+        true => (
+            quote! { let id = ::uuid::Uuid::now_v7().to_string(); },
+            quote! { id, },
+        ),
+        false => (quote! {}, quote! {}),
+    };
+
+    let owned_impl = attrs.owner.as_ref().map(|owner| {
+        let OwnedQueries {
+            load_all,
+            load,
+            create,
+            update,
+            delete,
+            delete_all,
+        } = OwnedQueries::new(
+            table,
+            primary_key,
+            owner,
+            &column_names,
+            &writable_names,
+            generated_key,
+        );
+
+        quote! {
+            #[::async_trait::async_trait]
+            impl ::shipwright_db::OwnedEntity for #name {
+                const OWNER: &'static str = #owner;
+
+                async fn load_all_for_owner<'a>(
+                    owner_id: i64,
+                    executor: impl ::sqlx::Executor<'_, Database = ::shipwright_db::Db>,
+                ) -> Result<Vec<#name>, ::shipwright_db::Error> {
+                    let records = ::sqlx::query_as!(#name, #load_all, owner_id)
+                        .fetch_all(executor)
+                        .await?;
+
+                    Ok(records)
+                }
+
+                async fn load_for_owner<'a>(
+                    id: Self::Id,
+                    owner_id: i64,
+                    executor: impl ::sqlx::Executor<'_, Database = ::shipwright_db::Db>,
+                ) -> Result<#name, ::shipwright_db::Error> {
+                    let record = ::sqlx::query_as!(#name, #load, id, owner_id)
+                        .fetch_optional(executor)
+                        .await?
+                        .ok_or(::shipwright_db::Error::NoRecordFound)?;
+
+                    Ok(record)
+                }
+
+                async fn create_for_owner<'a>(
+                    owner_id: i64,
+                    record: #changeset,
+                    executor: impl ::sqlx::Executor<'_, Database = ::shipwright_db::Db>,
+                ) -> Result<#name, ::shipwright_db::Error> {
+                    ::validator::Validate::validate(&record)?;
+
+                    #generate_key
+
+                    let record = ::sqlx::query_as!(#name, #create, #key_arg owner_id, #(record.#writable),*)
+                        .fetch_one(executor)
+                        .await?;
+
+                    Ok(record)
+                }
+
+                async fn create_batch_for_owner(
+                    owner_id: i64,
+                    records: Vec<#changeset>,
+                    db_pool: &::shipwright_db::DbPool,
+                ) -> Result<Vec<#name>, ::shipwright_db::Error> {
+                    let mut tx = ::shipwright_db::transaction(db_pool).await?;
+
+                    let mut results: Vec<#name> = vec![];
+
+                    for record in records {
+                        let result = Self::create_for_owner(owner_id, record, &mut *tx).await?;
+                        results.push(result);
+                    }
+
+                    tx.commit().await?;
+
+                    Ok(results)
+                }
+
+                async fn update_for_owner<'a>(
+                    id: Self::Id,
+                    owner_id: i64,
+                    record: #changeset,
+                    executor: impl ::sqlx::Executor<'_, Database = ::shipwright_db::Db>,
+                ) -> Result<#name, ::shipwright_db::Error> {
+                    ::validator::Validate::validate(&record)?;
+
+                    let record = ::sqlx::query_as!(#name, #update, #(record.#writable,)* id, owner_id)
+                        .fetch_optional(executor)
+                        .await?
+                        .ok_or(::shipwright_db::Error::NoRecordFound)?;
+
+                    Ok(record)
+                }
+
+                async fn delete_for_owner<'a>(
+                    id: Self::Id,
+                    owner_id: i64,
+                    executor: impl ::sqlx::Executor<'_, Database = ::shipwright_db::Db>,
+                ) -> Result<#name, ::shipwright_db::Error> {
+                    let record = ::sqlx::query_as!(#name, #delete, id, owner_id)
+                        .fetch_optional(executor)
+                        .await?
+                        .ok_or(::shipwright_db::Error::NoRecordFound)?;
+
+                    Ok(record)
+                }
+
+                async fn delete_all_for_owner<'a>(
+                    owner_id: i64,
+                    executor: impl ::sqlx::Executor<'_, Database = ::shipwright_db::Db>,
+                ) -> Result<u64, ::shipwright_db::Error> {
+                    let deleted = ::sqlx::query!(#delete_all, owner_id)
+                        .execute(executor)
+                        .await?
+                        .rows_affected();
+
+                    Ok(deleted)
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        #[::async_trait::async_trait]
+        impl ::shipwright_db::Entity for #name {
+            type Id = #id_ty;
+
+            type Record<'a> = #name;
+
+            type Changeset = #changeset;
+
+            const TABLE: &'static str = #table;
+
+            const COLUMNS: &'static [&'static str] = &[#(#column_names),*];
+
+            const PRIMARY_KEY: &'static str = #primary_key;
+
+            async fn load_all<'a>(
+                executor: impl ::sqlx::Executor<'_, Database = ::shipwright_db::Db>,
+            ) -> Result<Vec<#name>, ::shipwright_db::Error> {
+                let records = ::sqlx::query_as!(#name, #load_all)
+                    .fetch_all(executor)
+                    .await?;
+
+                Ok(records)
+            }
+
+            async fn load<'a>(
+                id: Self::Id,
+                executor: impl ::sqlx::Executor<'_, Database = ::shipwright_db::Db>,
+            ) -> Result<#name, ::shipwright_db::Error> {
+                let record = ::sqlx::query_as!(#name, #load, id)
+                    .fetch_optional(executor)
+                    .await?
+                    .ok_or(::shipwright_db::Error::NoRecordFound)?;
+
+                Ok(record)
+            }
+
+            async fn create<'a>(
+                record: #changeset,
+                executor: impl ::sqlx::Executor<'_, Database = ::shipwright_db::Db>,
+            ) -> Result<#name, ::shipwright_db::Error> {
+                ::validator::Validate::validate(&record)?;
+
+                #generate_key
+
+                let record = ::sqlx::query_as!(#name, #create, #key_arg #(record.#writable),*)
+                    .fetch_one(executor)
+                    .await?;
+
+                Ok(record)
+            }
+
+            async fn create_batch(
+                records: Vec<#changeset>,
+                db_pool: &::shipwright_db::DbPool,
+            ) -> Result<Vec<#name>, ::shipwright_db::Error> {
+                let mut tx = ::shipwright_db::transaction(db_pool).await?;
+
+                let mut results: Vec<#name> = vec![];
+
+                for record in records {
+                    let result = Self::create(record, &mut *tx).await?;
+                    results.push(result);
+                }
+
+                tx.commit().await?;
+
+                Ok(results)
+            }
+
+            async fn update<'a>(
+                id: Self::Id,
+                record: #changeset,
+                executor: impl ::sqlx::Executor<'_, Database = ::shipwright_db::Db>,
+            ) -> Result<#name, ::shipwright_db::Error> {
+                ::validator::Validate::validate(&record)?;
+
+                let record = ::sqlx::query_as!(#name, #update, #(record.#writable,)* id)
+                    .fetch_optional(executor)
+                    .await?
+                    .ok_or(::shipwright_db::Error::NoRecordFound)?;
+
+                Ok(record)
+            }
+
+            async fn delete<'a>(
+                id: Self::Id,
+                executor: impl ::sqlx::Executor<'_, Database = ::shipwright_db::Db>,
+            ) -> Result<#name, ::shipwright_db::Error> {
+                let record = ::sqlx::query_as!(#name, #delete, id)
+                    .fetch_optional(executor)
+                    .await?
+                    .ok_or(::shipwright_db::Error::NoRecordFound)?;
+
+                Ok(record)
+            }
+
+            async fn delete_batch(
+                ids: Vec<Self::Id>,
+                db_pool: &::shipwright_db::DbPool,
+            ) -> Result<Vec<#name>, ::shipwright_db::Error> {
+                let mut tx = ::shipwright_db::transaction(db_pool).await?;
+
+                let mut results: Vec<#name> = vec![];
+
+                for id in ids {
+                    let result = Self::delete(id, &mut *tx).await?;
+                    results.push(result);
+                }
+
+                tx.commit().await?;
+
+                Ok(results)
+            }
+        }
+
+        #owned_impl
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn queries_bind_the_generated_key_first() {
+        let queries = Queries::new(
+            "invoices",
+            "id",
+            &strings(&["id", "amount", "created_at"]),
+            &strings(&["amount"]),
+            true,
+        );
+
+        assert_eq!(
+            queries,
+            Queries {
+                load_all: r#"select id as "id!", amount, created_at from invoices"#.to_string(),
+                load: r#"select id as "id!", amount, created_at from invoices where id = $1"#
+                    .to_string(),
+                create: r#"insert into invoices (id, amount) values ($1, $2) returning id as "id!", amount, created_at"#
+                    .to_string(),
+                update: r#"update invoices set amount = $1 where id = $2 returning id as "id!", amount, created_at"#
+                    .to_string(),
+                delete: r#"delete from invoices where id = $1 returning id as "id!", amount, created_at"#
+                    .to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn queries_leave_database_keys_out_of_inserts() {
+        let queries = Queries::new(
+            "todos",
+            "id",
+            &strings(&["id", "description", "done"]),
+            &strings(&["description", "done"]),
+            false,
+        );
+
+        assert_eq!(
+            queries.create,
+            r#"insert into todos (description, done) values ($1, $2) returning id as "id!", description, done"#
+        );
+        assert_eq!(
+            queries.update,
+            r#"update todos set description = $1, done = $2 where id = $3 returning id as "id!", description, done"#
+        );
+    }
+
+    #[test]
+    fn owned_queries_bind_the_owner_after_the_key() {
+        let queries = OwnedQueries::new(
+            "todos",
+            "id",
+            "user_id",
+            &strings(&["id", "user_id", "description"]),
+            &strings(&["description"]),
+            false,
+        );
+
+        assert_eq!(
+            queries,
+            OwnedQueries {
+                load_all: r#"select id as "id!", user_id, description from todos where user_id = $1 order by id"#
+                    .to_string(),
+                load: r#"select id as "id!", user_id, description from todos where id = $1 and user_id = $2"#
+                    .to_string(),
+                create: r#"insert into todos (user_id, description) values ($1, $2) returning id as "id!", user_id, description"#
+                    .to_string(),
+                update: r#"update todos set description = $1 where id = $2 and user_id = $3 returning id as "id!", user_id, description"#
+                    .to_string(),
+                delete: r#"delete from todos where id = $1 and user_id = $2 returning id as "id!", user_id, description"#
+                    .to_string(),
+                delete_all: "delete from todos where user_id = $1".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,46 @@
+//! Derive macros for the shipwright data layer.
+use proc_macro::TokenStream;
+use syn::{DeriveInput, parse_macro_input};
+
+mod entity;
+
+/// Derives `shipwright_db::Entity` for a struct that maps the columns of a table.
+///
+/// Every field is a column. The queries are written out at compile time and checked against the
+/// database with `sqlx::query_as!`, just like a hand-written implementation.
+///
+/// ```rust,ignore
+/// #[derive(Serialize, Deserialize, FromRow, Entity)]
+/// #[entity(table = "invoices", primary_key = "id", changeset = InvoiceChangeset)]
+/// pub struct Invoice {
+///     pub id: String,
+///     pub amount: Option<f64>,
+///     #[entity(read_only)]
+///     pub created_at: Option<OffsetDateTime>,
+/// }
+/// ```
+///
+/// Container attributes:
+///
+/// * `table` – the table the records are stored in (required).
+/// * `primary_key` – the primary key column, defaults to `id`.
+/// * `changeset` – the changeset type used by `create` and `update`, defaults to `<Name>Changeset`.
+/// * `owner` – the column holding the id of the user a record belongs to, e.g.
+///   `owner = "user_id"`. The field must be an `i64`. Also implements `shipwright_db::OwnedEntity`,
+///   whose `*_for_owner` methods only load, write and delete the records of the given owner.
+///
+/// Field attributes:
+///
+/// * `read_only` – the column is loaded but never written, e.g. timestamps set by the database.
+///
+/// Every other field apart from the primary key is written from the field of the same name on the
+/// changeset. `String` primary keys are generated as UUIDv7 on `create`, any other primary key is
+/// left for the database to generate.
+#[proc_macro_derive(Entity, attributes(entity))]
+pub fn derive_entity(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    entity::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -14,12 +14,13 @@ impl AuthMailer {
         let subject = "Please confirm your registration".to_string();
 
         let text = format!(
-            "Welcome to {}!\nEnter the code to confirm your registration: {}",
+            "Welcome to {}!\nEnter the code to confirm your registration: {}\nThe code expires in 2 hours.",
             config.app.name, register_token
         );
         let html = format!(
             "Welcome to {}!<br />\
-        Enter the code to confirm your registration: {}",
+        Enter the code to confirm your registration: {}<br />\
+        The code expires in 2 hours.",
             config.app.name, register_token
         );
 
@@ -31,4 +32,109 @@ impl AuthMailer {
             text,
         )
     }
+
+    pub fn send_magic_link(
+        email_client: &EmailClient,
+        config: &Config,
+        email_recipient: &str,
+        login_token: &str,
+    ) -> EmailPayload {
+        let subject = format!("Your sign in link for {}", config.app.name);
+
+        let login_url = format!(
+            "{}/auth/magic-link/verify?token={}",
+            config.server.host, login_token
+        );
+
+        let text = format!(
+            "Someone asked for a link to sign in to your {} account.\n\
+            Follow the link to sign in: {}\n\
+            The link expires in 15 minutes and works once. If it wasn't you, you can ignore this email.",
+            config.app.name, login_url
+        );
+        let html = format!(
+            "Someone asked for a link to sign in to your {} account.<br />\
+        <a href=\"{}\">Sign in</a><br />\
+        The link expires in 15 minutes and works once. If it wasn't you, you can ignore this email.",
+            config.app.name, login_url
+        );
+
+        EmailPayload::new(
+            email_client.sender.clone(),
+            vec![email_recipient.to_owned()],
+            subject,
+            html,
+            text,
+        )
+    }
+
+    pub fn send_email_change(
+        email_client: &EmailClient,
+        config: &Config,
+        new_email: &str,
+        change_token: &str,
+    ) -> EmailPayload {
+        let subject = "Confirm your new email".to_string();
+
+        let confirm_url = format!(
+            "{}/account/email/confirm?token={}",
+            config.server.host, change_token
+        );
+
+        let text = format!(
+            "Someone asked to move their {} account to this email.\n\
+            Follow the link to confirm the change: {}\n\
+            The link expires in 2 hours. If it wasn't you, you can ignore this email.",
+            config.app.name, confirm_url
+        );
+        let html = format!(
+            "Someone asked to move their {} account to this email.<br />\
+        <a href=\"{}\">Confirm the change</a><br />\
+        The link expires in 2 hours. If it wasn't you, you can ignore this email.",
+            config.app.name, confirm_url
+        );
+
+        EmailPayload::new(
+            email_client.sender.clone(),
+            vec![new_email.to_owned()],
+            subject,
+            html,
+            text,
+        )
+    }
+
+    pub fn send_password_reset(
+        email_client: &EmailClient,
+        config: &Config,
+        email_recipient: &str,
+        reset_token: &str,
+    ) -> EmailPayload {
+        let subject = "Reset your password".to_string();
+
+        let reset_url = format!(
+            "{}/auth/password/reset?token={}",
+            config.server.host, reset_token
+        );
+
+        let text = format!(
+            "Someone asked to reset the password of your {} account.\n\
+            Follow the link to choose a new password: {}\n\
+            The link expires in 1 hour. If it wasn't you, you can ignore this email.",
+            config.app.name, reset_url
+        );
+        let html = format!(
+            "Someone asked to reset the password of your {} account.<br />\
+        <a href=\"{}\">Choose a new password</a><br />\
+        The link expires in 1 hour. If it wasn't you, you can ignore this email.",
+            config.app.name, reset_url
+        );
+
+        EmailPayload::new(
+            email_client.sender.clone(),
+            vec![email_recipient.to_owned()],
+            subject,
+            html,
+            text,
+        )
+    }
 }
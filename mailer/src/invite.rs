@@ -0,0 +1,60 @@
+use shipwright_config::Config;
+
+use crate::{EmailClient, EmailPayload};
+
+pub struct InviteMailer;
+
+impl InviteMailer {
+    pub fn send_invitation(
+        email_client: &EmailClient,
+        config: &Config,
+        email_recipient: &str,
+        team_name: &str,
+        inviter_email: &str,
+        invite_token: &str,
+    ) -> EmailPayload {
+        let subject = format!(
+            "You're invited to join {} on {}",
+            team_name, config.app.name
+        );
+
+        let accept_url = format!(
+            "{}/teams/invitations/accept?token={}",
+            config.server.host, invite_token
+        );
+
+        let text = format!(
+            "{} invited you to join the team {} on {}.\n\
+            Follow the link to accept, you can sign up on the way if you don't have an account yet: {}\n\
+            The invitation expires in 7 days. If you weren't expecting it, you can ignore this email.",
+            inviter_email, team_name, config.app.name, accept_url
+        );
+        // Team names are typed in by users, so they're escaped before going into the html
+        let html = format!(
+            "{} invited you to join the team <strong>{}</strong> on {}.<br />\
+        <a href=\"{}\">Accept the invitation</a>, you can sign up on the way if you don't have an account yet.<br />\
+        The invitation expires in 7 days. If you weren't expecting it, you can ignore this email.",
+            escape_html(inviter_email),
+            escape_html(team_name),
+            config.app.name,
+            accept_url
+        );
+
+        EmailPayload::new(
+            email_client.sender.clone(),
+            vec![email_recipient.to_owned()],
+            subject,
+            html,
+            text,
+        )
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
+}
@@ -1,4 +1,5 @@
 pub mod auth;
+pub mod invite;
 
 use core::time;
 
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     future::pending,
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
@@ -23,6 +23,14 @@ pub trait ViewRenderer {
     ///
     /// This function will return an error if render fails
     fn render<S: Serialize>(&self, key: &str, data: S) -> Result<String, Error>;
+
+    /// Render only the block named `block` of the template located by `key`, e.g. to answer
+    /// an htmx request that swaps part of a page
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if render fails or the template has no such block
+    fn render_block<S: Serialize>(&self, key: &str, block: &str, data: S) -> Result<String, Error>;
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -35,10 +43,9 @@ impl<E> ViewEngine<E> {
     }
 }
 
-impl<S, E> FromRequestParts<S> for ViewEngine<E>
+impl<S> FromRequestParts<S> for ViewEngine<View>
 where
     S: Send + Sync,
-    E: Clone + Send + Sync + 'static,
 {
     type Rejection = std::convert::Infallible;
 
@@ -46,14 +53,40 @@ where
         parts: &mut Parts,
         state: &S,
     ) -> std::result::Result<Self, Self::Rejection> {
-        let Extension(tl): Extension<Self> = Extension::from_request_parts(parts, state)
+        let Extension(mut tl): Extension<Self> = Extension::from_request_parts(parts, state)
             .await
             .expect("view_engine missing. Is the view_engine initialized?");
 
+        if let Some(globals) = parts.extensions.get::<RequestGlobals>() {
+            tl.0.globals = globals.clone();
+        }
+
         Ok(tl)
     }
 }
 
+/// Values every template rendered for a request can use, e.g. the csrf token.
+///
+/// Middlewares put them in the request extensions and [`ViewEngine`] hands them to [`View`].
+/// The data passed to a render wins over a global with the same name.
+#[derive(Clone, Debug, Default)]
+pub struct RequestGlobals(BTreeMap<String, minijinja::Value>);
+
+impl RequestGlobals {
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<minijinja::Value>) {
+        self.0.insert(name.into(), value.into());
+    }
+
+    /// Inserts markup, it is rendered as is rather than escaped.
+    pub fn insert_html(&mut self, name: impl Into<String>, html: String) {
+        self.insert(name, minijinja::Value::from_safe_string(html));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&minijinja::Value> {
+        self.0.get(name)
+    }
+}
+
 /// A struct representing an inline Minijinja view renderer.
 ///
 /// This struct provides functionality to render templates using the Minijinja templating engine
@@ -76,6 +109,8 @@ impl<E> From<E> for ViewEngine<E> {
 pub struct View {
     pub reloader: Arc<AutoReloader>,
     pub component_engine: ComponentEngine,
+    /// The globals of the current request, see [`RequestGlobals`].
+    pub globals: RequestGlobals,
 }
 
 impl View {
@@ -98,15 +133,36 @@ impl View {
         Ok(Self {
             reloader: Arc::new(reloader),
             component_engine,
+            globals: RequestGlobals::default(),
         })
     }
 }
 
+impl View {
+    fn context<S: Serialize>(&self, data: S) -> minijinja::Value {
+        let globals = minijinja::Value::from_iter(self.globals.0.clone());
+        minijinja::context! {
+            ..minijinja::Value::from_serialize(data),
+            ..globals
+        }
+    }
+}
+
 impl ViewRenderer for View {
     fn render<S: Serialize>(&self, key: &str, data: S) -> Result<String, Error> {
         let env = self.reloader.acquire_env()?;
         let template = env.get_template(key)?;
-        let base_html = template.render(minijinja::Value::from_serialize(data))?;
+        let base_html = template.render(self.context(data))?;
+        let rendered = self.clone().component_engine.inject(&base_html)?;
+        Ok(rendered)
+    }
+
+    fn render_block<S: Serialize>(&self, key: &str, block: &str, data: S) -> Result<String, Error> {
+        let env = self.reloader.acquire_env()?;
+        let template = env.get_template(key)?;
+        let base_html = template
+            .eval_to_state(self.context(data))?
+            .render_block(block)?;
         let rendered = self.clone().component_engine.inject(&base_html)?;
         Ok(rendered)
     }
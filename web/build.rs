@@ -0,0 +1,14 @@
+//! Points the sqlx query macros at the offline query data of the Postgres backend when the
+//! `postgres` feature is enabled, SQLite builds use the workspace `.sqlx` directory.
+use std::{env, path::PathBuf};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=SQLX_OFFLINE_DIR");
+
+    // `cargo sqlx prepare` sets its own directory
+    if env::var_os("CARGO_FEATURE_POSTGRES").is_some() && env::var_os("SQLX_OFFLINE_DIR").is_none()
+    {
+        let dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("../.sqlx/postgres");
+        println!("cargo:rustc-env=SQLX_OFFLINE_DIR={}", dir.display());
+    }
+}
@@ -1,3 +1,5 @@
+use std::net::SocketAddr;
+
 use shipwright_config::Environment;
 use shipwright_ui::{static_assets::StaticAssetsInitializer, view_engine::ViewEngineInitializer};
 use shipwright_worker::WorkerInitializer;
@@ -41,8 +43,12 @@ impl App {
         asset_handler.before_run()?;
 
         // Initialize the background worker
-        let worker =
-            WorkerInitializer::init(&app_state.config, app_state.email_client.clone()).await?;
+        let worker = WorkerInitializer::init(
+            &app_state.config,
+            app_state.email_client.clone(),
+            app_state.db_pool.clone(),
+        )
+        .await?;
 
         // Initialize the view engine
         let view_engine = ViewEngineInitializer::default();
@@ -73,7 +79,12 @@ impl App {
             app.app_state.config.server.host, app.app_state.config.server.port
         );
 
-        serve(listener, app.router)
+        // Keep the connection info around, the client ip is used to throttle logins
+        let service = app
+            .router
+            .into_make_service_with_connect_info::<SocketAddr>();
+
+        serve(listener, service)
             .with_graceful_shutdown(shutdown_signal(vec![
                 app.deletion_task.abort_handle(),
                 app.worker_monitor_task.abort_handle(),
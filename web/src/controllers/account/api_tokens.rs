@@ -0,0 +1,90 @@
+use axum::{
+    Form, Router,
+    extract::{Path, State},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+};
+use shipwright_context::Account;
+use shipwright_db::entities::api_token::{ApiToken, CreateApiToken};
+use shipwright_ui::view_engine::{View, ViewEngine};
+use time::OffsetDateTime;
+
+use crate::{
+    error::Error,
+    middlewares::{
+        auth::AuthSession,
+        flash::{Flash, IncomingFlashes},
+    },
+    state::AppState,
+    views::account::api_tokens::ApiTokenView,
+};
+
+pub struct ApiTokenController;
+
+impl ApiTokenController {
+    pub fn router() -> Router<AppState> {
+        Router::new()
+            .route(
+                "/account/api-tokens",
+                get(ApiTokenController::index).post(ApiTokenController::create),
+            )
+            .route(
+                "/account/api-tokens/{id}/revoke",
+                post(ApiTokenController::revoke),
+            )
+    }
+
+    pub async fn index(
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+    ) -> Result<(IncomingFlashes, ApiTokenView), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+
+        let tokens = ApiToken::load_for_user(user.id, &state.db_pool).await?;
+
+        Ok((flashes.clone(), ApiTokenView::Index(v, flashes, tokens)))
+    }
+
+    pub async fn create(
+        v: ViewEngine<View>,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+        Form(form): Form<CreateApiToken>,
+    ) -> Result<Response, Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+
+        let token = Account::generate_api_token();
+        let expires_at = Account::api_token_expiry(form.expires_in_days, OffsetDateTime::now_utc());
+        let api_token = ApiToken::create(
+            user.id,
+            &form,
+            &Account::hash_api_token(&token),
+            expires_at,
+            &state.db_pool,
+        )
+        .await?;
+
+        // Only the hash is stored, so this is the one chance to copy the token
+        Ok(ApiTokenView::Created(v, token, api_token).into_response())
+    }
+
+    pub async fn revoke(
+        flash: Flash,
+        auth_session: AuthSession,
+        Path(id): Path<i64>,
+        State(state): State<AppState>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+
+        if !ApiToken::delete_for_user(id, user.id, &state.db_pool).await? {
+            return Err(shipwright_db::Error::NoRecordFound.into());
+        }
+
+        Ok((
+            flash.info("the api token was revoked"),
+            Redirect::to("/account/api-tokens"),
+        ))
+    }
+}
@@ -0,0 +1,115 @@
+use axum::{
+    Form, Router,
+    extract::State,
+    response::Redirect,
+    routing::{get, post},
+};
+use shipwright_context::Account;
+use shipwright_db::entities::{
+    account_deletion::{AccountDeletion, DeleteAccount},
+    user_data::UserData,
+};
+use shipwright_ui::view_engine::{View, ViewEngine};
+use time::OffsetDateTime;
+
+use crate::{
+    controllers::account::password::verify_current_password,
+    error::Error,
+    middlewares::{
+        auth::AuthSession,
+        flash::{Flash, IncomingFlashes},
+    },
+    state::AppState,
+    views::account::data::DataView,
+};
+
+/// Lets users download everything stored about them and delete their account.
+pub struct DataController;
+
+impl DataController {
+    pub fn router() -> Router<AppState> {
+        Router::new()
+            .route("/account/data", get(DataController::index))
+            .route("/account/data/export", get(DataController::export))
+            .route("/account/data/delete", post(DataController::delete))
+            .route("/account/data/delete/cancel", post(DataController::cancel))
+    }
+
+    pub async fn index(
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+    ) -> Result<(IncomingFlashes, DataView), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let deletion = AccountDeletion::try_get_for_user(user.id, &state.db_pool).await?;
+
+        Ok((
+            flashes.clone(),
+            DataView::Index(
+                v,
+                flashes,
+                deletion,
+                state.config.account.deletion_grace_days,
+            ),
+        ))
+    }
+
+    /// Downloads the account as a JSON archive.
+    pub async fn export(
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+    ) -> Result<DataView, Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let data = UserData::load(user.id, &state.db_pool).await?;
+
+        Ok(DataView::Export(data))
+    }
+
+    /// Schedules the deletion of the account, it is deleted once the grace period is over.
+    pub async fn delete(
+        flash: Flash,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+        Form(form): Form<DeleteAccount>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+
+        match verify_current_password(&user, &form.current_password).await {
+            Ok(()) => (),
+            Err(Error::Account(shipwright_context::Error::InvalidCredentials)) => {
+                return Ok((
+                    flash.error("❌ your current password is wrong"),
+                    Redirect::to("/account/data"),
+                ));
+            }
+            Err(e) => return Err(e),
+        }
+
+        let delete_after = Account::deletion_date(
+            state.config.account.deletion_grace_days,
+            OffsetDateTime::now_utc(),
+        );
+        AccountDeletion::schedule(user.id, delete_after, &state.db_pool).await?;
+
+        Ok((
+            flash.info("your account will be deleted, you can cancel until then"),
+            Redirect::to("/account/data"),
+        ))
+    }
+
+    pub async fn cancel(
+        flash: Flash,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+
+        AccountDeletion::cancel(user.id, &state.db_pool).await?;
+
+        Ok((
+            flash.success("✅ your account will not be deleted"),
+            Redirect::to("/account/data"),
+        ))
+    }
+}
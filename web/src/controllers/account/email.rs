@@ -0,0 +1,159 @@
+use axum::{
+    Extension, Form, Router,
+    extract::{Query, State},
+    response::Redirect,
+    routing::get,
+};
+use serde::Deserialize;
+use shipwright_db::{
+    Validate as _,
+    entities::{
+        email_change_token::{ConfirmEmailChange, EmailChangeToken},
+        user::{ChangeEmail, User},
+    },
+    transaction,
+};
+use shipwright_mailer::{EmailPayload, auth::AuthMailer};
+use shipwright_ui::view_engine::{View, ViewEngine};
+use shipwright_worker::{Storage, WorkerStorage};
+
+use crate::{
+    controllers::account::password::verify_current_password,
+    error::Error,
+    middlewares::{
+        auth::AuthSession,
+        flash::{Flash, IncomingFlashes},
+    },
+    state::AppState,
+    views::account::email::EmailView,
+};
+
+// The change token is sent to the new address as a link, so it arrives in the query string.
+#[derive(Debug, Deserialize)]
+pub struct ChangeTokenQuery {
+    token: Option<String>,
+}
+
+/// Asks for an email change, only routed for logged in users.
+pub struct EmailController;
+
+impl EmailController {
+    pub fn router() -> Router<AppState> {
+        Router::new().route(
+            "/account/email",
+            get(EmailController::index).post(EmailController::update),
+        )
+    }
+
+    pub async fn index(
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+        auth_session: AuthSession,
+    ) -> Result<(IncomingFlashes, EmailView), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+
+        Ok((flashes.clone(), EmailView::Index(v, flashes, user.email)))
+    }
+
+    /// Sends a confirmation link to the new address, the email only changes once it is opened.
+    pub async fn update(
+        flash: Flash,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+        Extension(mut jobs): Extension<WorkerStorage<EmailPayload>>,
+        Form(form): Form<ChangeEmail>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+
+        match verify_current_password(&user, &form.current_password).await {
+            Ok(()) => (),
+            Err(Error::Account(shipwright_context::Error::InvalidCredentials)) => {
+                return Ok((
+                    flash.error("❌ your current password is wrong"),
+                    Redirect::to("/account/email"),
+                ));
+            }
+            Err(e) => return Err(e),
+        }
+        form.validate().map_err(shipwright_db::Error::from)?;
+
+        let mut tx = transaction(&state.db_pool).await?;
+        // Any previous link stops working once a new one is sent
+        EmailChangeToken::delete_for_user(user.id, &mut *tx).await?;
+        let change_token = EmailChangeToken::create(user.id, &form.email, &mut *tx).await?;
+        tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+        jobs.push(AuthMailer::send_email_change(
+            &state.email_client,
+            &state.config,
+            &form.email,
+            &change_token,
+        ))
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to send email change confirmation: {:?}", e);
+        })
+        .ok();
+
+        Ok((
+            flash.info("we sent a link to your new email, open it to finish the change"),
+            Redirect::to("/account/email"),
+        ))
+    }
+}
+
+/// Confirms an email change with the token of the link, whoever has the link can use it.
+pub struct EmailConfirmController;
+
+impl EmailConfirmController {
+    pub fn router() -> Router<AppState> {
+        Router::new().route(
+            "/account/email/confirm",
+            get(EmailConfirmController::index).post(EmailConfirmController::confirm),
+        )
+    }
+
+    /// The page the emailed link opens.
+    ///
+    /// Mail scanners follow links to check them, so opening the link only shows a button and
+    /// the token is used up by the form it posts.
+    pub async fn index(
+        v: ViewEngine<View>,
+        Query(ChangeTokenQuery { token }): Query<ChangeTokenQuery>,
+        flashes: IncomingFlashes,
+    ) -> (IncomingFlashes, EmailView) {
+        (flashes.clone(), EmailView::Confirm(v, flashes, token))
+    }
+
+    pub async fn confirm(
+        flash: Flash,
+        State(state): State<AppState>,
+        Form(form): Form<ConfirmEmailChange>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let mut tx = transaction(&state.db_pool).await?;
+        // The token is deleted so the link can't be used again
+        let Some(change) = EmailChangeToken::try_consume(&form, &mut *tx).await? else {
+            return Ok((
+                flash.error("❌ this confirmation link is invalid or has expired"),
+                Redirect::to("/account/email"),
+            ));
+        };
+        // Someone may have registered with the address since the link was sent
+        match User::update_email(change.user_id, &change.new_email, &mut *tx).await {
+            Ok(_) => (),
+            Err(shipwright_db::Error::UniqueConstraint(_)) => {
+                return Ok((
+                    flash.error("❌ this email is already in use"),
+                    Redirect::to("/account/email"),
+                ));
+            }
+            Err(e) => return Err(e.into()),
+        }
+        tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+        Ok((
+            flash.success("✅ your email has been changed"),
+            Redirect::to("/account/email"),
+        ))
+    }
+}
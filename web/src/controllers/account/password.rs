@@ -0,0 +1,93 @@
+use axum::{Form, Router, extract::State, response::Redirect, routing::get};
+use shipwright_context::Account;
+use shipwright_db::{
+    Validate as _,
+    entities::{
+        session::Session,
+        user::{ChangePassword, User},
+    },
+    transaction,
+};
+use shipwright_ui::view_engine::{View, ViewEngine};
+use tokio::task;
+
+use crate::{
+    error::Error,
+    middlewares::{
+        auth::AuthSession,
+        flash::{Flash, IncomingFlashes},
+    },
+    state::AppState,
+    views::account::password::PasswordView,
+};
+
+pub struct PasswordController;
+
+impl PasswordController {
+    pub fn router() -> Router<AppState> {
+        Router::new().route(
+            "/account/password",
+            get(PasswordController::index).post(PasswordController::update),
+        )
+    }
+
+    pub async fn index(
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+    ) -> (IncomingFlashes, PasswordView) {
+        (flashes.clone(), PasswordView::Index(v, flashes))
+    }
+
+    /// Changes the password and signs out every other session, this one stays logged in.
+    pub async fn update(
+        flash: Flash,
+        mut auth_session: AuthSession,
+        session: tower_sessions::Session,
+        State(state): State<AppState>,
+        Form(form): Form<ChangePassword>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let user = auth_session.user.clone().ok_or(Error::Unauthenticated)?;
+
+        match verify_current_password(&user, &form.current_password).await {
+            Ok(()) => (),
+            Err(Error::Account(shipwright_context::Error::InvalidCredentials)) => {
+                return Ok((
+                    flash.error("❌ your current password is wrong"),
+                    Redirect::to("/account/password"),
+                ));
+            }
+            Err(e) => return Err(e),
+        }
+        form.validate().map_err(shipwright_db::Error::from)?;
+
+        let current = session.id().map(|id| id.to_string());
+        let mut tx = transaction(&state.db_pool).await?;
+        let user = User::update_password(user.id, &form.password, &mut *tx).await?;
+        // The other sessions stopped working with the old password hash, remove them too
+        Session::delete_all_for_user(user.id, current.as_deref(), &mut *tx).await?;
+        tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+        // This session was tied to the old password hash as well, log it in again
+        auth_session
+            .login(&user)
+            .await
+            .map_err(|e| Error::Unexpected(e.into()))?;
+
+        Ok((
+            flash.success("✅ your password has been changed, your other sessions are signed out"),
+            Redirect::to("/account/password"),
+        ))
+    }
+}
+
+/// Checks the password of the logged in user before their account is changed.
+pub(crate) async fn verify_current_password(user: &User, password: &str) -> Result<(), Error> {
+    let (user, password) = (user.clone(), password.to_string());
+
+    // Verifying the password is blocking and potentially slow
+    task::spawn_blocking(move || Account::verify_password(&user, &password))
+        .await
+        .map_err(|e| Error::Unexpected(e.into()))??;
+
+    Ok(())
+}
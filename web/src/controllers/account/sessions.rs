@@ -0,0 +1,98 @@
+use axum::{
+    Router,
+    extract::{Path, State},
+    response::Redirect,
+    routing::{get, post},
+};
+use shipwright_context::Account;
+use shipwright_db::entities::session::Session;
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::{
+    error::Error,
+    middlewares::{
+        auth::AuthSession,
+        flash::{Flash, IncomingFlashes},
+    },
+    state::AppState,
+    views::account::sessions::SessionView,
+};
+
+pub struct SessionController;
+
+impl SessionController {
+    pub fn router() -> Router<AppState> {
+        Router::new()
+            .route("/account/sessions", get(SessionController::index))
+            .route(
+                "/account/sessions/{handle}/revoke",
+                post(SessionController::revoke),
+            )
+            .route(
+                "/account/sessions/revoke-others",
+                post(SessionController::revoke_others),
+            )
+    }
+
+    pub async fn index(
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+        auth_session: AuthSession,
+        session: tower_sessions::Session,
+        State(state): State<AppState>,
+    ) -> Result<(IncomingFlashes, SessionView), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let current = session.id().map(|id| id.to_string());
+
+        let sessions = Session::load_active_for_user(user.id, &state.db_pool).await?;
+
+        Ok((
+            flashes.clone(),
+            SessionView::Index(v, flashes, sessions, current),
+        ))
+    }
+
+    /// Signs out one of the user's other sessions, use logout to end the current one.
+    pub async fn revoke(
+        flash: Flash,
+        auth_session: AuthSession,
+        session: tower_sessions::Session,
+        Path(handle): Path<String>,
+        State(state): State<AppState>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let current = session.id().map(|id| id.to_string());
+
+        let revoked = Session::load_active_for_user(user.id, &state.db_pool)
+            .await?
+            .into_iter()
+            .filter(|active| Some(&active.id) != current.as_ref())
+            .find(|active| Account::session_handle(&active.id) == handle)
+            .ok_or(shipwright_db::Error::NoRecordFound)?;
+
+        Session::delete_for_user(&revoked.id, user.id, &state.db_pool).await?;
+
+        Ok((
+            flash.info("the session was signed out"),
+            Redirect::to("/account/sessions"),
+        ))
+    }
+
+    pub async fn revoke_others(
+        flash: Flash,
+        auth_session: AuthSession,
+        session: tower_sessions::Session,
+        State(state): State<AppState>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let current = session.id().map(|id| id.to_string());
+
+        let revoked =
+            Session::delete_all_for_user(user.id, current.as_deref(), &state.db_pool).await?;
+
+        Ok((
+            flash.info(format!("signed out of {revoked} other sessions")),
+            Redirect::to("/account/sessions"),
+        ))
+    }
+}
@@ -0,0 +1,161 @@
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use axum_login::login_required;
+use serde_json::json;
+use shipwright_db::{
+    OwnedEntity, Page, PageMeta, PageRequest,
+    entities::todo::{Todo, TodoChangeset},
+};
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::{
+    error::Error,
+    format,
+    middlewares::{
+        auth::{AuthBackend, AuthSession},
+        bearer::bearer_auth,
+    },
+    openapi::{self, ApiDoc},
+    permission_required,
+    state::AppState,
+};
+
+/// The JSON routes for scripts and mobile clients, nested under `/api`.
+///
+/// Requests are authenticated with a personal access token or the cookie session, both go
+/// through `login_required!` so the handlers see the same user.
+pub struct ApiController;
+
+impl ApiController {
+    pub fn router() -> Router<AppState> {
+        Router::new()
+            .route("/todos", post(ApiController::create_todo))
+            .route_layer(permission_required!("todos.write"))
+            .route("/me", get(ApiController::me))
+            .route("/todos", get(ApiController::todos))
+            .route_layer(login_required!(AuthBackend))
+            .route_layer(middleware::from_fn(bearer_auth))
+            // The docs are public, they tell clients how to authenticate
+            .route("/openapi.json", get(ApiController::openapi))
+            .route("/docs", get(ApiController::docs))
+    }
+
+    /// Describes the routes above in the OpenAPI document, see [`crate::openapi`].
+    pub fn openapi_routes(mut doc: ApiDoc) -> ApiDoc {
+        let security = json!([{ "token": [] }, { "session": [] }]);
+        let me = json!({
+            "tags": ["api"],
+            "operationId": "api_me",
+            "summary": "The signed in user",
+            "security": security,
+            "responses": {
+                "200": {
+                    "description": "The id and email of the user",
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "integer", "format": "int64" },
+                            "email": { "type": "string", "format": "email" },
+                        },
+                        "required": ["id", "email"],
+                    } } },
+                },
+                "401": doc.problem("Not signed in"),
+            },
+        });
+        let todos = json!({
+            "tags": ["api"],
+            "operationId": "api_todos",
+            "summary": "List a page of the todos of the user",
+            "parameters": doc.page_parameters(),
+            "security": security,
+            "responses": {
+                "200": {
+                    "description": "A page of todos",
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "properties": {
+                            "todos": { "type": "array", "items": doc.schema::<Todo>() },
+                            "page": doc.schema::<PageMeta>(),
+                        },
+                        "required": ["todos", "page"],
+                    } } },
+                },
+                "400": doc.problem("The page request is invalid"),
+                "401": doc.problem("Not signed in"),
+            },
+        });
+        let create_todo = json!({
+            "tags": ["api"],
+            "operationId": "api_create_todo",
+            "summary": "Create a todo for the user",
+            "description": "Requires the `todos.write` permission.",
+            "security": security,
+            "requestBody": {
+                "required": true,
+                "content": { "application/json": { "schema": doc.schema::<TodoChangeset>() } },
+            },
+            "responses": {
+                "201": doc.json::<Todo>("The created todo"),
+                "401": doc.problem("Not signed in"),
+                "403": doc.problem("Missing permission"),
+                "422": doc.problem("The changeset is invalid"),
+            },
+        });
+
+        doc.operation("get", "/api/me", me)
+            .operation("get", "/api/todos", todos)
+            .operation("post", "/api/todos", create_todo)
+    }
+
+    pub async fn openapi(State(state): State<AppState>) -> Result<Response, Error> {
+        format::json(openapi::document(&state.config))
+    }
+
+    /// Renders the OpenAPI document as a page, without any scripts or assets from elsewhere.
+    pub async fn docs(
+        ViewEngine(v): ViewEngine<View>,
+        State(state): State<AppState>,
+    ) -> Result<Response, Error> {
+        format::render().view(
+            &v,
+            "api/docs.html",
+            json!({ "spec": openapi::document(&state.config) }),
+        )
+    }
+
+    pub async fn me(auth_session: AuthSession) -> Result<Response, Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+
+        format::json(json!({ "id": user.id, "email": user.email }))
+    }
+
+    pub async fn todos(
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+        Query(page): Query<PageRequest>,
+    ) -> Result<Response, Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let Page { records, meta } =
+            Todo::load_page_for_owner(user.id, &page, &state.db_pool).await?;
+
+        format::json(json!({ "todos": records, "page": meta }))
+    }
+
+    pub async fn create_todo(
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+        Json(record): Json<TodoChangeset>,
+    ) -> Result<Response, Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let todo = Todo::create_for_owner(user.id, record, &state.db_pool).await?;
+
+        Ok((StatusCode::CREATED, Json(todo)).into_response())
+    }
+}
@@ -0,0 +1,71 @@
+use axum::{Extension, Form, Router, extract::State, response::Redirect, routing::get};
+use shipwright_db::{
+    entities::{
+        password_reset_token::{ForgotPassword, PasswordResetToken},
+        user::User,
+    },
+    transaction,
+};
+use shipwright_mailer::{EmailPayload, auth::AuthMailer};
+use shipwright_ui::view_engine::{View, ViewEngine};
+use shipwright_worker::{Storage, WorkerStorage};
+
+use crate::{
+    error::Error,
+    middlewares::flash::{Flash, IncomingFlashes},
+    state::AppState,
+    views::auth::forgot_password::ForgotPasswordView,
+};
+
+pub struct ForgotPasswordController;
+
+impl ForgotPasswordController {
+    pub fn router() -> Router<AppState> {
+        Router::new().route(
+            "/auth/password/forgot",
+            get(ForgotPasswordController::index).post(ForgotPasswordController::request),
+        )
+    }
+
+    pub async fn index(
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+    ) -> (IncomingFlashes, ForgotPasswordView) {
+        (flashes.clone(), ForgotPasswordView::Index(v, flashes))
+    }
+
+    pub async fn request(
+        flash: Flash,
+        State(state): State<AppState>,
+        Extension(mut jobs): Extension<WorkerStorage<EmailPayload>>,
+        Form(form): Form<ForgotPassword>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let mut tx = transaction(&state.db_pool).await?;
+        // Everyone sees the same response so the endpoint can't be used to find out which
+        // emails are registered
+        if let Some(user) = User::try_get_by_email(&form.email, &mut *tx).await? {
+            // Any previous link stops working once a new one is sent
+            PasswordResetToken::delete_for_user(user.id, &mut *tx).await?;
+            let reset_token = PasswordResetToken::create(user.id, &mut *tx).await?;
+            tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+            // Send the reset email in a background job
+            jobs.push(AuthMailer::send_password_reset(
+                &state.email_client,
+                &state.config,
+                &user.email,
+                &reset_token,
+            ))
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to send password reset email: {:?}", e);
+            })
+            .ok();
+        }
+
+        Ok((
+            flash.info("if an account exists for that email a reset link is on its way"),
+            Redirect::to("/auth/password/forgot"),
+        ))
+    }
+}
@@ -1,77 +1,333 @@
 use axum::Router;
-use axum::extract::Query;
+use axum::extract::{Query, State};
 use axum::routing::get;
 use axum::{Form, response::Redirect};
-use shipwright_db::entities::user::UserCredentials;
+use serde::{Deserialize, Serialize};
+use shipwright_context::Account;
+use shipwright_db::entities::{
+    login_throttle::LoginThrottle,
+    two_factor::{TotpCode, TotpCredential},
+    user::{User, UserCredentials},
+};
+use shipwright_db::transaction;
 use shipwright_ui::view_engine::{View, ViewEngine};
-use serde::Deserialize;
+use time::{Duration, OffsetDateTime};
+use tower_sessions::Session;
 
+use crate::controllers::auth::two_factor::verify_code;
 use crate::error::Error;
-use crate::middlewares::auth::AuthSession;
+use crate::middlewares::auth::{AuthSession, Credentials};
+use crate::middlewares::client_ip::ClientIp;
 use crate::middlewares::flash::{Flash, IncomingFlashes};
+use crate::middlewares::session_activity::SessionClient;
+use crate::return_url::ReturnUrl;
 use crate::state::AppState;
 use crate::views::auth::login::LoginView;
 
 // This allows us to extract the "next" field from the query string. We use this
-// to redirect after log in.
+// to redirect after log in, once it has been checked with `ReturnUrl`.
 #[derive(Debug, Deserialize)]
 pub struct NextUrl {
-    next: Option<String>,
+    pub next: Option<String>,
+}
+
+/// A login that passed the password check and is waiting for the two-factor code.
+///
+/// It is kept in the session instead of logging the user in, so protected routes still see a
+/// logged out user until the second step passes.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingLogin {
+    user_id: i64,
+    next: Option<ReturnUrl>,
+    expires_at: i64,
+}
+
+impl PendingLogin {
+    const SESSION_KEY: &'static str = "pending_login";
 }
 
 pub struct LoginController;
 
 impl LoginController {
     pub fn router() -> Router<AppState> {
-        Router::new().route(
-            "/auth/login",
-            get(LoginController::index).post(LoginController::login),
-        )
+        Router::new()
+            .route(
+                "/auth/login",
+                get(LoginController::index).post(LoginController::login),
+            )
+            .route(
+                "/auth/login/two-factor",
+                get(LoginController::two_factor_index).post(LoginController::two_factor),
+            )
     }
 
     pub async fn index(
         v: ViewEngine<View>,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
         Query(NextUrl { next }): Query<NextUrl>,
         flashes: IncomingFlashes,
     ) -> (IncomingFlashes, LoginView) {
-        (flashes.clone(), LoginView::Index(v, flashes, next))
+        let providers = auth_session.backend.oauth().providers();
+        let next = ReturnUrl::parse_next(next.as_deref(), &state.config.server.host);
+
+        (
+            flashes.clone(),
+            LoginView::Index(v, flashes, next, providers, state.config.auth.magic_link),
+        )
     }
 
     pub async fn login(
-        mut auth_session: AuthSession,
+        auth_session: AuthSession,
+        session: Session,
         flash: Flash,
+        State(state): State<AppState>,
+        ClientIp(ip): ClientIp,
+        client: SessionClient,
         Form(creds): Form<UserCredentials>,
     ) -> Result<(Flash, Redirect), Error> {
-        let user = match auth_session.authenticate(creds.clone()).await {
+        let next = ReturnUrl::parse_next(creds.next.as_deref(), &state.config.server.host);
+        let login_url = match &next {
+            Some(next) => next.append_to("/auth/login"),
+            None => "/auth/login".to_string(),
+        };
+
+        // Failed logins are counted per email and per ip
+        let email_key = LoginThrottle::email_key(&creds.email);
+        let throttle_keys = std::iter::once(email_key.clone())
+            .chain(ip.map(LoginThrottle::ip_key))
+            .collect::<Vec<_>>();
+
+        // Refuse throttled logins before the password is checked
+        match check_throttles(&throttle_keys, &state).await {
+            Err(Error::Account(e @ shipwright_context::Error::TooManyAttempts { .. })) => {
+                return Ok((flash.error(format!("❌ {e}")), Redirect::to(&login_url)));
+            }
+            result => result?,
+        }
+
+        let user = match auth_session
+            .authenticate(Credentials::Password(creds.clone()))
+            .await
+        {
             Ok(Some(user)) => user,
             Ok(None) => {
-                let mut login_url = "/auth/login".to_string();
-                if let Some(next) = creds.next {
-                    login_url = format!("{}?next={}", login_url, next);
-                };
+                record_failure(&throttle_keys, &state).await?;
+
                 return Ok((
                     flash.error("❌ invalid credentials"),
                     Redirect::to(&login_url),
                 ));
             }
+            Err(axum_login::Error::Backend(Error::Account(
+                shipwright_context::Error::PendingConfirmation,
+            ))) => {
+                // The password was right, so this doesn't count as a failure
+                LoginThrottle::clear(&email_key, &state.db_pool).await?;
+
+                let confirm_url = match &next {
+                    Some(next) => next.append_to("/auth/register/confirm"),
+                    None => "/auth/register/confirm".to_string(),
+                };
+
+                return Ok((
+                    flash.info("please confirm your email before logging in"),
+                    Redirect::to(&confirm_url),
+                ));
+            }
             Err(e) => return Err(Error::Unexpected(e.into())),
         };
 
-        auth_session
-            .login(&user)
+        LoginThrottle::clear(&email_key, &state.db_pool).await?;
+
+        start_session(auth_session, session, client, flash, &user, next, &state).await
+    }
+
+    pub async fn two_factor_index(
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+    ) -> (IncomingFlashes, LoginView) {
+        (flashes.clone(), LoginView::TwoFactor(v, flashes))
+    }
+
+    pub async fn two_factor(
+        auth_session: AuthSession,
+        session: Session,
+        flash: Flash,
+        State(state): State<AppState>,
+        ClientIp(ip): ClientIp,
+        client: SessionClient,
+        Form(form): Form<TotpCode>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let pending_login = session
+            .get::<PendingLogin>(PendingLogin::SESSION_KEY)
             .await
-            .map_err(|e| Error::Unexpected(e.into()))?;
+            .map_err(|e| Error::Unexpected(e.into()))?
+            .filter(|pending| pending.expires_at > OffsetDateTime::now_utc().unix_timestamp());
+
+        let Some(pending_login) = pending_login else {
+            return Ok((
+                flash.error("❌ your login has expired, please log in again"),
+                Redirect::to("/auth/login"),
+            ));
+        };
+
+        let user = User::try_get_by_id(&pending_login.user_id, &state.db_pool)
+            .await?
+            .ok_or(Error::Unauthenticated)?;
+        let credential = TotpCredential::try_get_by_user_id(user.id, &state.db_pool)
+            .await?
+            .ok_or(Error::Unauthenticated)?;
+
+        // Codes are guessed just like passwords, so they count towards the same throttle
+        let email_key = LoginThrottle::email_key(&user.email);
+        let throttle_keys = std::iter::once(email_key.clone())
+            .chain(ip.map(LoginThrottle::ip_key))
+            .collect::<Vec<_>>();
+
+        match check_throttles(&throttle_keys, &state).await {
+            Err(Error::Account(e @ shipwright_context::Error::TooManyAttempts { .. })) => {
+                return Ok((
+                    flash.error(format!("❌ {e}")),
+                    Redirect::to("/auth/login/two-factor"),
+                ));
+            }
+            result => result?,
+        }
+
+        // The code is used up together with the login, so it can't be replayed
+        let mut tx = transaction(&state.db_pool).await?;
+        match verify_code(&credential, &form.code, &mut tx).await {
+            Ok(()) => (),
+            Err(Error::Account(shipwright_context::Error::InvalidTotpCode)) => {
+                // SQLite allows one writer at a time, the failure is recorded outside the login
+                drop(tx);
+                record_failure(&throttle_keys, &state).await?;
 
-        if let Some(ref next) = creds.next {
-            Ok((
-                flash.success("✅ successfully logged in"),
-                Redirect::to(next),
-            ))
-        } else {
-            Ok((
-                flash.success("✅ successfully logged in"),
-                Redirect::to("/"),
-            ))
+                return Ok((
+                    flash.error("❌ invalid code"),
+                    Redirect::to("/auth/login/two-factor"),
+                ));
+            }
+            Err(e) => return Err(e),
         }
+
+        LoginThrottle::clear(&email_key, &mut *tx).await?;
+        tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+        session
+            .remove::<PendingLogin>(PendingLogin::SESSION_KEY)
+            .await
+            .map_err(|e| Error::Unexpected(e.into()))?;
+
+        complete_login(
+            auth_session,
+            session,
+            client,
+            flash,
+            &user,
+            pending_login.next,
+            &state,
+        )
+        .await
+    }
+}
+
+/// Logs in a user who has passed the first step of a login, or asks for their two-factor code
+/// first if they have it turned on.
+pub(crate) async fn start_session(
+    auth_session: AuthSession,
+    session: Session,
+    client: SessionClient,
+    flash: Flash,
+    user: &User,
+    next: Option<ReturnUrl>,
+    state: &AppState,
+) -> Result<(Flash, Redirect), Error> {
+    // Users with two-factor authentication get a session once they've entered a code
+    if TotpCredential::try_get_by_user_id(user.id, &state.db_pool)
+        .await?
+        .is_some_and(|credential| credential.enabled)
+    {
+        let pending_login = PendingLogin {
+            user_id: user.id,
+            next,
+            expires_at: (OffsetDateTime::now_utc() + Duration::minutes(5)).unix_timestamp(),
+        };
+        session
+            .insert(PendingLogin::SESSION_KEY, pending_login)
+            .await
+            .map_err(|e| Error::Unexpected(e.into()))?;
+
+        return Ok((flash, Redirect::to("/auth/login/two-factor")));
+    }
+
+    complete_login(auth_session, session, client, flash, user, next, state).await
+}
+
+/// Logs the user in and sends them on to where they were going.
+async fn complete_login(
+    mut auth_session: AuthSession,
+    session: Session,
+    client: SessionClient,
+    flash: Flash,
+    user: &User,
+    next: Option<ReturnUrl>,
+    state: &AppState,
+) -> Result<(Flash, Redirect), Error> {
+    auth_session
+        .login(user)
+        .await
+        .map_err(|e| Error::Unexpected(e.into()))?;
+
+    // The login gave the session a new id, it is saved now so it can be stamped
+    session
+        .save()
+        .await
+        .map_err(|e| Error::Unexpected(e.into()))?;
+    if let Some(id) = session.id() {
+        client
+            .track(&id.to_string(), user.id, Duration::ZERO, &state.db_pool)
+            .await?;
+    }
+
+    if let Some(ref next) = next {
+        Ok((
+            flash.success("✅ successfully logged in"),
+            Redirect::to(next.as_str()),
+        ))
+    } else {
+        Ok((
+            flash.success("✅ successfully logged in"),
+            Redirect::to("/"),
+        ))
     }
 }
+
+/// Returns an error while any of the keys is throttled.
+async fn check_throttles(throttle_keys: &[String], state: &AppState) -> Result<(), Error> {
+    let now = OffsetDateTime::now_utc();
+
+    for key in throttle_keys {
+        let throttle = LoginThrottle::try_get(key, &state.db_pool).await?;
+        Account::check_throttle(throttle.as_ref(), now)?;
+    }
+
+    Ok(())
+}
+
+/// Counts a failed login against every key and works out how long it has to wait.
+async fn record_failure(throttle_keys: &[String], state: &AppState) -> Result<(), Error> {
+    let now = OffsetDateTime::now_utc();
+    let mut tx = transaction(&state.db_pool).await?;
+
+    for key in throttle_keys {
+        let throttle = LoginThrottle::try_get(key, &mut *tx).await?;
+        let (failures, locked_until) =
+            Account::throttle_failure(throttle.as_ref(), &state.config.auth.throttle, now);
+        LoginThrottle::upsert(key, failures, locked_until, &mut *tx).await?;
+    }
+
+    tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use axum::{
+    Extension, Form, Router,
+    extract::{Query, State},
+    response::Redirect,
+    routing::get,
+};
+use serde::Deserialize;
+use shipwright_db::{
+    entities::{
+        magic_link_token::{MagicLinkLogin, MagicLinkToken, RequestMagicLink},
+        user::{User, UserStatus},
+    },
+    transaction,
+};
+use shipwright_mailer::{EmailPayload, auth::AuthMailer};
+use shipwright_ui::view_engine::{View, ViewEngine};
+use shipwright_worker::{Storage, WorkerStorage};
+use tower_sessions::Session;
+
+use crate::{
+    controllers::auth::login::start_session,
+    error::Error,
+    middlewares::{
+        auth::AuthSession,
+        flash::{Flash, IncomingFlashes},
+        session_activity::SessionClient,
+    },
+    state::AppState,
+    views::auth::magic_link::MagicLinkView,
+};
+
+// The login token is sent to the user as a link, so it arrives in the query string.
+#[derive(Debug, Deserialize)]
+pub struct LoginTokenQuery {
+    token: Option<String>,
+}
+
+/// Passwordless sign in with a link sent by email, only routed when `auth.magic_link` is on.
+pub struct MagicLinkController;
+
+impl MagicLinkController {
+    pub fn router() -> Router<AppState> {
+        Router::new()
+            .route(
+                "/auth/magic-link",
+                get(MagicLinkController::index).post(MagicLinkController::request),
+            )
+            .route(
+                "/auth/magic-link/verify",
+                get(MagicLinkController::verify_index).post(MagicLinkController::verify),
+            )
+    }
+
+    pub async fn index(
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+    ) -> (IncomingFlashes, MagicLinkView) {
+        (flashes.clone(), MagicLinkView::Index(v, flashes))
+    }
+
+    pub async fn request(
+        flash: Flash,
+        State(state): State<AppState>,
+        Extension(mut jobs): Extension<WorkerStorage<EmailPayload>>,
+        Form(form): Form<RequestMagicLink>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let mut tx = transaction(&state.db_pool).await?;
+        // Everyone sees the same response so the endpoint can't be used to find out which
+        // emails are registered. Pending users confirm their email with the code they were sent.
+        if let Some(user) = User::try_get_by_email(&form.email, &mut *tx)
+            .await?
+            .filter(|user| matches!(user.status, UserStatus::Confirmed))
+        {
+            // Any previous link stops working once a new one is sent
+            MagicLinkToken::delete_for_user(user.id, &mut *tx).await?;
+            let login_token = MagicLinkToken::create(user.id, &mut *tx).await?;
+            tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+            jobs.push(AuthMailer::send_magic_link(
+                &state.email_client,
+                &state.config,
+                &user.email,
+                &login_token,
+            ))
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to send magic link email: {:?}", e);
+            })
+            .ok();
+        }
+
+        Ok((
+            flash.info("if an account exists for that email a sign in link is on its way"),
+            Redirect::to("/auth/magic-link"),
+        ))
+    }
+
+    /// The page the emailed link opens.
+    ///
+    /// Mail scanners follow links to check them, so opening the link only shows a button and
+    /// the token is used up by the form it posts.
+    pub async fn verify_index(
+        v: ViewEngine<View>,
+        Query(LoginTokenQuery { token }): Query<LoginTokenQuery>,
+        flashes: IncomingFlashes,
+    ) -> (IncomingFlashes, MagicLinkView) {
+        (flashes.clone(), MagicLinkView::Verify(v, flashes, token))
+    }
+
+    pub async fn verify(
+        auth_session: AuthSession,
+        session: Session,
+        client: SessionClient,
+        flash: Flash,
+        State(state): State<AppState>,
+        Form(form): Form<MagicLinkLogin>,
+    ) -> Result<(Flash, Redirect), Error> {
+        // The token is deleted so the link can't be used again
+        let Some(user_id) = MagicLinkToken::try_consume(&form, &state.db_pool).await? else {
+            return Ok((
+                flash.error("❌ this sign in link is invalid or has expired"),
+                Redirect::to("/auth/magic-link"),
+            ));
+        };
+        let user = User::try_get_by_id(&user_id, &state.db_pool)
+            .await?
+            .ok_or(Error::Unauthenticated)?;
+
+        // Users with two-factor authentication still have to enter a code
+        start_session(auth_session, session, client, flash, &user, None, &state).await
+    }
+}
@@ -1,4 +1,9 @@
+pub mod forgot_password;
 pub mod login;
 pub mod logout;
+pub mod magic_link;
+pub mod oauth;
 pub mod register;
 pub mod register_confirm;
+pub mod reset_password;
+pub mod two_factor;
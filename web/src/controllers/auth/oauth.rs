@@ -0,0 +1,178 @@
+use axum::{
+    Router,
+    extract::{Path, Query, State},
+    response::Redirect,
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+use shipwright_context::Account;
+use tower_sessions::Session;
+use tracing::error;
+
+use crate::{
+    controllers::auth::login::start_session,
+    error::Error,
+    middlewares::{
+        auth::{AuthSession, Credentials, OAuthCredentials},
+        flash::Flash,
+        session_activity::SessionClient,
+    },
+    return_url::ReturnUrl,
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeParams {
+    next: Option<String>,
+}
+
+/// The query string a provider redirects back with, either a code or an error.
+#[derive(Debug, Deserialize)]
+pub struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+/// A login that was sent to a provider and is waiting for it to redirect back.
+#[derive(Debug, Serialize, Deserialize)]
+struct OAuthLogin {
+    provider: String,
+    state: String,
+    code_verifier: String,
+    next: Option<ReturnUrl>,
+}
+
+impl OAuthLogin {
+    const SESSION_KEY: &'static str = "oauth_login";
+}
+
+pub struct OAuthController;
+
+impl OAuthController {
+    pub fn router() -> Router<AppState> {
+        Router::new()
+            .route("/auth/oauth/{provider}", get(OAuthController::authorize))
+            .route(
+                "/auth/oauth/{provider}/callback",
+                get(OAuthController::callback),
+            )
+    }
+
+    pub async fn authorize(
+        auth_session: AuthSession,
+        session: Session,
+        State(app_state): State<AppState>,
+        Path(provider): Path<String>,
+        Query(AuthorizeParams { next }): Query<AuthorizeParams>,
+    ) -> Result<Redirect, Error> {
+        let next = ReturnUrl::parse_next(next.as_deref(), &app_state.config.server.host);
+        let state = Account::generate_oauth_state();
+        let code_verifier = Account::generate_pkce_verifier();
+
+        let url = auth_session
+            .backend
+            .oauth()
+            .authorize_url(&provider, &state, &Account::pkce_challenge(&code_verifier))
+            .ok_or_else(|| Error::UnknownOAuthProvider(provider.clone()))?;
+
+        session
+            .insert(
+                OAuthLogin::SESSION_KEY,
+                OAuthLogin {
+                    provider,
+                    state,
+                    code_verifier,
+                    next,
+                },
+            )
+            .await
+            .map_err(|e| Error::Unexpected(e.into()))?;
+
+        Ok(Redirect::to(url.as_str()))
+    }
+
+    pub async fn callback(
+        auth_session: AuthSession,
+        session: Session,
+        client: SessionClient,
+        flash: Flash,
+        State(state): State<AppState>,
+        Path(provider): Path<String>,
+        Query(params): Query<CallbackParams>,
+    ) -> Result<(Flash, Redirect), Error> {
+        // The login can only be finished once, and only by the browser that started it
+        let oauth_login = session
+            .remove::<OAuthLogin>(OAuthLogin::SESSION_KEY)
+            .await
+            .map_err(|e| Error::Unexpected(e.into()))?
+            .filter(|login| {
+                login.provider == provider && params.state == Some(login.state.clone())
+            });
+
+        let Some(oauth_login) = oauth_login else {
+            return Ok((
+                flash.error("❌ your login has expired, please log in again"),
+                Redirect::to("/auth/login"),
+            ));
+        };
+
+        let code = match (params.code, params.error) {
+            (Some(code), None) => code,
+            // The user cancelled or the provider refused, e.g. `access_denied`
+            _ => {
+                return Ok((
+                    flash.error("❌ the login was cancelled"),
+                    Redirect::to("/auth/login"),
+                ));
+            }
+        };
+
+        let credentials = Credentials::OAuth(OAuthCredentials {
+            provider,
+            code,
+            code_verifier: oauth_login.code_verifier,
+        });
+
+        let user = match auth_session.authenticate(credentials).await {
+            Ok(Some(user)) => user,
+            Ok(None) => {
+                return Ok((
+                    flash.error("❌ invalid credentials"),
+                    Redirect::to("/auth/login"),
+                ));
+            }
+            Err(axum_login::Error::Backend(Error::Account(
+                shipwright_context::Error::UnverifiedEmail,
+            ))) => {
+                return Ok((
+                    flash.error("❌ please verify your email with the provider before logging in"),
+                    Redirect::to("/auth/login"),
+                ));
+            }
+            Err(axum_login::Error::Backend(Error::OAuth(e))) => {
+                error!(
+                    "an error occured while talking to the oauth provider: {:?}",
+                    e
+                );
+
+                return Ok((
+                    flash.error("❌ the provider could not log you in, please try again"),
+                    Redirect::to("/auth/login"),
+                ));
+            }
+            Err(e) => return Err(Error::Unexpected(e.into())),
+        };
+
+        start_session(
+            auth_session,
+            session,
+            client,
+            flash,
+            &user,
+            oauth_login.next,
+            &state,
+        )
+        .await
+    }
+}
@@ -1,13 +1,24 @@
 use crate::{
+    controllers::auth::login::NextUrl,
     error::Error,
+    form::FormState,
+    htmx::HxRequest,
     middlewares::flash::{Flash, IncomingFlashes},
+    return_url::ReturnUrl,
     state::AppState,
     views::auth::register::RegisterView,
 };
-use axum::{Extension, Form, Router, extract::State, response::Redirect, routing::get};
+use axum::{
+    Extension, Form, Router,
+    extract::{Query, State},
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+};
 use shipwright_db::{
+    Validate as _,
     entities::{
         register_token::RegisterToken,
+        role::Role,
         user::{RegisterUser, User},
     },
     transaction,
@@ -29,18 +40,35 @@ impl RegisterController {
     pub async fn index(
         v: ViewEngine<View>,
         flashes: IncomingFlashes,
+        State(state): State<AppState>,
+        Query(NextUrl { next }): Query<NextUrl>,
     ) -> (IncomingFlashes, RegisterView) {
-        (flashes.clone(), RegisterView::Index(v, flashes))
+        let next = ReturnUrl::parse_next(next.as_deref(), &state.config.server.host);
+
+        (flashes.clone(), RegisterView::Index(v, flashes, next))
     }
 
+    /// Registers a pending user, `next` is passed on to the confirmation, e.g. to return to a
+    /// team invitation.
     pub async fn register(
+        hx: HxRequest,
+        v: ViewEngine<View>,
         flash: Flash,
         State(app_state): State<AppState>,
         Extension(mut jobs): Extension<WorkerStorage<EmailPayload>>,
+        Query(NextUrl { next }): Query<NextUrl>,
         Form(form): Form<RegisterUser>,
-    ) -> Result<(Flash, Redirect), Error> {
+    ) -> Result<Response, Error> {
+        let next = ReturnUrl::parse_next(next.as_deref(), &app_state.config.server.host);
+        // The passwords are not sent back, they have to be entered again
+        if let Err(errors) = form.validate() {
+            let state = FormState::invalid(&errors).value("email", &form.email);
+            return Ok(RegisterView::Form(hx, v, next, state).into_response());
+        }
+
         let mut tx = transaction(&app_state.db_pool).await?;
         let user = User::create(form, &mut *tx).await?;
+        Role::assign(user.id, Role::MEMBER, &mut *tx).await?;
         let register_token = RegisterToken::create(user.id, &mut *tx).await?;
         tx.commit()
             .await
@@ -60,9 +88,14 @@ impl RegisterController {
         .ok();
 
         // Redirect to the confirmation page
+        let confirm_url = match &next {
+            Some(next) => next.append_to("/auth/register/confirm"),
+            None => "/auth/register/confirm".to_string(),
+        };
         Ok((
             flash.info("please check your email for the confirmation code"),
-            Redirect::to("/auth/register/confirm"),
-        ))
+            Redirect::to(&confirm_url),
+        )
+            .into_response())
     }
 }
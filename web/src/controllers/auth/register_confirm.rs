@@ -1,19 +1,28 @@
-use axum::{Form, Router, extract::State, response::Redirect, routing::get};
+use axum::{
+    Extension, Form, Router,
+    extract::{Query, State},
+    response::Redirect,
+    routing::{get, post},
+};
 use shipwright_db::{
     entities::{
-        register_token::{RegisterToken, RegisterTokenValidate},
+        register_token::{RegisterToken, RegisterTokenValidate, ResendRegisterToken},
         user::{User, UserStatus},
     },
     transaction,
 };
+use shipwright_mailer::{EmailPayload, auth::AuthMailer};
 use shipwright_ui::view_engine::{View, ViewEngine};
+use shipwright_worker::{Storage, WorkerStorage};
 
 use crate::{
+    controllers::auth::login::NextUrl,
     error::Error,
     middlewares::{
         auth::AuthSession,
         flash::{Flash, IncomingFlashes},
     },
+    return_url::ReturnUrl,
     state::AppState,
     views::auth::register_confirm::RegisterConfirmView,
 };
@@ -22,28 +31,45 @@ pub struct RegisterConfirmController;
 
 impl RegisterConfirmController {
     pub fn router() -> Router<AppState> {
-        Router::new().route(
-            "/auth/register/confirm",
-            get(RegisterConfirmController::index).post(RegisterConfirmController::verify),
-        )
+        Router::new()
+            .route(
+                "/auth/register/confirm",
+                get(RegisterConfirmController::index).post(RegisterConfirmController::verify),
+            )
+            .route(
+                "/auth/register/confirm/resend",
+                post(RegisterConfirmController::resend),
+            )
     }
 
     pub async fn index(
         v: ViewEngine<View>,
         flashes: IncomingFlashes,
+        State(state): State<AppState>,
+        Query(NextUrl { next }): Query<NextUrl>,
     ) -> (IncomingFlashes, RegisterConfirmView) {
-        (flashes.clone(), RegisterConfirmView::Index(v, flashes))
+        let next = ReturnUrl::parse_next(next.as_deref(), &state.config.server.host);
+
+        (
+            flashes.clone(),
+            RegisterConfirmView::Index(v, flashes, next),
+        )
     }
 
+    /// Confirms the user and logs them in, sending them on to `next` if the login sent them here.
     pub async fn verify(
         flash: Flash,
         State(state): State<AppState>,
         mut auth_session: AuthSession,
+        Query(NextUrl { next }): Query<NextUrl>,
         Form(form): Form<RegisterTokenValidate>,
     ) -> Result<(Flash, Redirect), Error> {
+        let next = ReturnUrl::parse_next(next.as_deref(), &state.config.server.host);
+
         let mut tx = transaction(&state.db_pool).await?;
-        // Get the user id by the user input register token
-        let user_id = RegisterToken::try_get_user_id_by_register_token(form, &mut *tx)
+        // Get the user id by the user input register token, the token is deleted so it can't be
+        // used again
+        let user_id = RegisterToken::try_consume(form, &mut *tx)
             .await?
             .ok_or_else(|| Error::InvalidRegisterToken)?;
         // Update the user status to from pending to confirmed
@@ -61,7 +87,44 @@ impl RegisterConfirmController {
 
         Ok((
             flash.success("Welcome! You are now registered"),
-            Redirect::to("/"),
+            Redirect::to(next.as_ref().map_or("/", ReturnUrl::as_str)),
+        ))
+    }
+
+    pub async fn resend(
+        flash: Flash,
+        State(state): State<AppState>,
+        Extension(mut jobs): Extension<WorkerStorage<EmailPayload>>,
+        Form(form): Form<ResendRegisterToken>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let mut tx = transaction(&state.db_pool).await?;
+        // Only pending users get a new code, everyone else sees the same response so the
+        // endpoint can't be used to find out which emails are registered
+        if let Some(user) = User::try_get_by_email(&form.email, &mut *tx)
+            .await?
+            .filter(|user| matches!(user.status, UserStatus::Pending))
+        {
+            // Any previous code stops working once a new one is sent
+            RegisterToken::delete_for_user(user.id, &mut *tx).await?;
+            let register_token = RegisterToken::create(user.id, &mut *tx).await?;
+            tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+            jobs.push(AuthMailer::send_confirmation(
+                &state.email_client,
+                &state.config,
+                &user.email,
+                &register_token.register_token,
+            ))
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to send confirmation email: {:?}", e);
+            })
+            .ok();
+        }
+
+        Ok((
+            flash.info("if your account is awaiting confirmation a new code is on its way"),
+            Redirect::to("/auth/register/confirm"),
         ))
     }
 }
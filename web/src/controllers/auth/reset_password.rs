@@ -0,0 +1,71 @@
+use axum::{
+    Form, Router,
+    extract::{Query, State},
+    response::Redirect,
+    routing::get,
+};
+use serde::Deserialize;
+use shipwright_db::{
+    entities::{
+        password_reset_token::{PasswordResetToken, ResetPassword},
+        user::User,
+    },
+    transaction,
+};
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::{
+    error::Error,
+    middlewares::flash::{Flash, IncomingFlashes},
+    state::AppState,
+    views::auth::reset_password::ResetPasswordView,
+};
+
+// The reset token is sent to the user as a link, so it arrives in the query string.
+#[derive(Debug, Deserialize)]
+pub struct ResetTokenQuery {
+    token: Option<String>,
+}
+
+pub struct ResetPasswordController;
+
+impl ResetPasswordController {
+    pub fn router() -> Router<AppState> {
+        Router::new().route(
+            "/auth/password/reset",
+            get(ResetPasswordController::index).post(ResetPasswordController::reset),
+        )
+    }
+
+    pub async fn index(
+        v: ViewEngine<View>,
+        Query(ResetTokenQuery { token }): Query<ResetTokenQuery>,
+        flashes: IncomingFlashes,
+    ) -> (IncomingFlashes, ResetPasswordView) {
+        (flashes.clone(), ResetPasswordView::Index(v, flashes, token))
+    }
+
+    pub async fn reset(
+        flash: Flash,
+        State(state): State<AppState>,
+        Form(form): Form<ResetPassword>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let mut tx = transaction(&state.db_pool).await?;
+        // The token is deleted so the link can't be used again
+        let Some(user_id) = PasswordResetToken::try_consume(&form, &mut *tx).await? else {
+            return Ok((
+                flash.error("❌ this reset link is invalid or has expired"),
+                Redirect::to("/auth/password/forgot"),
+            ));
+        };
+        // Changing the password hash logs the user out of every existing session
+        User::update_password(user_id, &form.password, &mut *tx).await?;
+        PasswordResetToken::delete_for_user(user_id, &mut *tx).await?;
+        tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+        Ok((
+            flash.success("✅ your password has been reset, please log in"),
+            Redirect::to("/auth/login"),
+        ))
+    }
+}
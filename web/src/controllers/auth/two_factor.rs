@@ -0,0 +1,188 @@
+use axum::{
+    Form, Router,
+    extract::State,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+};
+use qrcode::{QrCode, render::svg};
+use shipwright_context::Account;
+use shipwright_db::{
+    Db, Transaction,
+    entities::two_factor::{RecoveryCode, TotpCode, TotpCredential},
+    transaction,
+};
+use shipwright_ui::view_engine::{View, ViewEngine};
+use time::OffsetDateTime;
+
+use crate::{
+    error::Error,
+    middlewares::{
+        auth::AuthSession,
+        flash::{Flash, IncomingFlashes},
+    },
+    state::AppState,
+    views::auth::two_factor::TwoFactorView,
+};
+
+pub struct TwoFactorController;
+
+impl TwoFactorController {
+    pub fn router() -> Router<AppState> {
+        Router::new()
+            .route("/auth/two-factor", get(TwoFactorController::index))
+            .route("/auth/two-factor/enable", post(TwoFactorController::enable))
+            .route(
+                "/auth/two-factor/disable",
+                post(TwoFactorController::disable),
+            )
+    }
+
+    pub async fn index(
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+    ) -> Result<(IncomingFlashes, TwoFactorView), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+
+        let credential = match TotpCredential::try_get_by_user_id(user.id, &state.db_pool).await? {
+            Some(credential) if credential.enabled => {
+                return Ok((flashes.clone(), TwoFactorView::Enabled(v, flashes)));
+            }
+            // Keep the secret of an unfinished enrolment, the user may have scanned it already
+            Some(credential) => credential,
+            None => TotpCredential::try_start_enrolment(
+                user.id,
+                &Account::generate_totp_secret(),
+                &state.db_pool,
+            )
+            .await?
+            .ok_or(shipwright_db::Error::NoRecordFound)?,
+        };
+
+        let url = Account::totp(&credential.secret, &state.config.app.name, &user.email)?.get_url();
+        let qr_code = QrCode::new(url)
+            .map_err(|e| Error::Unexpected(e.into()))?
+            .render::<svg::Color>()
+            .min_dimensions(200, 200)
+            .build();
+
+        Ok((
+            flashes.clone(),
+            TwoFactorView::Enroll(v, flashes, credential.secret, qr_code),
+        ))
+    }
+
+    pub async fn enable(
+        v: ViewEngine<View>,
+        flash: Flash,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+        Form(form): Form<TotpCode>,
+    ) -> Result<Response, Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+
+        let Some(credential) = TotpCredential::try_get_by_user_id(user.id, &state.db_pool)
+            .await?
+            .filter(|credential| !credential.enabled)
+        else {
+            return Ok(Redirect::to("/auth/two-factor").into_response());
+        };
+
+        // The user proves their app works before it is needed to log in
+        let step = match Account::verify_totp(
+            &credential.secret,
+            &form.code,
+            OffsetDateTime::now_utc(),
+            credential.last_used_step,
+        ) {
+            Ok(step) => step,
+            Err(shipwright_context::Error::InvalidTotpCode) => {
+                return Ok((
+                    flash.error("❌ that code didn't match, please try again"),
+                    Redirect::to("/auth/two-factor"),
+                )
+                    .into_response());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let recovery_codes = Account::generate_recovery_codes();
+
+        let mut tx = transaction(&state.db_pool).await?;
+        TotpCredential::enable(user.id, &mut *tx).await?;
+        TotpCredential::try_use_step(user.id, step, &mut *tx).await?;
+        RecoveryCode::delete_for_user(user.id, &mut *tx).await?;
+        for code in &recovery_codes {
+            RecoveryCode::create(user.id, &Account::hash_recovery_code(code), &mut *tx).await?;
+        }
+        tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+        // The recovery codes are only stored hashed, so this is the one chance to see them
+        Ok(TwoFactorView::RecoveryCodes(v, recovery_codes).into_response())
+    }
+
+    pub async fn disable(
+        flash: Flash,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+        Form(form): Form<TotpCode>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+
+        let Some(credential) = TotpCredential::try_get_by_user_id(user.id, &state.db_pool)
+            .await?
+            .filter(|credential| credential.enabled)
+        else {
+            return Ok((flash, Redirect::to("/auth/two-factor")));
+        };
+
+        let mut tx = transaction(&state.db_pool).await?;
+        match verify_code(&credential, &form.code, &mut tx).await {
+            Ok(()) => (),
+            Err(Error::Account(shipwright_context::Error::InvalidTotpCode)) => {
+                return Ok((
+                    flash.error("❌ that code didn't match, two-factor authentication is still on"),
+                    Redirect::to("/auth/two-factor"),
+                ));
+            }
+            Err(e) => return Err(e),
+        }
+
+        TotpCredential::delete(user.id, &mut *tx).await?;
+        RecoveryCode::delete_for_user(user.id, &mut *tx).await?;
+        tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+        Ok((
+            flash.success("✅ two-factor authentication is off"),
+            Redirect::to("/auth/two-factor"),
+        ))
+    }
+}
+
+/// Checks a code from the user's authenticator app, or consumes one of their recovery codes.
+///
+/// Either way the code is used up in `tx`, commit it together with what the code was asked for.
+pub(crate) async fn verify_code(
+    credential: &TotpCredential,
+    code: &str,
+    tx: &mut Transaction<'static, Db>,
+) -> Result<(), Error> {
+    let used = if Account::is_recovery_code(code) {
+        let code_hash = Account::hash_recovery_code(code);
+        RecoveryCode::try_consume(credential.user_id, &code_hash, &mut **tx).await?
+    } else {
+        let step = Account::verify_totp(
+            &credential.secret,
+            code,
+            OffsetDateTime::now_utc(),
+            credential.last_used_step,
+        )?;
+        TotpCredential::try_use_step(credential.user_id, step, &mut **tx).await?
+    };
+
+    match used {
+        true => Ok(()),
+        false => Err(shipwright_context::Error::InvalidTotpCode.into()),
+    }
+}
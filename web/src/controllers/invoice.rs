@@ -1,20 +1,27 @@
 use async_trait::async_trait;
 use axum::{
-    Form, Router,
-    extract::{Path, State},
-    response::Redirect,
-    routing::{get, post},
+    Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post, put},
 };
 use shipwright_db::{
-    Entity,
-    entities::invoices::Invoice,
+    Entity, PageRequest, Validate as _, entities::invoices::Invoice,
     entities::invoices::InvoiceChangeset,
 };
 use shipwright_ui::view_engine::{View, ViewEngine};
 
 use crate::{
     error::Error,
-    middlewares::flash::{Flash, IncomingFlashes},
+    form::FormState,
+    htmx::HxRequest,
+    middlewares::{
+        auth::Permissions,
+        flash::{Flash, IncomingFlashes},
+    },
+    negotiation::{FormOrJson, Format},
+    permission_required,
     state::AppState,
     views::invoices::InvoiceView,
 };
@@ -29,85 +36,156 @@ impl Controller for InvoiceController {
 
     type View = InvoiceView;
 
+    type Record = Invoice;
+
     type EntityChangeset = InvoiceChangeset;
 
     type Error = Error;
 
+    const PATH: &'static str = "/invoices";
+
     fn router() -> Router<AppState> {
+        let item = format!("{}/{{id}}", Self::PATH);
+
         Router::new()
-            .route("/invoices", get(Self::read_all).post(Self::create))
-            .route("/invoices/batch", post(Self::create_batch))
-            .route(
-                "/invoices/{id}",
-                get(Self::read_one).put(Self::update).delete(Self::delete),
-            )
+            .route(Self::PATH, post(Self::create))
+            .route(&format!("{}/batch", Self::PATH), post(Self::create_batch))
+            .route(&item, put(Self::update).delete(Self::delete))
+            .route_layer(permission_required!("invoices.write"))
+            .route(Self::PATH, get(Self::read_all))
+            .route(&item, get(Self::read_one))
     }
 
     async fn read_all(
+        format: Format,
         v: ViewEngine<View>,
         flashes: IncomingFlashes,
+        permissions: Permissions,
         State(app_state): State<AppState>,
+        Query(page): Query<PageRequest>,
     ) -> Result<(IncomingFlashes, Self::View), Self::Error> {
-        let invoices = Invoice::load_all(&app_state.db_pool).await?;
+        let invoices = Invoice::load_page(&page, &app_state.db_pool).await?;
 
-        Ok((flashes.clone(), InvoiceView::Index(v, invoices, flashes)))
+        Ok((
+            flashes.clone(),
+            InvoiceView::Index(format, v, invoices, flashes, permissions),
+        ))
     }
 
     async fn create(
+        format: Format,
+        hx: HxRequest,
+        v: ViewEngine<View>,
         flash: Flash,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
         State(app_state): State<AppState>,
-        Form(record): Form<Self::EntityChangeset>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
+        FormOrJson(record): FormOrJson<Self::EntityChangeset>,
+    ) -> Result<Response, Self::Error> {
+        if !format.is_json()
+            && let Err(errors) = record.validate()
+        {
+            let form = FormState::invalid(&errors).value("amount", record.amount);
+            let invoices = Invoice::load_page(&PageRequest::default(), &app_state.db_pool).await?;
+            return Ok((
+                flashes.clone(),
+                InvoiceView::CreateForm(hx, v, invoices, form, flashes, permissions),
+            )
+                .into_response());
+        }
         let invoice = Invoice::create(record, &app_state.db_pool).await?;
 
-        Ok((
-            flash.success(&format!("✅ created new invoice")),
-            Redirect::to(&format!("/invoices/{}", invoice.id)),
-        ))
+        Ok(format.respond(StatusCode::CREATED, invoice, |invoice| {
+            (
+                flash.success("✅ created new invoice"),
+                Redirect::to(&format!("/invoices/{}", invoice.id)),
+            )
+        }))
     }
 
     async fn create_batch(
+        format: Format,
         flash: Flash,
         State(app_state): State<AppState>,
-        Form(records): Form<Vec<Self::EntityChangeset>>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let _records = Invoice::create_batch(records, &app_state.db_pool).await?;
-
-        Ok((flash.success(&format!("✅ created invoices")), Redirect::to("/invoices")))
+        FormOrJson(records): FormOrJson<Vec<Self::EntityChangeset>>,
+    ) -> Result<Response, Self::Error> {
+        let invoices = Invoice::create_batch(records, &app_state.db_pool).await?;
+
+        Ok(format.respond(StatusCode::CREATED, invoices, |_| {
+            (
+                flash.success("✅ created invoices"),
+                Redirect::to("/invoices"),
+            )
+        }))
     }
 
     async fn read_one(
+        format: Format,
         v: ViewEngine<View>,
         flashes: IncomingFlashes,
+        permissions: Permissions,
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
     ) -> Result<(IncomingFlashes, Self::View), Self::Error> {
         let invoice = Invoice::load(id, &app_state.db_pool).await?;
 
-        Ok((flashes.clone(), InvoiceView::Show(v, invoice, flashes)))
+        Ok((
+            flashes.clone(),
+            InvoiceView::Show(format, v, invoice, flashes, permissions),
+        ))
     }
 
     async fn update(
+        format: Format,
+        hx: HxRequest,
+        v: ViewEngine<View>,
         flash: Flash,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
-        Form(form): Form<Self::EntityChangeset>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let invoice = Invoice::update(id, form, &app_state.db_pool).await?;
+        FormOrJson(record): FormOrJson<Self::EntityChangeset>,
+    ) -> Result<Response, Self::Error> {
+        if !format.is_json()
+            && let Err(errors) = record.validate()
+        {
+            let form = FormState::invalid(&errors).value("amount", record.amount);
+            let invoice = Invoice::load(id, &app_state.db_pool).await?;
+            return Ok((
+                flashes.clone(),
+                InvoiceView::UpdateForm(hx, v, invoice, form, flashes, permissions),
+            )
+                .into_response());
+        }
+        let invoice = Invoice::update(id, record, &app_state.db_pool).await?;
+
+        // The form swaps itself, the flash goes out as an event
+        if hx.is_partial() && !format.is_json() {
+            return Ok((
+                flash.success("✅ updated invoice"),
+                InvoiceView::Update(v, invoice),
+            )
+                .into_response());
+        }
 
-        Ok((
-            flash.success(&format!("✅ updated invoice")),
-            Redirect::to(&format!("/invoices/{}", invoice.id)),
-        ))
+        Ok(format.respond(StatusCode::OK, invoice, |invoice| {
+            (
+                flash.success("✅ updated invoice"),
+                Redirect::to(&format!("/invoices/{}", invoice.id)),
+            )
+        }))
     }
 
     async fn delete(
+        format: Format,
         flash: Flash,
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let _invoice = Invoice::delete(id, &app_state.db_pool).await?;
+    ) -> Result<Response, Self::Error> {
+        let invoice = Invoice::delete(id, &app_state.db_pool).await?;
 
-        Ok((flash.info(&format!("deleted invoice")), Redirect::to("/invoices")))
+        Ok(format.respond(StatusCode::OK, invoice, |_| {
+            (flash.info("deleted invoice"), Redirect::to("/invoices"))
+        }))
     }
 }
@@ -1,20 +1,26 @@
 use async_trait::async_trait;
 use axum::{
-    Form, Router,
-    extract::{Path, State},
-    response::Redirect,
-    routing::{get, post},
+    Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post, put},
 };
 use shipwright_db::{
-    Entity,
-    entities::lions::Lion,
-    entities::lions::LionChangeset,
+    Entity, PageRequest, Validate as _, entities::lions::Lion, entities::lions::LionChangeset,
 };
 use shipwright_ui::view_engine::{View, ViewEngine};
 
 use crate::{
     error::Error,
-    middlewares::flash::{Flash, IncomingFlashes},
+    form::FormState,
+    htmx::HxRequest,
+    middlewares::{
+        auth::Permissions,
+        flash::{Flash, IncomingFlashes},
+    },
+    negotiation::{FormOrJson, Format},
+    permission_required,
     state::AppState,
     views::lions::LionView,
 };
@@ -29,85 +35,155 @@ impl Controller for LionController {
 
     type View = LionView;
 
+    type Record = Lion;
+
     type EntityChangeset = LionChangeset;
 
     type Error = Error;
 
+    const PATH: &'static str = "/lions";
+
     fn router() -> Router<AppState> {
+        let item = format!("{}/{{id}}", Self::PATH);
+
         Router::new()
-            .route("/lions", get(Self::read_all).post(Self::create))
-            .route("/lions/batch", post(Self::create_batch))
-            .route(
-                "/lions/{id}",
-                get(Self::read_one).put(Self::update).delete(Self::delete),
-            )
+            .route(Self::PATH, post(Self::create))
+            .route(&format!("{}/batch", Self::PATH), post(Self::create_batch))
+            .route(&item, put(Self::update).delete(Self::delete))
+            .route_layer(permission_required!("lions.write"))
+            .route(Self::PATH, get(Self::read_all))
+            .route(&item, get(Self::read_one))
     }
 
     async fn read_all(
+        format: Format,
         v: ViewEngine<View>,
         flashes: IncomingFlashes,
+        permissions: Permissions,
         State(app_state): State<AppState>,
+        Query(page): Query<PageRequest>,
     ) -> Result<(IncomingFlashes, Self::View), Self::Error> {
-        let lions = Lion::load_all(&app_state.db_pool).await?;
+        let lions = Lion::load_page(&page, &app_state.db_pool).await?;
 
-        Ok((flashes.clone(), LionView::Index(v, lions, flashes)))
+        Ok((
+            flashes.clone(),
+            LionView::Index(format, v, lions, flashes, permissions),
+        ))
     }
 
     async fn create(
+        format: Format,
+        hx: HxRequest,
+        v: ViewEngine<View>,
         flash: Flash,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
         State(app_state): State<AppState>,
-        Form(record): Form<Self::EntityChangeset>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
+        FormOrJson(record): FormOrJson<Self::EntityChangeset>,
+    ) -> Result<Response, Self::Error> {
+        if !format.is_json()
+            && let Err(errors) = record.validate()
+        {
+            let form = FormState::invalid(&errors)
+                .value("name", &record.name)
+                .value("email", &record.email);
+            let lions = Lion::load_page(&PageRequest::default(), &app_state.db_pool).await?;
+            return Ok((
+                flashes.clone(),
+                LionView::CreateForm(hx, v, lions, form, flashes, permissions),
+            )
+                .into_response());
+        }
         let lion = Lion::create(record, &app_state.db_pool).await?;
 
-        Ok((
-            flash.success(&format!("✅ created new lion")),
-            Redirect::to(&format!("/lions/{}", lion.id)),
-        ))
+        Ok(format.respond(StatusCode::CREATED, lion, |lion| {
+            (
+                flash.success("✅ created new lion"),
+                Redirect::to(&format!("/lions/{}", lion.id)),
+            )
+        }))
     }
 
     async fn create_batch(
+        format: Format,
         flash: Flash,
         State(app_state): State<AppState>,
-        Form(records): Form<Vec<Self::EntityChangeset>>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let _records = Lion::create_batch(records, &app_state.db_pool).await?;
+        FormOrJson(records): FormOrJson<Vec<Self::EntityChangeset>>,
+    ) -> Result<Response, Self::Error> {
+        let lions = Lion::create_batch(records, &app_state.db_pool).await?;
 
-        Ok((flash.success(&format!("✅ created lions")), Redirect::to("/lions")))
+        Ok(format.respond(StatusCode::CREATED, lions, |_| {
+            (flash.success("✅ created lions"), Redirect::to("/lions"))
+        }))
     }
 
     async fn read_one(
+        format: Format,
         v: ViewEngine<View>,
         flashes: IncomingFlashes,
+        permissions: Permissions,
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
     ) -> Result<(IncomingFlashes, Self::View), Self::Error> {
         let lion = Lion::load(id, &app_state.db_pool).await?;
 
-        Ok((flashes.clone(), LionView::Show(v, lion, flashes)))
+        Ok((
+            flashes.clone(),
+            LionView::Show(format, v, lion, flashes, permissions),
+        ))
     }
 
     async fn update(
+        format: Format,
+        hx: HxRequest,
+        v: ViewEngine<View>,
         flash: Flash,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
-        Form(form): Form<Self::EntityChangeset>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let lion = Lion::update(id, form, &app_state.db_pool).await?;
-
-        Ok((
-            flash.success(&format!("✅ updated lion")),
-            Redirect::to(&format!("/lions/{}", lion.id)),
-        ))
+        FormOrJson(record): FormOrJson<Self::EntityChangeset>,
+    ) -> Result<Response, Self::Error> {
+        if !format.is_json()
+            && let Err(errors) = record.validate()
+        {
+            let form = FormState::invalid(&errors)
+                .value("name", &record.name)
+                .value("email", &record.email);
+            let lion = Lion::load(id, &app_state.db_pool).await?;
+            return Ok((
+                flashes.clone(),
+                LionView::UpdateForm(hx, v, lion, form, flashes, permissions),
+            )
+                .into_response());
+        }
+        let lion = Lion::update(id, record, &app_state.db_pool).await?;
+
+        // The form swaps itself, the flash goes out as an event
+        if hx.is_partial() && !format.is_json() {
+            return Ok(
+                (flash.success("✅ updated lion"), LionView::Update(v, lion)).into_response(),
+            );
+        }
+
+        Ok(format.respond(StatusCode::OK, lion, |lion| {
+            (
+                flash.success("✅ updated lion"),
+                Redirect::to(&format!("/lions/{}", lion.id)),
+            )
+        }))
     }
 
     async fn delete(
+        format: Format,
         flash: Flash,
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let _lion = Lion::delete(id, &app_state.db_pool).await?;
+    ) -> Result<Response, Self::Error> {
+        let lion = Lion::delete(id, &app_state.db_pool).await?;
 
-        Ok((flash.info(&format!("deleted lion")), Redirect::to("/lions")))
+        Ok(format.respond(StatusCode::OK, lion, |_| {
+            (flash.info("deleted lion"), Redirect::to("/lions"))
+        }))
     }
 }
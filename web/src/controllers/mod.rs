@@ -1,20 +1,29 @@
 use async_trait::async_trait;
 use axum::{
-    Form, Router,
-    extract::{Path, State},
-    response::{IntoResponse, Redirect},
+    Router,
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
 };
-use shipwright_db::{DeserializeOwned, Validate};
+use serde::Serialize;
+use shipwright_db::{DeserializeOwned, JsonSchema, PageRequest, Validate};
 use shipwright_ui::view_engine::{View, ViewEngine};
 
 use crate::{
-    middlewares::flash::{Flash, IncomingFlashes},
+    htmx::HxRequest,
+    middlewares::{
+        auth::{AuthSession, Permissions},
+        flash::{Flash, IncomingFlashes},
+    },
+    negotiation::{FormOrJson, Format},
     state::AppState,
 };
 
+pub mod account;
+pub mod api;
 pub mod auth;
 pub mod home;
 pub mod ping;
+pub mod teams;
 pub mod todos;
 
 /// ------------------------------------------------------------------------
@@ -24,6 +33,17 @@ pub mod todos;
 /// Implement the Controller trait for your model's associated
 /// controller to receive a complete CRUD set of handlers!
 ///
+/// Every handler serves browsers and JSON clients, see [`crate::negotiation`].
+/// Reads hand the [`Format`] to the view, which renders a template or
+/// serializes the records. Writes accept a form or a JSON body and answer
+/// with [`Format::respond`], a redirect for browsers and the record for JSON.
+///
+/// The routes of the router are described in the OpenAPI document of the
+/// app, see [`crate::openapi`], with the schemas of the `Record` and the
+/// `EntityChangeset`. Mount the handlers at `PATH`, `PATH/batch` and
+/// `PATH/{id}` like the generated controllers and add the controller to
+/// [`resources`], which both the router and the document are built from.
+///
 /// ## Example
 ///
 /// ```rust
@@ -31,24 +51,32 @@ pub mod todos;
 /// impl Controller for ExampleController {
 ///     type Id = i64;
 ///     type View = ExampleView;
+///     type Record = Example;
 ///     type EntityChangeset = ExampleChangeset;
 ///     type Error = ExampleError;
-///     
+///     const PATH: &'static str = "/examples";
 ///
 ///     fn router() -> Router<AppState> {
+///         let item = format!("{}/{{id}}", Self::PATH);
+///
 ///         Router::new()
-///         .route("/", get(Self::index))
-///         .route("/", post(Self::create))
-///         .route("/:id", get(Self::show))
-///         .route("/:id", put(Self::update))
-///         .route("/:id", delete(Self::delete));
+///         .route(Self::PATH, post(Self::create))
+///         .route(&format!("{}/batch", Self::PATH), post(Self::create_batch))
+///         .route(&item, put(Self::update).delete(Self::delete))
+///         // Only users with the permission may write, see `permission_required!`
+///         .route_layer(permission_required!("examples.write"))
+///         .route(Self::PATH, get(Self::read_all))
+///         .route(&item, get(Self::read_one))
 ///     }
 ///
 ///     fn index(
+///         format: Format,
 ///         State(app_state): State<AppState>,
 ///         flashes: IncomingFlashes,
+///         permissions: Permissions,
+///         Query(page): Query<PageRequest>,
 ///         ) -> Result<(IncomingFlashes, Self::View), Self::Error> {
-///         // your handler implementation here
+///         // your handler implementation here, pass the format and the permissions on to the view
 ///         Ok((flashes, view))
 ///         }
 ///         // ...other methods
@@ -57,56 +85,197 @@ pub mod todos;
 
 #[async_trait]
 pub trait Controller {
-    type Id: PartialOrd;
+    type Id: PartialOrd + JsonSchema;
     type View: IntoResponse;
-    type EntityChangeset: Validate + DeserializeOwned;
+    /// The record JSON clients get back, described in the API docs like the changeset
+    type Record: Serialize + JsonSchema;
+    type EntityChangeset: Validate + DeserializeOwned + JsonSchema;
     type Error: IntoResponse;
 
+    /// Where the records are routed, e.g. `/todos`
+    const PATH: &'static str;
+
     /// Produces a app router with all methods for the Controller
     fn router() -> Router<AppState>;
 
-    /// Index handler to list all records
+    /// Index handler to list a page of records, see [`PageRequest`] for the query parameters
     async fn read_all(
+        format: Format,
         v: ViewEngine<View>,
         flashes: IncomingFlashes,
+        permissions: Permissions,
         State(app_state): State<AppState>,
+        Query(page): Query<PageRequest>,
     ) -> Result<(IncomingFlashes, Self::View), Self::Error>;
 
-    /// Create handler to create a new record
+    /// Create handler to create a new record, browsers get the form again when the record is
+    /// invalid
+    #[allow(clippy::too_many_arguments)]
     async fn create(
+        format: Format,
+        hx: HxRequest,
+        v: ViewEngine<View>,
         flash: Flash,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
         State(app_state): State<AppState>,
-        Form(record): Form<Self::EntityChangeset>,
-    ) -> Result<(Flash, Redirect), Self::Error>;
+        FormOrJson(record): FormOrJson<Self::EntityChangeset>,
+    ) -> Result<Response, Self::Error>;
 
     async fn create_batch(
+        format: Format,
         flash: Flash,
         State(app_state): State<AppState>,
-        Form(records): Form<Vec<Self::EntityChangeset>>,
-    ) -> Result<(Flash, Redirect), Self::Error>;
+        FormOrJson(records): FormOrJson<Vec<Self::EntityChangeset>>,
+    ) -> Result<Response, Self::Error>;
 
     /// Show handler to display a single record
     async fn read_one(
+        format: Format,
         v: ViewEngine<View>,
         flashes: IncomingFlashes,
+        permissions: Permissions,
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
     ) -> Result<(IncomingFlashes, Self::View), Self::Error>;
 
-    /// Update handler to update a single record
+    /// Update handler to update a single record, htmx requests get the updated form back and
+    /// browsers get the form again when the record is invalid
+    #[allow(clippy::too_many_arguments)]
     async fn update(
+        format: Format,
+        hx: HxRequest,
+        v: ViewEngine<View>,
         flash: Flash,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
-        form: Form<Self::EntityChangeset>,
-    ) -> Result<(Flash, Redirect), Self::Error>;
+        FormOrJson(record): FormOrJson<Self::EntityChangeset>,
+    ) -> Result<Response, Self::Error>;
 
     /// Delete handler to delete a single record
     async fn delete(
+        format: Format,
+        flash: Flash,
+        Path(id): Path<Self::Id>,
+        State(app_state): State<AppState>,
+    ) -> Result<Response, Self::Error>;
+}
+
+/// ------------------------------------------------------------------------
+/// # A Controller for models owned by the user who created them
+/// ------------------------------------------------------------------------
+///
+/// The same CRUD set of handlers as [`Controller`], for entities deriving
+/// [`shipwright_db::OwnedEntity`]. Every handler is given the [`AuthSession`]
+/// and only ever touches the records of its user, records of other users
+/// are answered with `404 Not Found`.
+///
+/// Route an OwnedController behind `require_login`.
+/// ------------------------------------------------------------------------
+#[async_trait]
+pub trait OwnedController {
+    type Id: PartialOrd + JsonSchema;
+    type View: IntoResponse;
+    /// The record JSON clients get back, described in the API docs like the changeset
+    type Record: Serialize + JsonSchema;
+    type EntityChangeset: Validate + DeserializeOwned + JsonSchema;
+    type Error: IntoResponse;
+
+    /// Where the records are routed, e.g. `/todos`
+    const PATH: &'static str;
+
+    /// Produces a app router with all methods for the OwnedController
+    fn router() -> Router<AppState>;
+
+    /// Index handler to list a page of the user's records
+    async fn read_all(
+        format: Format,
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
+        auth_session: AuthSession,
+        State(app_state): State<AppState>,
+        Query(page): Query<PageRequest>,
+    ) -> Result<(IncomingFlashes, Self::View), Self::Error>;
+
+    /// Create handler to create a new record owned by the user, browsers get the form again
+    /// when the record is invalid
+    #[allow(clippy::too_many_arguments)]
+    async fn create(
+        format: Format,
+        hx: HxRequest,
+        v: ViewEngine<View>,
+        flash: Flash,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
+        auth_session: AuthSession,
+        State(app_state): State<AppState>,
+        FormOrJson(record): FormOrJson<Self::EntityChangeset>,
+    ) -> Result<Response, Self::Error>;
+
+    async fn create_batch(
+        format: Format,
+        flash: Flash,
+        auth_session: AuthSession,
+        State(app_state): State<AppState>,
+        FormOrJson(records): FormOrJson<Vec<Self::EntityChangeset>>,
+    ) -> Result<Response, Self::Error>;
+
+    /// Show handler to display a single record of the user
+    async fn read_one(
+        format: Format,
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
+        auth_session: AuthSession,
+        Path(id): Path<Self::Id>,
+        State(app_state): State<AppState>,
+    ) -> Result<(IncomingFlashes, Self::View), Self::Error>;
+
+    /// Update handler to update a single record of the user, htmx requests get the updated
+    /// form back and browsers get the form again when the record is invalid
+    #[allow(clippy::too_many_arguments)]
+    async fn update(
+        format: Format,
+        hx: HxRequest,
+        v: ViewEngine<View>,
+        flash: Flash,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
+        auth_session: AuthSession,
+        Path(id): Path<Self::Id>,
+        State(app_state): State<AppState>,
+        FormOrJson(record): FormOrJson<Self::EntityChangeset>,
+    ) -> Result<Response, Self::Error>;
+
+    /// Delete handler to delete a single record of the user
+    async fn delete(
+        format: Format,
         flash: Flash,
+        auth_session: AuthSession,
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
-    ) -> Result<(Flash, Redirect), Self::Error>;
+    ) -> Result<Response, Self::Error>;
+}
+/// Visits the record controllers of the app, see [`resources`].
+pub trait Resources: Sized {
+    fn controller<C: Controller>(self) -> Self;
+
+    fn owned_controller<C: OwnedController>(self) -> Self;
 }
+
+/// The record controllers of the app, add generated controllers here.
+///
+/// The router mounts and the OpenAPI document describes the same controllers, so neither can
+/// miss one the other has.
+pub fn resources<R: Resources>(resources: R) -> R {
+    resources
+        .owned_controller::<todos::TodoController>()
+        .controller::<lion::LionController>()
+        .controller::<invoice::InvoiceController>()
+}
+
 pub mod invoice;
 pub mod lion;
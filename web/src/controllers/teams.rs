@@ -0,0 +1,312 @@
+use axum::{
+    Extension, Form, Router,
+    extract::{Path, Query, State},
+    response::Redirect,
+    routing::{get, post},
+};
+use serde::Deserialize;
+use shipwright_db::{
+    Entity as _,
+    entities::{
+        team::{CreateTeam, Membership, Team, TeamRole},
+        team_invitation::{AcceptInvitation, InviteMember, TeamInvitation},
+    },
+    transaction,
+};
+use shipwright_mailer::{EmailPayload, invite::InviteMailer};
+use shipwright_ui::view_engine::{View, ViewEngine};
+use shipwright_worker::{Storage, WorkerStorage};
+
+use crate::{
+    error::Error,
+    middlewares::{
+        auth::AuthSession,
+        flash::{Flash, IncomingFlashes},
+    },
+    return_url::ReturnUrl,
+    state::AppState,
+    views::teams::TeamView,
+};
+
+// The invite token is sent to the invitee as a link, so it arrives in the query string.
+#[derive(Debug, Deserialize)]
+pub struct InviteTokenQuery {
+    token: Option<String>,
+}
+
+/// Teams of the logged in user, owners invite new members by email.
+pub struct TeamController;
+
+impl TeamController {
+    pub fn router() -> Router<AppState> {
+        Router::new()
+            .route(
+                "/teams",
+                get(TeamController::index).post(TeamController::create),
+            )
+            .route("/teams/{id}", get(TeamController::show))
+            .route("/teams/{id}/invitations", post(TeamController::invite))
+            .route(
+                "/teams/{id}/invitations/{invitation_id}/revoke",
+                post(TeamController::revoke),
+            )
+    }
+
+    pub async fn index(
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+    ) -> Result<(IncomingFlashes, TeamView), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let memberships = Team::load_memberships(user.id, &state.db_pool).await?;
+
+        Ok((flashes.clone(), TeamView::Index(v, flashes, memberships)))
+    }
+
+    /// Creates a team with the user as its owner.
+    pub async fn create(
+        flash: Flash,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+        Form(form): Form<CreateTeam>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+
+        let mut tx = transaction(&state.db_pool).await?;
+        let team = Team::create(form, &mut *tx).await?;
+        Team::add_member(team.id, user.id, TeamRole::Owner, &mut *tx).await?;
+        tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+        Ok((
+            flash.success("✅ your team has been created"),
+            Redirect::to(&format!("/teams/{}", team.id)),
+        ))
+    }
+
+    /// The members of a team, owners also see the pending invitations.
+    pub async fn show(
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+        auth_session: AuthSession,
+        Path(id): Path<i64>,
+        State(state): State<AppState>,
+    ) -> Result<(IncomingFlashes, TeamView), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let membership = membership(id, user.id, &state).await?;
+
+        let members = Team::load_members(id, &state.db_pool).await?;
+        let invitations = if membership.is_owner() {
+            TeamInvitation::load_pending_for_team(id, &state.db_pool).await?
+        } else {
+            Vec::new()
+        };
+
+        Ok((
+            flashes.clone(),
+            TeamView::Show(v, flashes, membership, members, invitations),
+        ))
+    }
+
+    /// Sends an invitation link to an email, any earlier invitation to it stops working.
+    pub async fn invite(
+        flash: Flash,
+        auth_session: AuthSession,
+        Path(id): Path<i64>,
+        State(state): State<AppState>,
+        Extension(mut jobs): Extension<WorkerStorage<EmailPayload>>,
+        Form(form): Form<InviteMember>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let team_url = format!("/teams/{id}");
+        let membership = membership(id, user.id, &state).await?;
+        if !membership.is_owner() {
+            return Ok((
+                flash.error("❌ only owners can invite members"),
+                Redirect::to(&team_url),
+            ));
+        }
+
+        let mut tx = transaction(&state.db_pool).await?;
+        TeamInvitation::delete_for_email(id, &form.email, &mut *tx).await?;
+        let (invitation, invite_token) =
+            TeamInvitation::create(id, &form, user.id, &mut *tx).await?;
+        tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+        jobs.push(InviteMailer::send_invitation(
+            &state.email_client,
+            &state.config,
+            &invitation.email,
+            &membership.name,
+            &user.email,
+            &invite_token,
+        ))
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to send invitation email: {:?}", e);
+        })
+        .ok();
+
+        Ok((
+            flash.success(format!(
+                "✅ an invitation is on its way to {}",
+                invitation.email
+            )),
+            Redirect::to(&team_url),
+        ))
+    }
+
+    /// Revokes a pending invitation, its link stops working.
+    pub async fn revoke(
+        flash: Flash,
+        auth_session: AuthSession,
+        Path((id, invitation_id)): Path<(i64, i64)>,
+        State(state): State<AppState>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let team_url = format!("/teams/{id}");
+        if !membership(id, user.id, &state).await?.is_owner() {
+            return Ok((
+                flash.error("❌ only owners can revoke invitations"),
+                Redirect::to(&team_url),
+            ));
+        }
+
+        if !TeamInvitation::revoke(id, invitation_id, &state.db_pool).await? {
+            return Err(Error::Database(shipwright_db::Error::NoRecordFound));
+        }
+
+        Ok((
+            flash.success("✅ the invitation has been revoked"),
+            Redirect::to(&team_url),
+        ))
+    }
+}
+
+/// The user's membership of a team, teams of others are answered with `404 Not Found`.
+async fn membership(team_id: i64, user_id: i64, state: &AppState) -> Result<Membership, Error> {
+    Team::try_get_membership(team_id, user_id, &state.db_pool)
+        .await?
+        .ok_or(Error::Database(shipwright_db::Error::NoRecordFound))
+}
+
+/// Joins a team with the token of an invitation link.
+///
+/// Invitees may not have an account yet, so the page is public and sends them to sign in or
+/// register with the page as their `next` stop.
+pub struct InvitationController;
+
+impl InvitationController {
+    pub fn router() -> Router<AppState> {
+        Router::new().route(
+            "/teams/invitations/accept",
+            get(InvitationController::index).post(InvitationController::accept),
+        )
+    }
+
+    /// The page the emailed link opens.
+    ///
+    /// Mail scanners follow links to check them, so opening the link only shows a button and
+    /// the invitation is used up by the form it posts.
+    pub async fn index(
+        v: ViewEngine<View>,
+        flashes: IncomingFlashes,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+        Query(InviteTokenQuery { token }): Query<InviteTokenQuery>,
+    ) -> Result<(IncomingFlashes, TeamView), Error> {
+        let invitation = match token.as_deref() {
+            Some(token) => TeamInvitation::try_get_valid(token, &state.db_pool).await?,
+            None => None,
+        };
+        let team = match &invitation {
+            Some(invitation) => Some(Team::load(invitation.team_id, &state.db_pool).await?),
+            None => None,
+        };
+        let next = token.as_deref().and_then(|token| accept_url(token, &state));
+
+        Ok((
+            flashes.clone(),
+            TeamView::Accept(
+                v,
+                flashes,
+                invitation.zip(team),
+                token,
+                next,
+                auth_session.user.is_some(),
+            ),
+        ))
+    }
+
+    /// Adds the user to the team, the invitation has to be sent to their email.
+    pub async fn accept(
+        flash: Flash,
+        auth_session: AuthSession,
+        State(state): State<AppState>,
+        Form(form): Form<AcceptInvitation>,
+    ) -> Result<(Flash, Redirect), Error> {
+        let accept_page = accept_url(&form.invite_token, &state);
+        let back = accept_page
+            .as_ref()
+            .map_or("/teams/invitations/accept".to_string(), |url| {
+                url.to_string()
+            });
+
+        let Some(user) = auth_session.user else {
+            let login_url = accept_page.map_or("/auth/login".to_string(), |url| {
+                url.append_to("/auth/login")
+            });
+            return Ok((
+                flash.info("sign in or register to accept the invitation"),
+                Redirect::to(&login_url),
+            ));
+        };
+
+        // The invitation is checked before it is used up, so the invitee can still accept it
+        // after signing in with the right account
+        match TeamInvitation::try_get_valid(&form.invite_token, &state.db_pool).await? {
+            None => {
+                return Ok((
+                    flash.error("❌ this invitation is invalid or has expired"),
+                    Redirect::to("/teams"),
+                ));
+            }
+            Some(invitation) if !invitation.email.eq_ignore_ascii_case(&user.email) => {
+                return Ok((
+                    flash.error(format!(
+                        "❌ this invitation was sent to {}, sign in with that email to accept it",
+                        invitation.email
+                    )),
+                    Redirect::to(&back),
+                ));
+            }
+            Some(_) => (),
+        }
+
+        let mut tx = transaction(&state.db_pool).await?;
+        // The invitation is deleted so the link can't be used again
+        let Some(invitation) = TeamInvitation::try_consume(&form, &mut *tx).await? else {
+            return Ok((
+                flash.error("❌ this invitation is invalid or has expired"),
+                Redirect::to("/teams"),
+            ));
+        };
+        Team::add_member(invitation.team_id, user.id, TeamRole::Member, &mut *tx).await?;
+        tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+        Ok((
+            flash.success("✅ welcome to the team"),
+            Redirect::to(&format!("/teams/{}", invitation.team_id)),
+        ))
+    }
+}
+
+/// The accept page of an invitation, where signing in or registering returns to.
+fn accept_url(invite_token: &str, state: &AppState) -> Option<ReturnUrl> {
+    let query = serde_urlencoded::to_string([("token", invite_token)]).ok()?;
+
+    ReturnUrl::parse(
+        &format!("/teams/invitations/accept?{query}"),
+        &state.config.server.host,
+    )
+}
@@ -1,112 +1,200 @@
 use async_trait::async_trait;
 use axum::{
-    Form, Router,
-    extract::{Path, State},
-    response::Redirect,
-    routing::{get, post},
+    Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post, put},
 };
 use shipwright_db::{
-    Entity,
+    OwnedEntity, PageRequest, Validate as _,
     entities::todo::{Todo, TodoChangeset},
 };
 use shipwright_ui::view_engine::{View, ViewEngine};
 
 use crate::{
     error::Error,
-    middlewares::flash::{Flash, IncomingFlashes},
+    form::FormState,
+    htmx::HxRequest,
+    middlewares::{
+        auth::{AuthSession, Permissions},
+        flash::{Flash, IncomingFlashes},
+    },
+    negotiation::{FormOrJson, Format},
+    permission_required,
     state::AppState,
     views::todos::TodoView,
 };
 
-use super::Controller;
+use super::OwnedController;
 
 pub struct TodoController;
 
 #[async_trait]
-impl Controller for TodoController {
+impl OwnedController for TodoController {
     type Id = i64;
 
     type View = TodoView;
 
+    type Record = Todo;
+
     type EntityChangeset = TodoChangeset;
 
     type Error = Error;
 
+    const PATH: &'static str = "/todos";
+
     fn router() -> Router<AppState> {
+        let item = format!("{}/{{id}}", Self::PATH);
+
         Router::new()
-            .route("/todos", get(Self::read_all).post(Self::create))
-            .route("/todos/batch", post(Self::create_batch))
-            .route(
-                "/todos/{id}",
-                get(Self::read_one).put(Self::update).delete(Self::delete),
-            )
+            .route(Self::PATH, post(Self::create))
+            .route(&format!("{}/batch", Self::PATH), post(Self::create_batch))
+            .route(&item, put(Self::update).delete(Self::delete))
+            .route_layer(permission_required!("todos.write"))
+            .route(Self::PATH, get(Self::read_all))
+            .route(&item, get(Self::read_one))
     }
 
     async fn read_all(
+        format: Format,
         v: ViewEngine<View>,
         flashes: IncomingFlashes,
+        permissions: Permissions,
+        auth_session: AuthSession,
         State(app_state): State<AppState>,
+        Query(page): Query<PageRequest>,
     ) -> Result<(IncomingFlashes, Self::View), Self::Error> {
-        let todos = Todo::load_all(&app_state.db_pool).await?;
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let todos = Todo::load_page_for_owner(user.id, &page, &app_state.db_pool).await?;
 
-        Ok((flashes.clone(), TodoView::Index(v, todos, flashes)))
+        Ok((
+            flashes.clone(),
+            TodoView::Index(format, v, todos, flashes, permissions),
+        ))
     }
 
     async fn create(
+        format: Format,
+        hx: HxRequest,
+        v: ViewEngine<View>,
         flash: Flash,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
+        auth_session: AuthSession,
         State(app_state): State<AppState>,
-        Form(record): Form<Self::EntityChangeset>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let todo = Todo::create(record, &app_state.db_pool).await?;
-
-        Ok((
-            flash.success("✅ created new todo"),
-            Redirect::to(&format!("/todos/{}", todo.id)),
-        ))
+        FormOrJson(record): FormOrJson<Self::EntityChangeset>,
+    ) -> Result<Response, Self::Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        if !format.is_json()
+            && let Err(errors) = record.validate()
+        {
+            let form = FormState::invalid(&errors).value("description", &record.description);
+            let todos =
+                Todo::load_page_for_owner(user.id, &PageRequest::default(), &app_state.db_pool)
+                    .await?;
+            return Ok((
+                flashes.clone(),
+                TodoView::CreateForm(hx, v, todos, form, flashes, permissions),
+            )
+                .into_response());
+        }
+        let todo = Todo::create_for_owner(user.id, record, &app_state.db_pool).await?;
+
+        Ok(format.respond(StatusCode::CREATED, todo, |todo| {
+            (
+                flash.success("✅ created new todo"),
+                Redirect::to(&format!("/todos/{}", todo.id)),
+            )
+        }))
     }
 
     async fn create_batch(
+        format: Format,
         flash: Flash,
+        auth_session: AuthSession,
         State(app_state): State<AppState>,
-        Form(records): Form<Vec<Self::EntityChangeset>>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let _records = Todo::create_batch(records, &app_state.db_pool).await?;
-
-        Ok((flash.success("✅ created todos"), Redirect::to("/todos")))
+        FormOrJson(records): FormOrJson<Vec<Self::EntityChangeset>>,
+    ) -> Result<Response, Self::Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let todos = Todo::create_batch_for_owner(user.id, records, &app_state.db_pool).await?;
+
+        Ok(format.respond(StatusCode::CREATED, todos, |_| {
+            (flash.success("✅ created todos"), Redirect::to("/todos"))
+        }))
     }
 
     async fn read_one(
+        format: Format,
         v: ViewEngine<View>,
         flashes: IncomingFlashes,
+        permissions: Permissions,
+        auth_session: AuthSession,
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
     ) -> Result<(IncomingFlashes, Self::View), Self::Error> {
-        let todo = Todo::load(id, &app_state.db_pool).await?;
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let todo = Todo::load_for_owner(id, user.id, &app_state.db_pool).await?;
 
-        Ok((flashes.clone(), TodoView::Show(v, todo, flashes)))
+        Ok((
+            flashes.clone(),
+            TodoView::Show(format, v, todo, flashes, permissions),
+        ))
     }
 
     async fn update(
+        format: Format,
+        hx: HxRequest,
+        v: ViewEngine<View>,
         flash: Flash,
+        flashes: IncomingFlashes,
+        permissions: Permissions,
+        auth_session: AuthSession,
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
-        Form(form): Form<Self::EntityChangeset>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let todo = Todo::update(id, form, &app_state.db_pool).await?;
-
-        Ok((
-            flash.success("✅ updated todo"),
-            Redirect::to(&format!("/todos/{}", todo.id)),
-        ))
+        FormOrJson(record): FormOrJson<Self::EntityChangeset>,
+    ) -> Result<Response, Self::Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        if !format.is_json()
+            && let Err(errors) = record.validate()
+        {
+            let form = FormState::invalid(&errors).value("description", &record.description);
+            let todo = Todo::load_for_owner(id, user.id, &app_state.db_pool).await?;
+            return Ok((
+                flashes.clone(),
+                TodoView::UpdateForm(hx, v, todo, form, flashes, permissions),
+            )
+                .into_response());
+        }
+        let todo = Todo::update_for_owner(id, user.id, record, &app_state.db_pool).await?;
+
+        // The form swaps itself, the flash goes out as an event
+        if hx.is_partial() && !format.is_json() {
+            return Ok(
+                (flash.success("✅ updated todo"), TodoView::Update(v, todo)).into_response(),
+            );
+        }
+
+        Ok(format.respond(StatusCode::OK, todo, |todo| {
+            (
+                flash.success("✅ updated todo"),
+                Redirect::to(&format!("/todos/{}", todo.id)),
+            )
+        }))
     }
 
     async fn delete(
+        format: Format,
         flash: Flash,
+        auth_session: AuthSession,
         Path(id): Path<Self::Id>,
         State(app_state): State<AppState>,
-    ) -> Result<(Flash, Redirect), Self::Error> {
-        let _todo = Todo::delete(id, &app_state.db_pool).await?;
+    ) -> Result<Response, Self::Error> {
+        let user = auth_session.user.ok_or(Error::Unauthenticated)?;
+        let todo = Todo::delete_for_owner(id, user.id, &app_state.db_pool).await?;
 
-        Ok((flash.info("deleted todo"), Redirect::to("/todos")))
+        Ok(format.respond(StatusCode::OK, todo, |_| {
+            (flash.info("deleted todo"), Redirect::to("/todos"))
+        }))
     }
 }
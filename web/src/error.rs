@@ -8,6 +8,9 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use color_eyre::eyre;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use tracing::error;
 
 pub type Result<T, E = Error> = color_eyre::Result<T, E>;
@@ -16,6 +19,22 @@ pub type Result<T, E = Error> = color_eyre::Result<T, E>;
 pub enum Error {
     #[error("Failed to load configuration: {0}")]
     Config(#[from] shipwright_config::Error),
+    /// The cookie signing keys in `cookie` are missing or malformed.
+    ///
+    /// Only happens when the app starts.
+    #[error("invalid cookie signing keys: {0}")]
+    CookieKey(String),
+    /// A form was posted without the csrf token of the session, see
+    /// [`crate::middlewares::csrf`].
+    ///
+    /// Return `403 Forbidden`, the page the form is on has to be reloaded.
+    #[error("the form has expired or did not come from this site, reload the page and try again")]
+    InvalidCsrfToken,
+    /// A request body is larger than the app reads.
+    ///
+    /// Return `413 Payload Too Large`.
+    #[error("the request is too large")]
+    PayloadTooLarge,
     /// Unauthenticated user
     ///
     /// Return a `401 Unauthorized` response on an invalid register token.
@@ -26,6 +45,22 @@ pub enum Error {
     /// Return a `401 Unauthorized` response on an unauthenticated user.
     #[error("unauthenticated user")]
     Unauthenticated,
+    /// A business rule of the account context was broken.
+    ///
+    /// Return `403 Forbidden` on a pending account and `429 Too Many Requests` on a throttled
+    /// login.
+    #[error(transparent)]
+    Account(#[from] shipwright_context::Error),
+    /// No provider with this name is configured in `auth.oauth`.
+    ///
+    /// Return `404 Not Found` on an unknown provider.
+    #[error("unknown oauth provider: {0}")]
+    UnknownOAuthProvider(String),
+    /// The OAuth2 / OpenID Connect provider refused the authorization code or failed to answer.
+    ///
+    /// Return `502 Bad Gateway` on a provider error.
+    #[error("an error occured while talking to the oauth provider")]
+    OAuth(#[from] reqwest::Error),
     /// Could not render template
     ///
     /// Return `500 Internal Server Error` on a template rendering error.
@@ -64,6 +99,11 @@ pub enum Error {
 
     #[error(transparent)]
     InvalidMethod(#[from] InvalidMethod),
+    /// No route matches the requested url.
+    ///
+    /// Return `404 Not Found`.
+    #[error("page not found")]
+    PageNotFound,
     /// Enumerate any possible app arrors here.
     ///
     /// Return `500 Internal Server Error` on a `eyre::Error`.
@@ -75,6 +115,30 @@ impl Error {
     fn status_code(&self) -> StatusCode {
         match self {
             Error::Unauthenticated | Error::InvalidRegisterToken => StatusCode::UNAUTHORIZED,
+            Error::Account(shipwright_context::Error::InvalidCredentials) => {
+                StatusCode::UNAUTHORIZED
+            }
+            Error::Account(shipwright_context::Error::InvalidTotpCode) => StatusCode::UNAUTHORIZED,
+            Error::Account(shipwright_context::Error::InvalidTotpSecret(_)) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            Error::Account(shipwright_context::Error::PendingConfirmation) => StatusCode::FORBIDDEN,
+            Error::Account(shipwright_context::Error::UnverifiedEmail) => StatusCode::FORBIDDEN,
+            Error::Account(shipwright_context::Error::InvalidApiToken) => StatusCode::UNAUTHORIZED,
+            Error::Account(shipwright_context::Error::InsufficientScope) => StatusCode::FORBIDDEN,
+            Error::Account(shipwright_context::Error::TooManyAttempts { .. }) => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            Error::Account(shipwright_context::Error::PasswordHash(_)) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            Error::Account(shipwright_context::Error::Validation(_)) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            Error::InvalidCsrfToken => StatusCode::FORBIDDEN,
+            Error::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Error::UnknownOAuthProvider(_) | Error::PageNotFound => StatusCode::NOT_FOUND,
+            Error::OAuth(_) => StatusCode::BAD_GATEWAY,
             Error::ViewEngine(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::Database(shipwright_db::Error::NoRecordFound) => StatusCode::NOT_FOUND,
             Error::Database(shipwright_db::Error::UniqueConstraint(_)) => {
@@ -89,6 +153,10 @@ impl Error {
             Error::Database(shipwright_db::Error::PasswordHashError(_)) => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
+            Error::Database(shipwright_db::Error::UnsupportedBackend(_)) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            Error::Database(shipwright_db::Error::InvalidPageRequest(_)) => StatusCode::BAD_REQUEST,
             Error::Mailer(shipwright_mailer::Error::Request(_)) => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
@@ -103,96 +171,222 @@ impl Error {
             Error::InvalidHeaderName(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::InvalidMethod(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::CookieKey(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
-}
 
-impl IntoResponse for Error {
-    fn into_response(self) -> Response {
+    /// What the user is told about an error they can do something about.
+    ///
+    /// Errors of the app itself have none, their details are only logged.
+    fn detail(&self) -> Option<String> {
         match self {
-            Error::InvalidRegisterToken => {
-                // TODO: Return a invalid register token view here.
-                return (self.status_code(), "invalid register token".to_string()).into_response();
+            Error::InvalidRegisterToken
+            | Error::Unauthenticated
+            | Error::InvalidCsrfToken
+            | Error::PayloadTooLarge
+            | Error::UnknownOAuthProvider(_)
+            | Error::PageNotFound
+            | Error::Account(shipwright_context::Error::InvalidCredentials)
+            | Error::Account(shipwright_context::Error::PendingConfirmation)
+            | Error::Account(shipwright_context::Error::TooManyAttempts { .. })
+            | Error::Account(shipwright_context::Error::InvalidTotpCode)
+            | Error::Account(shipwright_context::Error::UnverifiedEmail)
+            | Error::Account(shipwright_context::Error::InvalidApiToken)
+            | Error::Account(shipwright_context::Error::InsufficientScope) => {
+                Some(self.to_string())
             }
-            Error::Unauthenticated => {
-                // TODO: Return a not authenticated view here.
-                return (self.status_code(), "unauthenticated".to_string()).into_response();
+            Error::Database(err @ shipwright_db::Error::NoRecordFound) => Some(err.to_string()),
+            Error::Account(shipwright_context::Error::Validation(err))
+            | Error::Database(shipwright_db::Error::ValidationError(err)) => Some(err.to_string()),
+            Error::Database(shipwright_db::Error::UniqueConstraint(_)) => {
+                Some("a record with the same values already exists".to_string())
             }
-            Error::ViewEngine(ref err) => {
-                // TODO: Return a not found view here.
-                error!("an error occured while rendering a template: {:?}", err);
-                return (self.status_code(), err.to_string()).into_response();
+            Error::Database(shipwright_db::Error::InvalidPageRequest(reason)) => {
+                Some(reason.to_string())
             }
+            _ => None,
+        }
+    }
 
-            Error::Database(shipwright_db::Error::NoRecordFound) => {
-                // TODO: Return a not found view here.
+    /// The messages of each invalid field.
+    fn field_errors(&self) -> Option<FieldErrors> {
+        match self {
+            Error::Account(shipwright_context::Error::Validation(err))
+            | Error::Database(shipwright_db::Error::ValidationError(err)) => {
+                Some(field_errors(err))
+            }
+            // The message of the database names the table and the constraint
+            Error::Database(shipwright_db::Error::UniqueConstraint(fields)) => Some(
+                fields
+                    .iter()
+                    .map(|(field, _)| (field.clone(), vec!["has already been taken".to_string()]))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
 
-                return (self.status_code(), "no record found".to_string()).into_response();
+    /// Logs the errors of the app itself and returns their details.
+    fn log(&self) -> Option<String> {
+        match self {
+            Error::Account(shipwright_context::Error::InvalidTotpSecret(err)) => {
+                error!("an error occured while building a totp: {}", err);
+            }
+            Error::Account(shipwright_context::Error::PasswordHash(err)) => {
+                error!("an error occured while verifying a password: {:?}", err);
             }
-            Error::Database(shipwright_db::Error::UniqueConstraint(ref _err)) => {
-                // TODO: Return a unique constaint error view here.
+            Error::OAuth(err) => {
+                error!(
+                    "an error occured while talking to the oauth provider: {:?}",
+                    err
+                );
             }
-            Error::Database(shipwright_db::Error::ValidationError(ref err)) => {
-                // TODO: Return a validation error view here.
-                return (self.status_code(), err.to_string()).into_response();
+            Error::ViewEngine(err) => {
+                error!("an error occured while rendering a template: {:?}", err);
             }
-            Error::Database(shipwright_db::Error::DatabaseError(ref err)) => {
+            Error::Database(shipwright_db::Error::DatabaseError(err)) => {
                 error!(
                     "an error occured while interacting with the database: {:?}",
                     err
                 );
-                return (self.status_code(), err.to_string()).into_response();
             }
-            Error::Database(shipwright_db::Error::PasswordHashError(ref err)) => {
-                // TODO: Return a password hash error view here.
+            Error::Database(shipwright_db::Error::PasswordHashError(err)) => {
                 error!("an error occured while hashing a password: {:?}", err);
             }
-            Error::Mailer(shipwright_mailer::Error::Request(ref err)) => {
+            Error::Database(shipwright_db::Error::UnsupportedBackend(backend)) => {
+                error!("database url points at an unsupported backend: {}", backend);
+            }
+            Error::Mailer(shipwright_mailer::Error::Request(err)) => {
                 error!("an error occured while sending email request: {:?}", err);
             }
-            Error::Mailer(shipwright_mailer::Error::Validation(ref err)) => {
+            Error::Mailer(shipwright_mailer::Error::Validation(err)) => {
                 error!("invalid inputs to mailer: {:?}", err);
             }
-
-            Error::Worker(ref err) => {
+            Error::Worker(err) => {
                 error!("an error occured while interacting with worker: {:?}", err);
             }
-
-            Error::Http(ref err) => {
+            Error::Http(err) => {
                 error!("an error occured while interacting with http: {:?}", err);
             }
-
-            Error::JSON(ref err) => {
+            Error::JSON(err) => {
                 error!("an error occured while parsing json: {:?}", err);
             }
-
-            Error::JsonRejection(ref err) => {
+            Error::JsonRejection(err) => {
                 error!("an error occured while parsing json: {:?}", err);
             }
-
-            Error::InvalidHeaderValue(ref err) => {
+            Error::InvalidHeaderValue(err) => {
                 error!("an error occured while parsing header value: {:?}", err);
             }
-
-            Error::InvalidHeaderName(ref err) => {
+            Error::InvalidHeaderName(err) => {
                 error!("an error occured while parsing header name: {:?}", err);
             }
-
-            Error::InvalidMethod(ref err) => {
+            Error::InvalidMethod(err) => {
                 error!("an error occured while parsing method: {:?}", err);
             }
-
-            Error::Config(ref err) => {
+            Error::Config(err) => {
                 error!("an error occured while loading configuration: {:?}", err);
             }
-
-            Error::Unexpected(ref err) => {
+            Error::CookieKey(reason) => {
+                error!("invalid cookie signing keys: {}", reason);
+            }
+            Error::Unexpected(err) => {
                 error!("an internal server error occured: {:?}", err);
             }
+            _ => return None,
+        }
+
+        Some(format!("{self:?}"))
+    }
+}
+
+/// The messages of each invalid field, keyed by the name of the field.
+pub type FieldErrors = BTreeMap<String, Vec<String>>;
+
+/// The messages of each invalid field of a changeset, the code of a rule stands in for a
+/// missing message.
+pub fn field_errors(errors: &shipwright_db::ValidationErrors) -> FieldErrors {
+    errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let messages = errors
+                .iter()
+                .map(|error| {
+                    error
+                        .message
+                        .as_ref()
+                        .map_or_else(|| error.code.to_string(), |message| message.to_string())
+                })
+                .collect();
+            (field.to_string(), messages)
+        })
+        .collect()
+}
+
+/// An error as told to the client, an RFC 7807 problem details object.
+///
+/// JSON clients get it as `application/problem+json`, browsers get it rendered with the error
+/// templates of `view.error_templates`.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Problem {
+    /// Problems are only told apart by their status, see RFC 7807 section 4.2
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub title: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// The messages of each invalid field of a `422 Unprocessable Entity`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<FieldErrors>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            kind: "about:blank",
+            title: status
+                .canonical_reason()
+                .unwrap_or("Unknown Error")
+                .to_string(),
+            status: status.as_u16(),
+            detail: None,
+            errors: None,
         }
+    }
+}
+
+/// Put in the extensions of the response to an error, so
+/// [`crate::middlewares::errors::render_errors`] can answer in the format the client asked for.
+#[derive(Debug, Clone)]
+pub struct ErrorReport {
+    pub problem: Problem,
+    /// What went wrong inside the app, it is logged and only shown in development.
+    pub internal: Option<String>,
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        let problem = Problem {
+            detail: self.detail(),
+            errors: self.field_errors(),
+            ..Problem::new(status)
+        };
+        let report = ErrorReport {
+            internal: self.log(),
+            problem,
+        };
 
-        // TODO: Return a default error view here.
-        self.status_code().into_response()
+        // Plain text in case the response doesn't pass the middleware
+        let text = report
+            .problem
+            .detail
+            .clone()
+            .unwrap_or_else(|| report.problem.title.clone());
+        let mut response = (status, text).into_response();
+        response.extensions_mut().insert(report);
+        response
     }
 }
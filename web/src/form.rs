@@ -0,0 +1,46 @@
+//! Forms that failed validation are shown again, with the submitted values and the errors of
+//! each field.
+//!
+//! Handlers validate the changeset a browser posted before writing it, an invalid one is
+//! answered with [`crate::format::RenderBuilder::invalid_form`]. The template gets a [`FormState`]
+//! as `form`, and the `partials/form.html` macros show the errors next to their fields:
+//!
+//! ```html
+//! {% import "partials/form.html" as forms %}
+//! <input type="text" name="description" value="{{ form.values.description if form }}" />
+//! {{ forms.field_errors(form, "description") }}
+//! ```
+//!
+//! JSON clients don't need this, they get the errors of each field in the problem details of
+//! [`crate::error::Error`].
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use shipwright_db::ValidationErrors;
+
+use crate::error::{FieldErrors, field_errors};
+
+/// The submitted values and the errors of each field of an invalid form.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FormState {
+    pub values: Map<String, Value>,
+    pub errors: FieldErrors,
+}
+
+impl FormState {
+    pub fn invalid(errors: &ValidationErrors) -> Self {
+        Self {
+            values: Map::new(),
+            errors: field_errors(errors),
+        }
+    }
+
+    /// Refills `field` with the submitted `value`.
+    ///
+    /// Values are refilled one by one so secrets like passwords are never sent back.
+    pub fn value(mut self, field: &str, value: impl Serialize) -> Self {
+        let value = serde_json::to_value(value).unwrap_or_default();
+        self.values.insert(field.to_string(), value);
+        self
+    }
+}
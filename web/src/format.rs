@@ -20,7 +20,11 @@
 //! }
 //! ```
 
-use crate::error::{Error, Result};
+use crate::{
+    error::{Error, Result},
+    htmx::{self, HX_PUSH_URL, HX_REDIRECT, HX_RETARGET, HxRequest},
+    negotiation::Format,
+};
 use axum::{
     Json,
     body::Body,
@@ -223,6 +227,40 @@ impl RenderBuilder {
         }
     }
 
+    /// Send the browser to `to` with a full page load, for htmx requests
+    #[must_use]
+    pub fn hx_redirect(self, to: &str) -> Self {
+        self.header(HX_REDIRECT, to)
+    }
+
+    /// Trigger the client side `event` with its `detail`, for htmx requests
+    ///
+    /// ```rust,ignore
+    /// format::render()
+    ///     .hx_trigger("todo-added", json!({ "id": todo.id }))
+    ///     .view_block(&v, "todos/index.html", "list", data)
+    /// ```
+    #[must_use]
+    pub fn hx_trigger(mut self, event: &str, detail: serde_json::Value) -> Self {
+        if let Some(headers) = self.response.headers_mut() {
+            htmx::add_trigger(headers, event, detail);
+        }
+        self
+    }
+
+    /// Swap the response into the element matching the CSS `selector` instead of the target
+    /// of the request, for htmx requests
+    #[must_use]
+    pub fn hx_retarget(self, selector: &str) -> Self {
+        self.header(HX_RETARGET, selector)
+    }
+
+    /// Push `url` into the browser history, for htmx requests
+    #[must_use]
+    pub fn hx_push_url(self, url: &str) -> Self {
+        self.header(HX_PUSH_URL, url)
+    }
+
     /// Add an etag
     ///
     /// # Errors
@@ -288,6 +326,77 @@ impl RenderBuilder {
         self.html(&content)
     }
 
+    /// Render only the block named `block` of the template located by `key`
+    ///
+    /// htmx requests that swap a part of a page are answered with the block of the page
+    /// rendering that part, see [`crate::htmx::HxRequest::is_partial`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if rendering fails
+    pub fn view_block<V, S>(self, v: &V, key: &str, block: &str, data: S) -> Result<Response>
+    where
+        V: ViewRenderer,
+        S: Serialize,
+    {
+        let content = v.render_block(key, block, data)?;
+        self.html(&content)
+    }
+
+    /// Render the page located by `key` again for a form that failed validation, with
+    /// `422 Unprocessable Entity`
+    ///
+    /// Partial htmx requests only get the `block` holding the form, see [`crate::form`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if rendering fails
+    pub fn invalid_form<V, S>(
+        self,
+        hx: &HxRequest,
+        v: &V,
+        key: &str,
+        block: &str,
+        data: S,
+    ) -> Result<Response>
+    where
+        V: ViewRenderer,
+        S: Serialize,
+    {
+        let builder = self.status(StatusCode::UNPROCESSABLE_ENTITY);
+        if hx.is_partial() {
+            builder.view_block(v, key, block, data)
+        } else {
+            builder.view(v, key, data)
+        }
+    }
+
+    /// Render template located by `key` for browsers, JSON clients get `record` instead
+    ///
+    /// See [`Format`] for how the format is picked.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if rendering or serializing fails
+    pub fn negotiate<V, S, T>(
+        self,
+        format: Format,
+        v: &V,
+        key: &str,
+        data: S,
+        record: T,
+    ) -> Result<Response>
+    where
+        V: ViewRenderer,
+        S: Serialize,
+        T: Serialize,
+    {
+        match format {
+            Format::Html => self.view(v, key, data),
+            Format::Json => self.json(record),
+        }
+    }
+
     /// Render template located by `key`
     ///
     /// # Errors
@@ -0,0 +1,173 @@
+//! Support for requests sent by htmx, see <https://htmx.org/reference/#headers>.
+//!
+//! [`HxRequest`] tells a handler whether htmx sent the request and what it is going to swap, so
+//! it can answer with a part of a page, see [`crate::format::RenderBuilder::view_block`]. The
+//! response headers htmx reacts to are set with the `hx_*` methods of
+//! [`crate::format::RenderBuilder`].
+//!
+//! Answers to htmx requests are read by javascript rather than the browser, so redirects become
+//! an `HX-Location` header ([`crate::middlewares::htmx::hx_redirects`]) and flash messages an
+//! `HX-Trigger` event ([`crate::middlewares::flash::Flash`]).
+
+use std::convert::Infallible;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, HeaderName, HeaderValue, request::Parts},
+};
+use serde_json::{Map, Value};
+
+/// `true` on every request htmx sends.
+pub const HX_REQUEST: HeaderName = HeaderName::from_static("hx-request");
+/// The id of the element the response is swapped into, if it has one.
+pub const HX_TARGET: HeaderName = HeaderName::from_static("hx-target");
+/// `true` on requests of links and forms enhanced with `hx-boost`.
+pub const HX_BOOSTED: HeaderName = HeaderName::from_static("hx-boosted");
+
+/// Sends the browser to another page with a full page load.
+pub const HX_REDIRECT: HeaderName = HeaderName::from_static("hx-redirect");
+/// Loads another page like a boosted link, without a full page load.
+pub const HX_LOCATION: HeaderName = HeaderName::from_static("hx-location");
+/// Triggers client side events, either a comma separated list of names or a JSON object of
+/// names and their details.
+pub const HX_TRIGGER: HeaderName = HeaderName::from_static("hx-trigger");
+/// A CSS selector of the element to swap the response into instead of the target.
+pub const HX_RETARGET: HeaderName = HeaderName::from_static("hx-retarget");
+/// Pushes a url into the browser history.
+pub const HX_PUSH_URL: HeaderName = HeaderName::from_static("hx-push-url");
+
+/// The htmx headers of a request.
+///
+/// ```rust,ignore
+/// async fn update(hx: HxRequest, /* .. */) -> Result<Response> {
+///     if hx.is_partial() {
+///         return format::render().view_block(&v, "todos/show.html", "update", data);
+///     }
+///     format::redirect("/todos")
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HxRequest {
+    /// Whether htmx sent the request.
+    pub is_htmx: bool,
+    /// Whether the request comes from an element with `hx-boost`.
+    pub boosted: bool,
+    /// The id of the target element.
+    pub target: Option<String>,
+}
+
+impl HxRequest {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let is_true = |name: &HeaderName| headers.get(name).is_some_and(|value| value == "true");
+
+        Self {
+            is_htmx: is_true(&HX_REQUEST),
+            boosted: is_true(&HX_BOOSTED),
+            target: headers
+                .get(HX_TARGET)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        }
+    }
+
+    /// Whether htmx swaps the response into the current page.
+    ///
+    /// Boosted requests stand in for normal links and forms, they are answered like them.
+    pub fn is_partial(&self) -> bool {
+        self.is_htmx && !self.boosted
+    }
+}
+
+impl<S> FromRequestParts<S> for HxRequest
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(HxRequest::from_headers(&parts.headers))
+    }
+}
+
+/// Adds the `event` with its `detail` to the `HX-Trigger` header, keeping the events already in
+/// it.
+pub fn add_trigger(headers: &mut HeaderMap, event: &str, detail: Value) {
+    let mut events = headers
+        .get(HX_TRIGGER)
+        .and_then(|value| value.to_str().ok())
+        .map(parse_trigger)
+        .unwrap_or_default();
+    events.insert(event.to_string(), detail);
+
+    let value = ascii_json(&Value::Object(events));
+    headers.insert(
+        HX_TRIGGER,
+        HeaderValue::from_str(&value).expect("escaped JSON is visible ASCII"),
+    );
+}
+
+/// Serializes JSON with non ASCII characters escaped, browsers read header values as latin-1.
+fn ascii_json(value: &Value) -> String {
+    let mut escaped = String::new();
+    for c in value.to_string().chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            let mut units = [0; 2];
+            for unit in c.encode_utf16(&mut units) {
+                escaped.push_str(&format!("\\u{unit:04x}"));
+            }
+        }
+    }
+    escaped
+}
+
+fn parse_trigger(value: &str) -> Map<String, Value> {
+    match serde_json::from_str(value) {
+        Ok(Value::Object(events)) => events,
+        _ => value
+            .split(',')
+            .map(str::trim)
+            .filter(|event| !event.is_empty())
+            .map(|event| (event.to_string(), Value::Null))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn boosted_requests_are_not_partial() {
+        let mut headers = HeaderMap::new();
+        assert!(!HxRequest::from_headers(&headers).is_htmx);
+
+        headers.insert(HX_REQUEST, HeaderValue::from_static("true"));
+        headers.insert(HX_TARGET, HeaderValue::from_static("todo-form"));
+        let hx = HxRequest::from_headers(&headers);
+        assert!(hx.is_partial());
+        assert_eq!(hx.target.as_deref(), Some("todo-form"));
+
+        headers.insert(HX_BOOSTED, HeaderValue::from_static("true"));
+        assert!(!HxRequest::from_headers(&headers).is_partial());
+    }
+
+    #[test]
+    fn triggers_are_merged() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HX_TRIGGER, HeaderValue::from_static("saved, closed"));
+
+        add_trigger(&mut headers, "flash", json!({ "messages": ["✅ saved"] }));
+
+        let value = headers[HX_TRIGGER].to_str().unwrap();
+        assert!(value.contains(r"\u2705 saved"));
+        let events: Value = serde_json::from_str(value).unwrap();
+        assert_eq!(
+            events,
+            json!({ "saved": null, "closed": null, "flash": { "messages": ["✅ saved"] } })
+        );
+    }
+}
@@ -1,8 +1,14 @@
 pub mod app;
 pub mod controllers;
 pub mod error;
+pub mod form;
 pub mod format;
+pub mod htmx;
 pub mod middlewares;
+pub mod negotiation;
+pub mod oauth;
+pub mod openapi;
+pub mod return_url;
 pub mod router;
 pub mod state;
 pub mod tracing;
@@ -1,33 +1,186 @@
+use std::collections::HashSet;
+
 use async_trait::async_trait;
-use axum_login::{AuthManagerLayer, AuthManagerLayerBuilder, AuthnBackend, UserId};
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::request::Parts,
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_login::{AuthManagerLayer, AuthManagerLayerBuilder, AuthnBackend, AuthzBackend, UserId};
+use serde::Serialize;
+use shipwright_context::{Account, IdentityLink};
 use shipwright_db::{
     DbPool,
-    entities::user::{User, UserCredentials},
+    entities::{
+        api_token::{ApiScope, ApiToken},
+        identity::Identity,
+        role::{Permission, Role},
+        session::Session,
+        user::{User, UserCredentials, UserStatus},
+    },
+    transaction,
 };
-use password_auth::verify_password;
+use time::OffsetDateTime;
 use tokio::task::{self, JoinHandle};
 use tower_sessions::{
     ExpiredDeletion, Expiry, SessionManagerLayer,
-    cookie::{Key, time::Duration},
+    cookie::time::Duration,
     session_store,
 };
-use tower_sessions_sqlx_store::SqliteStore;
 
-use crate::{error::Error, state::AppState};
+use crate::{error::Error, oauth::OAuthClient, return_url::ReturnUrl, state::AppState};
 
 // We use a type alias for convenience.
 //
 // Note that we've supplied our concrete backend here.
 pub type AuthSession = axum_login::AuthSession<AuthBackend>;
 
+/// Sessions are stored in the primary database, so the store follows the `postgres` feature.
+#[cfg(not(feature = "postgres"))]
+pub type SessionStore = tower_sessions_sqlx_store::SqliteStore;
+#[cfg(feature = "postgres")]
+pub type SessionStore = tower_sessions_sqlx_store::PostgresStore;
+
+/// The ways a user can prove who they are.
+#[derive(Debug, Clone)]
+pub enum Credentials {
+    /// An email and password from the login form.
+    Password(UserCredentials),
+    /// An authorization code from an OAuth2 / OpenID Connect provider.
+    OAuth(OAuthCredentials),
+    /// A personal access token from an `Authorization: Bearer` header.
+    ApiToken(ApiTokenCredentials),
+}
+
+/// The authorization code a provider sent back to the callback, with the PKCE code verifier of
+/// the login that started the flow.
+#[derive(Debug, Clone)]
+pub struct OAuthCredentials {
+    pub provider: String,
+    pub code: String,
+    pub code_verifier: String,
+}
+
+/// A personal access token and the scope the request it came with needs.
+#[derive(Clone)]
+pub struct ApiTokenCredentials {
+    pub token: String,
+    pub scope: ApiScope,
+}
+
+// Here we've implemented `Debug` manually to avoid accidentally logging the token.
+impl std::fmt::Debug for ApiTokenCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiTokenCredentials")
+            .field("token", &"[redacted]")
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AuthBackend {
     db: DbPool,
+    oauth: OAuthClient,
 }
 
 impl AuthBackend {
-    pub fn new(db: DbPool) -> Self {
-        Self { db }
+    pub fn new(db: DbPool, oauth: OAuthClient) -> Self {
+        Self { db, oauth }
+    }
+
+    pub fn oauth(&self) -> &OAuthClient {
+        &self.oauth
+    }
+
+    async fn authenticate_password(&self, creds: UserCredentials) -> Result<Option<User>, Error> {
+        let Some(user) = User::try_get_by_email(&creds.email, &self.db).await? else {
+            return Ok(None);
+        };
+        // Verifying the password is blocking and potentially slow, so we'll do so via
+        // `spawn_blocking`.
+        task::spawn_blocking(move || {
+            // The account context decides whether the credentials are good enough to log in,
+            // a wrong password is reported as no user so the login form can ask again.
+            match Account::validate_credentials(&user, &creds) {
+                Ok(()) => Ok(Some(user)),
+                Err(shipwright_context::Error::InvalidCredentials) => Ok(None),
+                Err(e) => Err(Error::Account(e)),
+            }
+        })
+        .await
+        .map_err(|e| Error::Unexpected(e.into()))?
+    }
+
+    async fn authenticate_oauth(&self, creds: OAuthCredentials) -> Result<Option<User>, Error> {
+        let claims = self
+            .oauth
+            .fetch_claims(&creds.provider, &creds.code, &creds.code_verifier)
+            .await?;
+
+        let identity = Identity::try_get_by_subject(&creds.provider, &claims.sub, &self.db).await?;
+        let user = match (&identity, &claims.email) {
+            (None, Some(email)) => User::try_get_by_email(email, &self.db).await?,
+            _ => None,
+        };
+
+        let user_id = match Account::link_identity(identity.as_ref(), user.as_ref(), &claims)? {
+            IdentityLink::Existing(user_id) => user_id,
+            IdentityLink::Link(user_id) => {
+                let mut tx = transaction(&self.db).await?;
+                Identity::create(user_id, &creds.provider, &claims.sub, &mut *tx).await?;
+                tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+                user_id
+            }
+            IdentityLink::Claim { user_id, password } => {
+                let mut tx = transaction(&self.db).await?;
+                User::update_password(user_id, &password, &mut *tx).await?;
+                Session::delete_all_for_user(user_id, None, &mut *tx).await?;
+                // The provider has verified the email, so the pending registration is confirmed
+                User::update_status(user_id, UserStatus::Confirmed, &mut *tx).await?;
+                Identity::create(user_id, &creds.provider, &claims.sub, &mut *tx).await?;
+                tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+                user_id
+            }
+            IdentityLink::Register(new_user) => {
+                let mut tx = transaction(&self.db).await?;
+                let user = User::create(new_user, &mut *tx).await?;
+                Role::assign(user.id, Role::MEMBER, &mut *tx).await?;
+                User::update_status(user.id, UserStatus::Confirmed, &mut *tx).await?;
+                Identity::create(user.id, &creds.provider, &claims.sub, &mut *tx).await?;
+                tx.commit().await.map_err(|e| Error::Database(e.into()))?;
+
+                user.id
+            }
+        };
+
+        let user = User::try_get_by_id(&user_id, &self.db).await?;
+        Ok(user)
+    }
+
+    async fn authenticate_api_token(
+        &self,
+        creds: ApiTokenCredentials,
+    ) -> Result<Option<User>, Error> {
+        let token_hash = Account::hash_api_token(&creds.token);
+        let Some(token) = ApiToken::try_get_by_hash(&token_hash, &self.db).await? else {
+            return Ok(None);
+        };
+
+        let now = OffsetDateTime::now_utc();
+        match Account::authorize_api_token(&token, creds.scope, now) {
+            Ok(()) => (),
+            Err(shipwright_context::Error::InvalidApiToken) => return Ok(None),
+            Err(e) => return Err(Error::Account(e)),
+        }
+
+        ApiToken::touch(token.id, now, &self.db).await?;
+
+        let user = User::try_get_by_id(&token.user_id, &self.db).await?;
+        Ok(user)
     }
 }
 
@@ -37,23 +190,18 @@ impl AuthBackend {
 #[async_trait]
 impl AuthnBackend for AuthBackend {
     type User = User;
-    type Credentials = UserCredentials;
+    type Credentials = Credentials;
     type Error = Error;
 
     async fn authenticate(
         &self,
         creds: Self::Credentials,
     ) -> Result<Option<Self::User>, Self::Error> {
-        let user: Option<Self::User> = User::try_get_by_email(&creds.email, &self.db).await?;
-        // Verifying the password is blocking and potentially slow, so we'll do so via
-        // `spawn_blocking`.
-        task::spawn_blocking(|| {
-            // We're using password-based authentication--this works by comparing our form
-            // input with an argon2 password hash.
-            Ok(user.filter(|user| verify_password(creds.password, &user.password_hash).is_ok()))
-        })
-        .await
-        .map_err(|e| Error::Unexpected(e.into()))?
+        match creds {
+            Credentials::Password(creds) => self.authenticate_password(creds).await,
+            Credentials::OAuth(creds) => self.authenticate_oauth(creds).await,
+            Credentials::ApiToken(creds) => self.authenticate_api_token(creds).await,
+        }
     }
 
     async fn get_user(&self, user_id: &UserId<Self>) -> Result<Option<Self::User>, Self::Error> {
@@ -62,6 +210,96 @@ impl AuthnBackend for AuthBackend {
     }
 }
 
+/// ------------------------------------------------------------------------
+/// Permissions are granted through the roles a user holds.
+/// ------------------------------------------------------------------------
+#[async_trait]
+impl AuthzBackend for AuthBackend {
+    type Permission = Permission;
+
+    async fn get_group_permissions(
+        &self,
+        user: &Self::User,
+    ) -> Result<HashSet<Self::Permission>, Self::Error> {
+        let permissions = Role::load_permissions_for_user(user.id, &self.db).await?;
+        Ok(permissions.into_iter().collect())
+    }
+}
+
+/// Guards routes on the permissions of the current user, anyone without all of them gets a
+/// `403 Forbidden`.
+///
+/// ```rust,ignore
+/// Router::new()
+///     .route("/todos", post(Self::create))
+///     .route_layer(permission_required!("todos.write"))
+/// ```
+#[macro_export]
+macro_rules! permission_required {
+    ($($perm:expr),+ $(,)?) => {
+        ::axum_login::permission_required!($crate::middlewares::auth::AuthBackend, $($perm),+)
+    };
+}
+
+/// Where [`require_login`] sends visitors.
+const LOGIN_URL: &str = "/auth/login";
+
+/// Sends visitors to the login page, which brings them back to the page they asked for.
+///
+/// Works like `login_required!` with a `login_url`, except the `next` parameter is a
+/// [`ReturnUrl`] like any other.
+///
+/// ```rust,ignore
+/// Router::new()
+///     .route("/todos", get(Self::index))
+///     .route_layer(middleware::from_fn_with_state(app_state.clone(), require_login))
+/// ```
+pub async fn require_login(
+    State(state): State<AppState>,
+    auth_session: AuthSession,
+    req: Request,
+    next: Next,
+) -> Response {
+    if auth_session.user.is_some() {
+        return next.run(req).await;
+    }
+
+    let login_url = req
+        .uri()
+        .path_and_query()
+        .and_then(|path| ReturnUrl::parse(path.as_str(), &state.config.server.host))
+        .map(|next| next.append_to(LOGIN_URL))
+        .unwrap_or_else(|| LOGIN_URL.to_string());
+
+    Redirect::temporary(&login_url).into_response()
+}
+
+/// Permissions extracts what the current user may do, so templates can hide what they may not.
+///
+/// Serializes to the list of permission names, anonymous visitors have none.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Permissions(HashSet<Permission>);
+
+impl<S> FromRequestParts<S> for Permissions
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let auth_session = AuthSession::from_request_parts(parts, state)
+            .await
+            .map_err(|(_, msg)| Error::Unexpected(color_eyre::eyre::eyre!(msg)))?;
+
+        let permissions = match &auth_session.user {
+            Some(user) => auth_session.backend.get_all_permissions(user).await?,
+            None => HashSet::new(),
+        };
+
+        Ok(Self(permissions))
+    }
+}
+
 /// ------------------------------------------------------------------------
 /// A convenience struct to build and manage the authentication session.
 /// ------------------------------------------------------------------------
@@ -74,13 +312,20 @@ impl AuthnBackend for AuthBackend {
 pub struct AuthSessionManager {
     pub deletion_task: JoinHandle<Result<(), session_store::Error>>,
     pub auth_layer:
-        AuthManagerLayer<AuthBackend, SqliteStore, tower_sessions::service::SignedCookie>,
+        AuthManagerLayer<AuthBackend, SessionStore, tower_sessions::service::SignedCookie>,
 }
 
 impl AuthSessionManager {
     pub fn new(app_state: &AppState) -> Self {
-        // Connect to the session store in sqlite.
-        let session_store = SqliteStore::new(app_state.db_pool.clone())
+        // Connect to the session store in the primary database.
+        let session_store = SessionStore::new(app_state.db_pool.clone());
+        // The Postgres store defaults to its own schema, the sessions table
+        // is created by our migrations in the public schema.
+        #[cfg(feature = "postgres")]
+        let session_store = session_store
+            .with_schema_name("public")
+            .expect("invalid session store schema name");
+        let session_store = session_store
             .with_table_name("sessions")
             .expect("unable to connect to session store");
         // Panic here as this is a fatal error
@@ -92,8 +337,8 @@ impl AuthSessionManager {
                 .continuously_delete_expired(tokio::time::Duration::from_secs(60)),
         );
 
-        // Generate a cryptographic key to sign the session cookie.
-        let key = Key::generate();
+        // Sign the session cookie with the configured key, see `CookieKeys` for rotation.
+        let key = app_state.cookie_keys.signing_key().clone();
 
         let session_layer = SessionManagerLayer::new(session_store)
             .with_secure(true)
@@ -104,7 +349,10 @@ impl AuthSessionManager {
         //
         // This combines the session layer with our backend to establish the auth
         // service which will provide the auth session as a request extension.
-        let backend = AuthBackend::new(app_state.db_pool.clone());
+        let backend = AuthBackend::new(
+            app_state.db_pool.clone(),
+            OAuthClient::new(&app_state.config),
+        );
         let auth_layer = AuthManagerLayerBuilder::new(backend, session_layer).build();
 
         Self {
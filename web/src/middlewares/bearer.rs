@@ -0,0 +1,53 @@
+//! Middleware authenticating requests with a personal access token.
+//!
+//! A request with an `Authorization: Bearer <token>` header is logged in as the owner of the
+//! token for that request only, nothing is written to the session. Put it in front of
+//! `login_required!` so handlers see the same [`AuthSession::user`] as for a cookie session.
+
+use axum::{
+    extract::Request,
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use shipwright_db::entities::api_token::ApiScope;
+
+use crate::{
+    error::Error,
+    middlewares::auth::{ApiTokenCredentials, AuthSession, Credentials},
+};
+
+pub async fn bearer_auth(mut auth_session: AuthSession, mut req: Request, next: Next) -> Response {
+    let Some(token) = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return next.run(req).await;
+    };
+
+    // Read tokens can only make requests that don't change anything
+    let scope = match req.method().is_safe() {
+        true => ApiScope::Read,
+        false => ApiScope::Write,
+    };
+    let credentials = Credentials::ApiToken(ApiTokenCredentials {
+        token: token.to_string(),
+        scope,
+    });
+
+    let user = match auth_session.authenticate(credentials).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return Error::Account(shipwright_context::Error::InvalidApiToken).into_response();
+        }
+        Err(axum_login::Error::Backend(e)) => return e.into_response(),
+        Err(e) => return Error::Unexpected(e.into()).into_response(),
+    };
+
+    auth_session.user = Some(user);
+    req.extensions_mut().insert(auth_session);
+
+    next.run(req).await
+}
@@ -0,0 +1,108 @@
+//! Extractor for the ip address of the client making the request.
+//!
+//! By default this is the ip of the connection, which is only known when the app is served with
+//! `into_make_service_with_connect_info`. Behind a proxy every connection comes from the proxy,
+//! so with `server.trusted_proxy` turned on the ip is read from `server.client_ip_header`
+//! instead. The header is never read otherwise, anyone can send it.
+
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{HeaderMap, request::Parts},
+};
+use shipwright_config::ServerConfig;
+
+use crate::state::AppState;
+
+/// The ip of the client, if it could be found.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl ClientIp {
+    /// The header proxies usually put the client ip in.
+    const DEFAULT_HEADER: &str = "x-forwarded-for";
+
+    fn resolve(headers: &HeaderMap, connection: Option<IpAddr>, config: &ServerConfig) -> Self {
+        if !config.trusted_proxy {
+            return ClientIp(connection);
+        }
+
+        let header = config
+            .client_ip_header
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_HEADER);
+        let ip = headers
+            .get(header)
+            .and_then(|value| value.to_str().ok())
+            // The proxy appends the ip it saw to whatever the client sent, so only the last
+            // entry can be trusted
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+
+        ClientIp(ip.or(connection))
+    }
+}
+
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let connection = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        Ok(ClientIp::resolve(
+            &parts.headers,
+            connection,
+            &state.config.server,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(header: Option<&str>, trusted_proxy: bool) -> Option<IpAddr> {
+        let mut headers = HeaderMap::new();
+        if let Some(header) = header {
+            headers.insert("x-forwarded-for", header.parse().unwrap());
+        }
+        let config = ServerConfig {
+            trusted_proxy,
+            ..ServerConfig::default()
+        };
+
+        ClientIp::resolve(&headers, Some([10, 0, 0, 1].into()), &config).0
+    }
+
+    #[test]
+    fn the_header_is_ignored_without_a_trusted_proxy() {
+        assert_eq!(
+            resolve(Some("203.0.113.7"), false),
+            Some([10, 0, 0, 1].into())
+        );
+    }
+
+    #[test]
+    fn the_ip_the_proxy_appended_is_used() {
+        assert_eq!(
+            resolve(Some("198.51.100.1, 203.0.113.7"), true),
+            Some([203, 0, 113, 7].into())
+        );
+    }
+
+    #[test]
+    fn the_connection_is_used_when_the_proxy_sent_no_ip() {
+        assert_eq!(resolve(None, true), Some([10, 0, 0, 1].into()));
+        assert_eq!(resolve(Some("unknown"), true), Some([10, 0, 0, 1].into()));
+    }
+}
@@ -0,0 +1,230 @@
+//! The keys signing the session and flash cookies, see [`CookieConfig`].
+//!
+//! The session and flash layers only know a single key, the first one. During a rotation
+//! [`resign_cookies`] runs in front of them and re-signs cookies that were signed with one of the
+//! older keys, so they keep working until they expire or are replaced.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, header::COOKIE},
+    middleware::Next,
+    response::Response,
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use shipwright_config::{CookieConfig, Environment};
+use tower_sessions::cookie::{Cookie, CookieJar, Key};
+
+/// The cookie signing keys, the first one signs new cookies.
+#[derive(Clone)]
+pub struct CookieKeys(Arc<Vec<Key>>);
+
+impl CookieKeys {
+    /// Loads the keys from `cookie.keys` followed by the lines of `cookie.keys_file`.
+    ///
+    /// Without any keys a random key is generated, except in staging and production. Fails with
+    /// the reason the keys are unusable.
+    pub fn from_config(config: &CookieConfig, env: &Environment) -> Result<Self, String> {
+        let mut encoded = config.keys.clone();
+        if let Some(path) = &config.keys_file {
+            let file = std::fs::read_to_string(path)
+                .map_err(|e| format!("could not read the keys file {path}: {e}"))?;
+            encoded.extend(
+                file.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+
+        let keys = encoded
+            .iter()
+            .map(|key| Self::parse(key))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if !keys.is_empty() {
+            return Ok(Self(Arc::new(keys)));
+        }
+
+        match env {
+            Environment::Staging | Environment::Production => {
+                Err("no keys are configured, set cookie.keys or cookie.keys_file".to_string())
+            }
+            Environment::Development => {
+                tracing::warn!("no cookie keys are configured, sessions end on every restart");
+                Ok(Self(Arc::new(vec![Key::generate()])))
+            }
+            Environment::Test => Ok(Self(Arc::new(vec![Key::generate()]))),
+        }
+    }
+
+    /// Decodes a base64 encoded key of at least 64 bytes.
+    pub fn parse(encoded: &str) -> Result<Key, String> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .map_err(|_| "a key is not valid base64".to_string())?;
+
+        Key::try_from(bytes.as_slice()).map_err(|_| "a key is shorter than 64 bytes".to_string())
+    }
+
+    /// Generates a new base64 encoded key.
+    pub fn generate() -> String {
+        STANDARD.encode(Key::generate().master())
+    }
+
+    /// The key new cookies are signed with.
+    pub fn signing_key(&self) -> &Key {
+        &self.0[0]
+    }
+
+    /// Re-signs a cookie signed with one of the older keys, `None` if there is nothing to do.
+    fn resign(&self, cookie: Cookie<'static>) -> Option<String> {
+        let name = cookie.name().to_string();
+        let mut jar = CookieJar::new();
+        jar.add_original(cookie);
+
+        if jar.signed(self.signing_key()).get(&name).is_some() {
+            return None;
+        }
+
+        let verified = self.0[1..]
+            .iter()
+            .find_map(|key| jar.signed(key).get(&name))?;
+
+        let mut resigned = CookieJar::new();
+        resigned.signed_mut(self.signing_key()).add(verified);
+        resigned
+            .get(&name)
+            .map(|cookie| cookie.stripped().to_string())
+    }
+
+    /// Rewrites the `Cookie` headers so every cookie signed with an older key is signed with the
+    /// signing key.
+    fn resign_headers(&self, headers: &mut HeaderMap) {
+        let mut resigned = false;
+        let cookies: Vec<String> = headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .map(str::trim)
+            .filter(|raw| !raw.is_empty())
+            .map(|raw| {
+                match Cookie::parse(raw.to_string())
+                    .ok()
+                    .and_then(|cookie| self.resign(cookie))
+                {
+                    Some(cookie) => {
+                        resigned = true;
+                        cookie
+                    }
+                    None => raw.to_string(),
+                }
+            })
+            .collect();
+
+        if !resigned {
+            return;
+        }
+
+        if let Ok(value) = HeaderValue::from_str(&cookies.join("; ")) {
+            headers.remove(COOKIE);
+            headers.insert(COOKIE, value);
+        }
+    }
+}
+
+pub async fn resign_cookies(
+    State(keys): State<CookieKeys>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    // With a single key there is nothing to rotate from
+    if keys.0.len() > 1 {
+        keys.resign_headers(req.headers_mut());
+    }
+
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed(key: &Key, name: &str, value: &str) -> String {
+        let mut jar = CookieJar::new();
+        jar.signed_mut(key)
+            .add(Cookie::new(name.to_string(), value.to_string()));
+        jar.get(name).unwrap().stripped().to_string()
+    }
+
+    fn keys(keys: &[&Key]) -> CookieKeys {
+        CookieKeys(Arc::new(keys.iter().map(|key| (*key).clone()).collect()))
+    }
+
+    #[test]
+    fn generated_keys_can_be_parsed() {
+        let encoded = CookieKeys::generate();
+
+        let key = CookieKeys::parse(&encoded).unwrap();
+
+        assert_eq!(STANDARD.encode(key.master()), encoded);
+    }
+
+    #[test]
+    fn short_and_malformed_keys_are_refused() {
+        assert!(CookieKeys::parse(&STANDARD.encode([7u8; 32])).is_err());
+        assert!(CookieKeys::parse("not base64!").is_err());
+    }
+
+    #[test]
+    fn keys_are_required_in_production() {
+        let config = CookieConfig::default();
+
+        assert!(CookieKeys::from_config(&config, &Environment::Production).is_err());
+        assert!(CookieKeys::from_config(&config, &Environment::Test).is_ok());
+    }
+
+    #[test]
+    fn the_first_configured_key_signs() {
+        let (old, new) = (CookieKeys::generate(), CookieKeys::generate());
+        let config = CookieConfig {
+            keys: vec![new.clone(), old],
+            keys_file: None,
+        };
+
+        let keys = CookieKeys::from_config(&config, &Environment::Production).unwrap();
+
+        assert_eq!(STANDARD.encode(keys.signing_key().master()), new);
+    }
+
+    #[test]
+    fn cookies_signed_with_an_old_key_are_resigned() {
+        let (old, new) = (Key::generate(), Key::generate());
+        let keys = keys(&[&new, &old]);
+        let mut headers = HeaderMap::new();
+        let cookie = format!("{}; theme=dark", signed(&old, "id", "session"));
+        headers.insert(COOKIE, HeaderValue::from_str(&cookie).unwrap());
+
+        keys.resign_headers(&mut headers);
+
+        let header = headers.get(COOKIE).unwrap().to_str().unwrap();
+        assert_eq!(
+            header,
+            format!("{}; theme=dark", signed(&new, "id", "session"))
+        );
+    }
+
+    #[test]
+    fn cookies_signed_with_an_unknown_key_are_left_alone() {
+        let keys = keys(&[&Key::generate(), &Key::generate()]);
+        let mut headers = HeaderMap::new();
+        let cookie = signed(&Key::generate(), "id", "session");
+        headers.insert(COOKIE, HeaderValue::from_str(&cookie).unwrap());
+
+        keys.resign_headers(&mut headers);
+
+        assert_eq!(headers.get(COOKIE).unwrap().to_str().unwrap(), cookie);
+    }
+}
@@ -0,0 +1,153 @@
+//! Middleware protecting against cross-site request forgery.
+//!
+//! Every session gets a random token, which is handed to the templates as the `csrf_token` and
+//! `csrf_field` globals. A request that could change something, anything but `GET`, `HEAD`,
+//! `OPTIONS` and `TRACE`, has to send the token back, either in the `_csrf` field of a urlencoded
+//! form or in the `X-CSRF-Token` header, which `base.html` sets for every htmx request. Another
+//! site can make a browser post to the app, but it can't read the token.
+//!
+//! Requests with an `Authorization` header are let through untouched, browsers never add one on
+//! their own and the api authenticates them with a token anyway. Put it inside the auth layer,
+//! which provides the session.
+
+use axum::{
+    body::{Body, to_bytes},
+    extract::{Request, State},
+    http::{
+        HeaderMap, HeaderName,
+        header::{AUTHORIZATION, CONTENT_TYPE},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http_body_util::LengthLimitError;
+use shipwright_context::Account;
+use shipwright_ui::view_engine::RequestGlobals;
+
+use crate::{error::Error, state::AppState};
+
+/// The session key the token is stored under.
+const SESSION_KEY: &str = "csrf_token";
+
+/// The form field forms send the token in, see the `csrf_field` template global.
+pub const FORM_FIELD: &str = "_csrf";
+
+/// The header htmx and scripts send the token in.
+pub static HEADER: HeaderName = HeaderName::from_static("x-csrf-token");
+
+/// The largest form body that is read looking for the token, the same as axum's default limit.
+const FORM_LIMIT: usize = 2 * 1024 * 1024;
+
+pub async fn verify_csrf(
+    State(state): State<AppState>,
+    session: tower_sessions::Session,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    if !state.config.csrf.enabled || req.headers().contains_key(AUTHORIZATION) {
+        return next.run(req).await;
+    }
+
+    let token = match session_token(&session).await {
+        Ok(token) => token,
+        Err(e) => return Error::Unexpected(e.into()).into_response(),
+    };
+
+    if !req.method().is_safe() {
+        let (given, checked) = match submitted_token(req).await {
+            Ok(submitted) => submitted,
+            Err(e) => return e.into_response(),
+        };
+
+        if !given.is_some_and(|given| Account::verify_csrf_token(&token, &given)) {
+            return Error::InvalidCsrfToken.into_response();
+        }
+
+        req = checked;
+    }
+
+    let mut globals = req
+        .extensions()
+        .get::<RequestGlobals>()
+        .cloned()
+        .unwrap_or_default();
+    globals.insert_html(
+        "csrf_field",
+        format!(r#"<input type="hidden" name="{FORM_FIELD}" value="{token}" />"#),
+    );
+    globals.insert("csrf_token", token);
+    req.extensions_mut().insert(globals);
+
+    next.run(req).await
+}
+
+/// The token of the session, a session without one gets a new token.
+async fn session_token(
+    session: &tower_sessions::Session,
+) -> Result<String, tower_sessions::session::Error> {
+    if let Some(token) = session.get::<String>(SESSION_KEY).await? {
+        return Ok(token);
+    }
+
+    let token = Account::generate_csrf_token();
+    session.insert(SESSION_KEY, &token).await?;
+
+    Ok(token)
+}
+
+/// Finds the token a request sent, the form body is read and put back for the handler.
+async fn submitted_token(req: Request) -> Result<(Option<String>, Request), Error> {
+    if let Some(token) = header_token(req.headers()) {
+        return Ok((Some(token), req));
+    }
+
+    let is_form = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(mime::APPLICATION_WWW_FORM_URLENCODED.as_ref()));
+    if !is_form {
+        return Ok((None, req));
+    }
+
+    let (parts, body) = req.into_parts();
+    let bytes = to_bytes(body, FORM_LIMIT).await.map_err(|e| {
+        if exceeds_limit(&e) {
+            Error::PayloadTooLarge
+        } else {
+            Error::Unexpected(e.into())
+        }
+    })?;
+
+    let token = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&bytes)
+        .ok()
+        .and_then(|fields| {
+            fields
+                .into_iter()
+                .find(|(name, _)| name == FORM_FIELD)
+                .map(|(_, value)| value)
+        });
+
+    Ok((token, Request::from_parts(parts, Body::from(bytes))))
+}
+
+/// Whether reading a body failed because it is larger than the limit, the error of the limited
+/// body ends up wrapped in more than one layer.
+fn exceeds_limit(err: &axum::Error) -> bool {
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        if err.is::<LengthLimitError>() {
+            return true;
+        }
+        source = err.source();
+    }
+
+    false
+}
+
+fn header_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(&HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
@@ -0,0 +1,90 @@
+//! Middleware answering errors in the format the client asked for.
+//!
+//! [`Error`](crate::error::Error) leaves an [`ErrorReport`] in the extensions of its response.
+//! JSON clients get it as an RFC 7807 `application/problem+json` body, browsers get the error
+//! page of its status from `view.error_templates`. What went wrong inside the app is only shown
+//! in development, everywhere else it stays in the logs.
+
+use axum::{
+    extract::{Request, State},
+    http::{
+        HeaderValue,
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+    },
+    middleware::Next,
+    response::Response,
+};
+use serde_json::json;
+use shipwright_config::{Environment, ErrorTemplatesConfig};
+use shipwright_ui::view_engine::{View, ViewEngine, ViewRenderer};
+
+use crate::{error::ErrorReport, negotiation::Format, state::AppState};
+
+const PROBLEM_JSON: &str = "application/problem+json";
+
+pub async fn render_errors(
+    State(state): State<AppState>,
+    format: Format,
+    ViewEngine(v): ViewEngine<View>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(req).await;
+    let Some(report) = response.extensions().get::<ErrorReport>().cloned() else {
+        return response;
+    };
+    let internal = report
+        .internal
+        .filter(|_| state.env == Environment::Development);
+
+    let (mut parts, body) = response.into_parts();
+    let body = match format {
+        Format::Json => {
+            let mut problem = json!(report.problem);
+            if let Some(internal) = internal {
+                problem["internal"] = json!(internal);
+            }
+            parts
+                .headers
+                .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+            problem.to_string().into()
+        }
+        Format::Html => {
+            let template = template(&state.config.view.error_templates, report.problem.status);
+            let data = json!({
+                "title": report.problem.title,
+                "status": report.problem.status,
+                "detail": report.problem.detail,
+                "errors": report.problem.errors,
+                "internal": internal,
+            });
+            match v.render(template, data) {
+                Ok(page) => {
+                    parts.headers.insert(
+                        CONTENT_TYPE,
+                        HeaderValue::from_static(mime::TEXT_HTML_UTF_8.as_ref()),
+                    );
+                    page.into()
+                }
+                // The plain text of the error is still better than nothing
+                Err(e) => {
+                    tracing::error!("failed to render the error page {}: {:?}", template, e);
+                    body
+                }
+            }
+        }
+    };
+    parts.headers.remove(CONTENT_LENGTH);
+
+    Response::from_parts(parts, body)
+}
+
+fn template(templates: &ErrorTemplatesConfig, status: u16) -> &str {
+    match status {
+        401 => &templates.unauthorized,
+        404 => &templates.not_found,
+        422 => &templates.unprocessable_entity,
+        500 => &templates.internal_server_error,
+        _ => &templates.default,
+    }
+}
@@ -61,22 +61,27 @@ use axum::{
 };
 use axum_extra::extract::cookie::{self, Cookie, SignedCookieJar};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::{borrow::Cow, fmt};
 use std::{
     convert::{Infallible, TryInto},
     time::Duration,
 };
 
+use crate::htmx::{self, HxRequest};
+
 pub use axum_extra::extract::cookie::Key;
 
 /// Extractor for setting outgoing flash messages.
 ///
-/// The flashes will be stored in a signed cookie.
+/// The flashes will be stored in a signed cookie. Partial htmx requests don't load the page that
+/// reads the cookie, their flashes are sent as a `flash` event in the `HX-Trigger` header instead.
 #[derive(Clone)]
 pub struct Flash {
     flashes: Vec<FlashMessage>,
     use_secure_cookies: bool,
     key: Key,
+    htmx: bool,
 }
 
 impl fmt::Debug for Flash {
@@ -85,6 +90,7 @@ impl fmt::Debug for Flash {
             .field("flashes", &self.flashes)
             .field("use_secure_cookies", &self.use_secure_cookies)
             .field("key", &"REDACTED")
+            .field("htmx", &self.htmx)
             .finish()
     }
 }
@@ -132,13 +138,14 @@ where
 {
     type Rejection = Infallible;
 
-    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let config = Config::from_ref(state);
 
         Ok(Self {
             key: config.key,
             use_secure_cookies: config.use_secure_cookies,
             flashes: Default::default(),
+            htmx: HxRequest::from_headers(&parts.headers).is_partial(),
         })
     }
 }
@@ -148,7 +155,17 @@ const COOKIE_NAME: &str = "axum-flash";
 impl IntoResponseParts for Flash {
     type Error = Infallible;
 
-    fn into_response_parts(self, res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        if self.htmx {
+            let messages = self
+                .flashes
+                .iter()
+                .map(|flash| json!({ "level": flash.level.to_string(), "message": flash.message }))
+                .collect::<Vec<_>>();
+            htmx::add_trigger(res.headers_mut(), "flash", json!({ "messages": messages }));
+            return Ok(res);
+        }
+
         let json =
             serde_json::to_string(&self.flashes).expect("failed to serialize flash messages");
 
@@ -428,4 +445,32 @@ mod tests {
         let body = String::from_utf8(bytes.to_vec()).unwrap();
         assert_eq!(body, "Debug: Hi from flash!");
     }
+
+    #[tokio::test]
+    async fn htmx_requests_get_an_event() {
+        let config = Config::new(Key::generate()).use_secure_cookies(false);
+
+        let app = Router::new()
+            .route("/set-flash", get(set_flash))
+            .with_state(config);
+
+        async fn set_flash(flash: Flash) -> (Flash, Redirect) {
+            (flash.success("Hi from flash!"), Redirect::to("/"))
+        }
+
+        let request = Request::builder()
+            .uri("/set-flash")
+            .header("hx-request", "true")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        assert!(response.headers().get(header::SET_COOKIE).is_none());
+        let trigger: serde_json::Value =
+            serde_json::from_slice(response.headers()["hx-trigger"].as_bytes()).unwrap();
+        assert_eq!(
+            trigger,
+            json!({ "flash": { "messages": [{ "level": "success", "message": "Hi from flash!" }] } })
+        );
+    }
 }
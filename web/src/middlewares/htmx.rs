@@ -0,0 +1,30 @@
+//! Middleware turning redirects into something htmx can follow.
+//!
+//! Browsers follow the redirects of the requests htmx sends before htmx sees the response, the
+//! page a form redirects to ends up in the element the form swaps. Handlers can keep answering
+//! forms with a redirect, for partial htmx requests it is turned into an `HX-Location` header
+//! that loads the page like a boosted link. The other headers, e.g. the `HX-Trigger` of flash
+//! messages, are kept.
+
+use axum::{
+    extract::Request,
+    http::{StatusCode, header::LOCATION},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::htmx::{HX_LOCATION, HxRequest};
+
+pub async fn hx_redirects(hx: HxRequest, req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+
+    if hx.is_partial()
+        && response.status().is_redirection()
+        && let Some(location) = response.headers_mut().remove(LOCATION)
+    {
+        *response.status_mut() = StatusCode::OK;
+        response.headers_mut().insert(HX_LOCATION, location);
+    }
+
+    response
+}
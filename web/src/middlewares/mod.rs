@@ -1,2 +1,9 @@
 pub mod auth;
+pub mod bearer;
+pub mod client_ip;
+pub mod cookie_keys;
+pub mod csrf;
+pub mod errors;
 pub mod flash;
+pub mod htmx;
+pub mod session_activity;
@@ -0,0 +1,99 @@
+//! Middleware recording who a session belongs to and where it was last used.
+//!
+//! The session store only knows the id, data and expiry of a session. Every request of a logged
+//! in user stamps its session with the user, user agent, ip and time, so users can see where
+//! they are logged in and end sessions they don't recognise. Put it inside the auth layer.
+//!
+//! Logins stamp the new session right away with [`SessionClient::track`], so it is listed
+//! before the next request comes in.
+
+use std::{convert::Infallible, net::IpAddr};
+
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{header::USER_AGENT, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use shipwright_db::{DbPool, entities::session::Session};
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+    error::Error,
+    middlewares::{auth::AuthSession, client_ip::ClientIp},
+    state::AppState,
+};
+
+/// How long a session goes without being stamped again.
+const TRACK_INTERVAL: Duration = Duration::minutes(1);
+
+/// The client a request comes from, as recorded on its session.
+#[derive(Debug, Clone)]
+pub struct SessionClient {
+    user_agent: Option<String>,
+    ip: Option<IpAddr>,
+}
+
+impl SessionClient {
+    /// Stamps the stored session `id` with the user and this client, unless it was stamped for
+    /// the same user less than `interval` ago.
+    pub async fn track(
+        &self,
+        id: &str,
+        user_id: i64,
+        interval: Duration,
+        db_pool: &DbPool,
+    ) -> Result<(), Error> {
+        let now = OffsetDateTime::now_utc();
+
+        Session::track(
+            id,
+            user_id,
+            self.user_agent.as_deref(),
+            self.ip.map(|ip| ip.to_string()).as_deref(),
+            now,
+            now - interval,
+            db_pool,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl FromRequestParts<AppState> for SessionClient {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let ClientIp(ip) = ClientIp::from_request_parts(parts, state).await?;
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        Ok(SessionClient { user_agent, ip })
+    }
+}
+
+pub async fn track_session(
+    State(state): State<AppState>,
+    auth_session: AuthSession,
+    session: tower_sessions::Session,
+    client: SessionClient,
+    req: Request,
+    next: Next,
+) -> Response {
+    if let (Some(user), Some(id)) = (&auth_session.user, session.id())
+        && let Err(e) = client
+            .track(&id.to_string(), user.id, TRACK_INTERVAL, &state.db_pool)
+            .await
+    {
+        tracing::error!("failed to record session activity: {:?}", e);
+    }
+
+    next.run(req).await
+}
@@ -0,0 +1,199 @@
+//! Content negotiation, so one handler serves browsers and JSON clients.
+//!
+//! [`Format`] reads the `Accept` header of a request, [`FormOrJson`] reads a urlencoded form or a
+//! JSON body depending on its `Content-Type`. Views render a template or serialize their records
+//! with [`crate::format::RenderBuilder::negotiate`], writes answer with [`Format::respond`].
+//!
+//! JSON clients authenticated by the session have to send the `X-CSRF-Token` header like htmx
+//! does, see [`crate::middlewares::csrf`].
+
+use std::convert::Infallible;
+
+use axum::{
+    Form, Json,
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{
+        HeaderMap, StatusCode,
+        header::{ACCEPT, CONTENT_TYPE},
+        request::Parts,
+    },
+    response::{IntoResponse, Redirect, Response},
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::middlewares::flash::Flash;
+
+/// The format a client asked for in its `Accept` header.
+///
+/// Browsers ask for html, or for anything with `*/*`, so html is the default. JSON is only picked
+/// when the client prefers `application/json`, or a `+json` type, over html.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Html,
+    Json,
+}
+
+impl Format {
+    /// Picks the format from the value of an `Accept` header, the type with the highest `q` wins.
+    pub fn from_accept(accept: &str) -> Self {
+        let mut best = (Format::Html, 0.0);
+
+        for media in accept.split(',') {
+            let Ok(media) = media.trim().parse::<mime::Mime>() else {
+                continue;
+            };
+            let format = match (media.type_(), media.subtype(), media.suffix()) {
+                (mime::TEXT, mime::HTML, _) => Format::Html,
+                (mime::APPLICATION, subtype, _) if subtype == "xhtml" => Format::Html,
+                (mime::APPLICATION, mime::JSON, _) | (mime::APPLICATION, _, Some(mime::JSON)) => {
+                    Format::Json
+                }
+                _ => continue,
+            };
+            let quality = media
+                .get_param("q")
+                .and_then(|q| q.as_str().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            // Types of the same quality keep the order of the header
+            if quality > best.1 {
+                best = (format, quality);
+            }
+        }
+
+        best.0
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .map(Format::from_accept)
+            .unwrap_or_default()
+    }
+
+    pub fn is_json(self) -> bool {
+        self == Format::Json
+    }
+
+    /// Answers a write, JSON clients get the `record` with `status` and browsers are redirected.
+    ///
+    /// ```rust,ignore
+    /// Ok(format.respond(StatusCode::CREATED, todo, |todo| {
+    ///     (flash.success("✅ created new todo"), Redirect::to(&format!("/todos/{}", todo.id)))
+    /// }))
+    /// ```
+    pub fn respond<T, F>(self, status: StatusCode, record: T, redirect: F) -> Response
+    where
+        T: Serialize,
+        F: FnOnce(&T) -> (Flash, Redirect),
+    {
+        match self {
+            Format::Json => (status, Json(record)).into_response(),
+            Format::Html => redirect(&record).into_response(),
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for Format
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Format::from_headers(&parts.headers))
+    }
+}
+
+/// A body sent as JSON or as a urlencoded form, like `Form` it has to be the last extractor.
+///
+/// Bodies with an `application/json` or `+json` content type are read as JSON, everything else as
+/// a form.
+#[derive(Debug, Clone)]
+pub struct FormOrJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for FormOrJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if is_json(req.headers()) {
+            let Json(value) = Json::<T>::from_request(req, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+
+            return Ok(FormOrJson(value));
+        }
+
+        let Form(value) = Form::<T>::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        Ok(FormOrJson(value))
+    }
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<mime::Mime>().ok())
+        .is_some_and(|media| {
+            media.type_() == mime::APPLICATION
+                && (media.subtype() == mime::JSON || media.suffix() == Some(mime::JSON))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_is_the_default() {
+        assert_eq!(Format::from_accept(""), Format::Html);
+        assert_eq!(Format::from_accept("*/*"), Format::Html);
+        assert_eq!(Format::from_accept("image/png"), Format::Html);
+    }
+
+    #[test]
+    fn json_is_picked_when_asked_for() {
+        assert_eq!(Format::from_accept("application/json"), Format::Json);
+        assert_eq!(
+            Format::from_accept("application/problem+json"),
+            Format::Json
+        );
+        assert_eq!(
+            Format::from_accept("application/json, text/plain, */*"),
+            Format::Json
+        );
+    }
+
+    #[test]
+    fn browsers_get_html() {
+        assert_eq!(
+            Format::from_accept("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"),
+            Format::Html
+        );
+        assert_eq!(
+            Format::from_accept("text/html, application/json"),
+            Format::Html
+        );
+    }
+
+    #[test]
+    fn the_quality_decides() {
+        assert_eq!(
+            Format::from_accept("text/html;q=0.5, application/json"),
+            Format::Json
+        );
+        assert_eq!(
+            Format::from_accept("application/json;q=0.1, text/html"),
+            Format::Html
+        );
+    }
+}
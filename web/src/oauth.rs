@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use shipwright_config::{Config, OAuthProviderConfig};
+use shipwright_db::entities::identity::IdentityClaims;
+
+use crate::error::Error;
+
+/// A provider users can log in with, as shown on the login page.
+#[derive(Debug, Serialize)]
+pub struct OAuthProvider {
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Debug, Clone)]
+struct Provider {
+    config: OAuthProviderConfig,
+    authorize_url: Url,
+}
+
+/// Runs the authorization code flow with PKCE against the providers in `auth.oauth`.
+#[derive(Debug, Clone)]
+pub struct OAuthClient {
+    http_client: Client,
+    providers: BTreeMap<String, Provider>,
+    host: String,
+}
+
+impl OAuthClient {
+    pub fn new(config: &Config) -> Self {
+        let providers = config
+            .auth
+            .oauth
+            .iter()
+            .map(|(name, provider)| {
+                // Panic here as a misconfigured provider is a fatal error at startup.
+                let authorize_url = Url::parse(&provider.authorize_url).unwrap_or_else(|e| {
+                    panic!("invalid authorize_url for oauth provider {name}: {e}")
+                });
+                let provider = Provider {
+                    config: provider.clone(),
+                    authorize_url,
+                };
+                (name.clone(), provider)
+            })
+            .collect();
+
+        Self {
+            http_client: Client::new(),
+            providers,
+            host: config.server.host.clone(),
+        }
+    }
+
+    pub fn providers(&self) -> Vec<OAuthProvider> {
+        self.providers
+            .iter()
+            .map(|(name, provider)| OAuthProvider {
+                name: name.clone(),
+                display_name: provider.config.display_name.clone(),
+            })
+            .collect()
+    }
+
+    /// The url to send the user to so they can authorize the app at the provider.
+    ///
+    /// Returns `None` when the provider isn't configured.
+    pub fn authorize_url(&self, provider: &str, state: &str, code_challenge: &str) -> Option<Url> {
+        let Provider {
+            config,
+            authorize_url,
+        } = self.providers.get(provider)?;
+
+        let mut url = authorize_url.clone();
+        url.query_pairs_mut().extend_pairs([
+            ("response_type", "code"),
+            ("client_id", &config.client_id),
+            ("redirect_uri", &self.redirect_uri(provider)),
+            ("scope", &config.scopes.join(" ")),
+            ("state", state),
+            ("code_challenge", code_challenge),
+            ("code_challenge_method", "S256"),
+        ]);
+
+        Some(url)
+    }
+
+    /// Exchanges the authorization code from the callback for the claims of the user.
+    pub async fn fetch_claims(
+        &self,
+        provider: &str,
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<IdentityClaims, Error> {
+        let config = &self
+            .providers
+            .get(provider)
+            .ok_or_else(|| Error::UnknownOAuthProvider(provider.to_string()))?
+            .config;
+
+        let token = self
+            .http_client
+            .post(&config.token_url)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.redirect_uri(provider)),
+                ("client_id", &config.client_id),
+                ("client_secret", &config.client_secret),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<TokenResponse>()
+            .await?;
+
+        let claims = self
+            .http_client
+            .get(&config.userinfo_url)
+            .bearer_auth(token.access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<IdentityClaims>()
+            .await?;
+
+        Ok(claims)
+    }
+
+    fn redirect_uri(&self, provider: &str) -> String {
+        format!("{}/auth/oauth/{}/callback", self.host, provider)
+    }
+}
@@ -0,0 +1,295 @@
+//! The OpenAPI 3.1 document of the app, see <https://spec.openapis.org/oas/v3.1.0>.
+//!
+//! The document is put together from the [`resources`] the router mounts, with the routes each
+//! [`Controller`] and [`OwnedController`] prescribes at its `PATH` and the JSON Schemas of their
+//! `Record` and `EntityChangeset` derived by `schemars`. The `#[validate(..)]` constraints of a
+//! changeset end up in its schema, e.g. `length(min = 1)` as `minLength: 1`. The JSON routes of
+//! [`crate::controllers::api::ApiController`] are described by hand.
+//!
+//! It is served at `/api/openapi.json` along with a docs page at `/api/docs`, and written to a
+//! file with `cargo openapi`:
+//!
+//! ```rust,ignore
+//! let document = resources(ApiDoc::new()).build("shipwright");
+//! ```
+
+use schemars::{JsonSchema, SchemaGenerator, generate::SchemaSettings};
+use serde_json::{Map, Value, json};
+use shipwright_config::Config;
+use shipwright_db::{Page, pagination::MAX_PER_PAGE};
+
+use crate::{
+    controllers::{Controller, OwnedController, Resources, api::ApiController, resources},
+    error::Problem,
+};
+
+/// The version of the OpenAPI specification the document follows.
+pub const OPENAPI_VERSION: &str = "3.1.0";
+
+/// The content types the writes of a controller accept, see [`crate::negotiation::FormOrJson`].
+const REQUEST_CONTENT_TYPES: [&str; 2] = ["application/json", "application/x-www-form-urlencoded"];
+
+/// The OpenAPI document of the app, see the [module docs](self).
+pub fn document(config: &Config) -> Value {
+    let doc = resources(ApiDoc::new());
+
+    ApiController::openapi_routes(doc).build(&config.app.name)
+}
+
+/// Builds an OpenAPI document, collecting the schemas of the types its operations use.
+pub struct ApiDoc {
+    generator: SchemaGenerator,
+    paths: Map<String, Value>,
+}
+
+/// Adds the routes of [`Controller::router`] and [`OwnedController::router`] at their `PATH`.
+impl Resources for ApiDoc {
+    fn controller<C: Controller>(self) -> Self {
+        self.resource::<C::Id, C::Record, C::EntityChangeset>(C::PATH, false)
+    }
+
+    fn owned_controller<C: OwnedController>(self) -> Self {
+        self.resource::<C::Id, C::Record, C::EntityChangeset>(C::PATH, true)
+    }
+}
+
+impl Default for ApiDoc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ApiDoc {
+    pub fn new() -> Self {
+        let settings = SchemaSettings::draft2020_12().with(|settings| {
+            settings.definitions_path = "/components/schemas".into();
+            settings.meta_schema = None;
+        });
+
+        Self {
+            generator: settings.into_generator(),
+            paths: Map::new(),
+        }
+    }
+
+    /// Adds an operation, e.g. `get` of `/api/me`, replacing the one already there.
+    pub fn operation(mut self, method: &str, path: &str, operation: Value) -> Self {
+        let item = self
+            .paths
+            .entry(path.to_string())
+            .or_insert_with(|| json!({}));
+        item[method] = operation;
+        self
+    }
+
+    /// The schema of `T`, a `$ref` into the components for named types.
+    pub fn schema<T: JsonSchema>(&mut self) -> Value {
+        self.generator.subschema_for::<T>().to_value()
+    }
+
+    /// A JSON body of `T`.
+    pub fn json<T: JsonSchema>(&mut self, description: &str) -> Value {
+        json!({
+            "description": description,
+            "content": { "application/json": { "schema": self.schema::<T>() } },
+        })
+    }
+
+    /// An `application/problem+json` body, see [`crate::error::Problem`].
+    pub fn problem(&mut self, description: &str) -> Value {
+        json!({
+            "description": description,
+            "content": { "application/problem+json": { "schema": self.schema::<Problem>() } },
+        })
+    }
+
+    /// The query parameters of a [`shipwright_db::PageRequest`].
+    pub fn page_parameters(&self) -> Value {
+        let query = |name: &str, description: &str, schema: Value| {
+            json!({
+                "name": name,
+                "in": "query",
+                "description": description,
+                "schema": schema,
+            })
+        };
+
+        json!([
+            query("page", "The 1-based page number.", json!({ "type": "integer", "minimum": 1 })),
+            query(
+                "per_page",
+                "The number of records on a page.",
+                json!({ "type": "integer", "minimum": 1, "maximum": MAX_PER_PAGE }),
+            ),
+            query(
+                "cursor",
+                "Paginate by primary key, empty for the first page and `next_cursor` after that.",
+                json!({ "type": "string" }),
+            ),
+            query(
+                "sort",
+                "The column to order by, prefixed with `-` to sort in descending order.",
+                json!({ "type": "string" }),
+            ),
+            {
+                "name": "filters",
+                "in": "query",
+                "description": "Any other parameter is an equality filter on the column of the same name.",
+                "style": "form",
+                "explode": true,
+                "schema": { "type": "object", "additionalProperties": { "type": "string" } },
+            },
+        ])
+    }
+
+    /// The finished document.
+    pub fn build(mut self, title: &str) -> Value {
+        let mut schemas = self.generator.take_definitions(true);
+        // Only the summary of the doc comment of a type is meant for clients, the rest is about
+        // using it from Rust
+        for schema in schemas.values_mut() {
+            if let Some(Value::String(description)) = schema.get_mut("description")
+                && let Some(end) = description.find("\n\n")
+            {
+                description.truncate(end);
+            }
+        }
+
+        json!({
+            "openapi": OPENAPI_VERSION,
+            "info": { "title": title, "version": env!("CARGO_PKG_VERSION") },
+            "paths": self.paths,
+            "components": {
+                "schemas": schemas,
+                "securitySchemes": {
+                    "session": {
+                        "type": "apiKey",
+                        "in": "cookie",
+                        "name": "id",
+                        "description": "The session cookie of a signed in browser, writes also need the `X-CSRF-Token` header.",
+                    },
+                    "token": {
+                        "type": "http",
+                        "scheme": "bearer",
+                        "description": "A personal access token, created in the account settings.",
+                    },
+                },
+            },
+        })
+    }
+
+    /// The CRUD routes the controller traits prescribe.
+    fn resource<Id, R, C>(mut self, path: &str, owned: bool) -> Self
+    where
+        Id: JsonSchema,
+        R: JsonSchema,
+        C: JsonSchema,
+    {
+        let tag = path.trim_start_matches('/').to_string();
+        let item_path = format!("{path}/{{id}}");
+        let id = json!({
+            "name": "id",
+            "in": "path",
+            "required": true,
+            "schema": self.schema::<Id>(),
+        });
+        let changeset = self.schema::<C>();
+        let body = |schema: Value| {
+            let content: Map<String, Value> = REQUEST_CONTENT_TYPES
+                .iter()
+                .map(|content_type| (content_type.to_string(), json!({ "schema": schema })))
+                .collect();
+            json!({ "required": true, "content": content })
+        };
+        let permission = format!("Requires the `{tag}.write` permission.");
+        let security = if owned {
+            json!([{ "session": [] }])
+        } else {
+            json!([{}, { "session": [] }])
+        };
+
+        let read_all = json!({
+            "tags": [tag],
+            "operationId": format!("{tag}_read_all"),
+            "summary": format!("List a page of {tag}"),
+            "parameters": self.page_parameters(),
+            "security": security,
+            "responses": {
+                "200": self.json::<Page<R>>("A page of records"),
+                "400": self.problem("The page request is invalid"),
+            },
+        });
+        let create = json!({
+            "tags": [tag],
+            "operationId": format!("{tag}_create"),
+            "summary": "Create a record",
+            "description": permission,
+            "security": security,
+            "requestBody": body(changeset.clone()),
+            "responses": {
+                "201": self.json::<R>("The created record"),
+                "403": self.problem("Missing permission"),
+                "422": self.problem("The changeset is invalid"),
+            },
+        });
+        let create_batch = json!({
+            "tags": [tag],
+            "operationId": format!("{tag}_create_batch"),
+            "summary": "Create several records at once",
+            "description": permission,
+            "security": security,
+            "requestBody": body(json!({ "type": "array", "items": changeset })),
+            "responses": {
+                "201": self.json::<Vec<R>>("The created records"),
+                "403": self.problem("Missing permission"),
+                "422": self.problem("A changeset is invalid"),
+            },
+        });
+        let read_one = json!({
+            "tags": [tag],
+            "operationId": format!("{tag}_read_one"),
+            "summary": "Show a record",
+            "parameters": [id],
+            "security": security,
+            "responses": {
+                "200": self.json::<R>("The record"),
+                "404": self.problem("No record with the id"),
+            },
+        });
+        let update = json!({
+            "tags": [tag],
+            "operationId": format!("{tag}_update"),
+            "summary": "Update a record",
+            "description": permission,
+            "parameters": [id],
+            "security": security,
+            "requestBody": body(changeset),
+            "responses": {
+                "200": self.json::<R>("The updated record"),
+                "403": self.problem("Missing permission"),
+                "404": self.problem("No record with the id"),
+                "422": self.problem("The changeset is invalid"),
+            },
+        });
+        let delete = json!({
+            "tags": [tag],
+            "operationId": format!("{tag}_delete"),
+            "summary": "Delete a record",
+            "description": permission,
+            "parameters": [id],
+            "security": security,
+            "responses": {
+                "200": self.json::<R>("The deleted record"),
+                "403": self.problem("Missing permission"),
+                "404": self.problem("No record with the id"),
+            },
+        });
+
+        self.operation("get", path, read_all)
+            .operation("post", path, create)
+            .operation("post", &format!("{path}/batch"), create_batch)
+            .operation("get", &item_path, read_one)
+            .operation("put", &item_path, update)
+            .operation("delete", &item_path, delete)
+    }
+}
@@ -0,0 +1,121 @@
+//! Where a user is sent back to once they've logged in, see [`ReturnUrl`].
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+/// A page of this site to return to, e.g. the page that asked the user to log in.
+///
+/// The `next` parameter comes from the url, so anyone can put a link to the login page with
+/// `?next=https://evil.example` in an email. Only paths on `server.host` are accepted, an url
+/// on the host itself is cut down to its path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ReturnUrl(String);
+
+impl ReturnUrl {
+    /// Accepts `value` if it points at a page on `host`, the `server.host` of the config.
+    pub fn parse(value: &str, host: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+
+        // The value is resolved against the host the way a browser would, so absolute urls and
+        // `//evil.example` or `/\evil.example` land on another origin and are refused
+        let base = Url::parse(host).ok()?;
+        let url = base.join(value).ok()?;
+        if url.origin() != base.origin() {
+            return None;
+        }
+
+        // Resolving removes dot segments, so `/.//evil.example` stays on the host but becomes the
+        // path `//evil.example`. Redirecting to that path would take the browser to another origin
+        let path = url.path();
+        if path.starts_with("//") || path.starts_with("/\\") {
+            return None;
+        }
+
+        match url.query() {
+            Some(query) => Some(Self(format!("{path}?{query}"))),
+            None => Some(Self(path.to_string())),
+        }
+    }
+
+    /// Parses an optional `next` parameter, anything that isn't a page on `host` is dropped.
+    pub fn parse_next(next: Option<&str>, host: &str) -> Option<Self> {
+        next.and_then(|next| Self::parse(next, host))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Appends the url as the `next` parameter of `path`, e.g. the login page.
+    pub fn append_to(&self, path: &str) -> String {
+        let query = serde_urlencoded::to_string([("next", self.as_str())]).unwrap_or_default();
+
+        format!("{path}?{query}")
+    }
+}
+
+impl std::fmt::Display for ReturnUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOST: &str = "https://shipwright.example";
+
+    fn parse(value: &str) -> Option<String> {
+        ReturnUrl::parse(value, HOST).map(|url| url.to_string())
+    }
+
+    #[test]
+    fn paths_are_accepted() {
+        assert_eq!(parse("/todos"), Some("/todos".to_string()));
+        assert_eq!(parse("/todos?page=2"), Some("/todos?page=2".to_string()));
+    }
+
+    #[test]
+    fn urls_on_the_host_are_cut_down_to_their_path() {
+        assert_eq!(
+            parse("https://shipwright.example/todos?page=2"),
+            Some("/todos?page=2".to_string())
+        );
+    }
+
+    #[test]
+    fn other_sites_are_refused() {
+        for value in [
+            "https://evil.example",
+            "http://shipwright.example/todos",
+            "https://shipwright.example.evil.example/todos",
+            "//evil.example/todos",
+            "/\\evil.example/todos",
+            " //evil.example",
+            "\t//evil.example",
+            "/.//evil.example/x",
+            "/..//evil.example",
+            "/a/..//evil.example",
+            "/%2e//evil.example",
+            "javascript:alert(1)",
+            "",
+        ] {
+            assert_eq!(parse(value), None, "{value:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn the_next_parameter_is_encoded() {
+        let url = ReturnUrl::parse("/todos?page=2&size=10", HOST).unwrap();
+
+        assert_eq!(
+            url.append_to("/auth/login"),
+            "/auth/login?next=%2Ftodos%3Fpage%3D2%26size%3D10"
+        );
+    }
+}
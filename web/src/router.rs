@@ -1,60 +1,138 @@
 use std::time::Duration;
 
-use axum::{Extension, Router, routing::get};
-use axum_login::{AuthManagerLayer, login_required};
+use axum::{Extension, Router, middleware, routing::get};
+use axum_login::AuthManagerLayer;
 use serde::Serialize;
 use shipwright_db::DeserializeOwned;
 use shipwright_worker::WorkerStorage;
 use tower::ServiceBuilder;
 use tower_http::{timeout::TimeoutLayer, trace::TraceLayer};
-use tower_sessions_sqlx_store::SqliteStore;
 
 use crate::{
     controllers::{
-        Controller,
+        Controller, OwnedController, Resources,
+        account::{
+            api_tokens::ApiTokenController,
+            data::DataController,
+            email::{EmailConfirmController, EmailController},
+            password::PasswordController,
+            sessions::SessionController,
+        },
+        api::ApiController,
         auth::{
-            login::LoginController, logout::LogoutController, register::RegisterController,
-            register_confirm::RegisterConfirmController,
+            forgot_password::ForgotPasswordController, login::LoginController,
+            logout::LogoutController, magic_link::MagicLinkController, oauth::OAuthController,
+            register::RegisterController, register_confirm::RegisterConfirmController,
+            reset_password::ResetPasswordController, two_factor::TwoFactorController,
         },
         home::HomeController,
-        invoice::InvoiceController,
-        lion::LionController,
         ping::PingController,
-        todos::TodoController,
+        resources,
+        teams::{InvitationController, TeamController},
+    },
+    middlewares::{
+        auth::{AuthBackend, SessionStore, require_login},
+        cookie_keys::resign_cookies,
+        csrf::verify_csrf,
+        errors::render_errors,
+        htmx::hx_redirects,
+        session_activity::track_session,
     },
-    middlewares::auth::AuthBackend,
+    error::Error,
     state::AppState,
 };
 
+/// The routers of the record controllers, see [`resources`].
+#[derive(Default)]
+struct ResourceRouters {
+    /// Routed behind `require_login`
+    owned: Router<AppState>,
+    public: Router<AppState>,
+}
+
+impl Resources for ResourceRouters {
+    fn controller<C: Controller>(mut self) -> Self {
+        self.public = self.public.merge(C::router());
+        self
+    }
+
+    fn owned_controller<C: OwnedController>(mut self) -> Self {
+        self.owned = self.owned.merge(C::router());
+        self
+    }
+}
+
 pub fn init_router<T>(
     app_state: &AppState,
-    auth_layer: AuthManagerLayer<AuthBackend, SqliteStore, tower_sessions::service::SignedCookie>,
+    auth_layer: AuthManagerLayer<AuthBackend, SessionStore, tower_sessions::service::SignedCookie>,
     worker_layer: WorkerStorage<T>,
 ) -> Router
 where
     T: 'static + Serialize + DeserializeOwned + Send + Sync + Unpin,
 {
+    let resources = resources(ResourceRouters::default());
+
     Router::new()
         .route(
             "/protected",
             get(|| async { "you gotta be logged in to see me!" }),
         )
-        .merge(TodoController::router())
-        .route_layer(login_required!(AuthBackend, login_url = "/auth/login"))
+        .merge(resources.owned)
+        .merge(TwoFactorController::router())
+        .merge(ApiTokenController::router())
+        .merge(SessionController::router())
+        .merge(EmailController::router())
+        .merge(PasswordController::router())
+        .merge(DataController::router())
+        .merge(TeamController::router())
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            require_login,
+        ))
         .merge(HomeController::router())
         .merge(LoginController::router())
         .merge(LogoutController::router())
+        .merge(OAuthController::router())
+        // Passwordless sign in is opt-in, see `auth.magic_link`
+        .merge(if app_state.config.auth.magic_link {
+            MagicLinkController::router()
+        } else {
+            Router::new()
+        })
         .merge(RegisterController::router())
         .merge(RegisterConfirmController::router())
-        .merge(LionController::router())
-        .merge(InvoiceController::router())
+        .merge(ForgotPasswordController::router())
+        .merge(ResetPasswordController::router())
+        .merge(EmailConfirmController::router())
+        .merge(InvitationController::router())
+        .merge(resources.public)
         .merge(PingController::router())
+        .nest("/api", ApiController::router())
+        .fallback(|| async { Error::PageNotFound })
         .with_state(app_state.clone())
+        .layer(middleware::from_fn(hx_redirects))
+        // Inside the csrf layer below, error pages get the csrf token of the session
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            render_errors,
+        ))
+        // Runs inside the auth layer below, which provides the auth session
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            track_session,
+        ))
+        // Also inside the auth layer, forged requests are refused before anything else runs
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            verify_csrf,
+        ))
         .layer(ServiceBuilder::new().layer((
             TraceLayer::new_for_http(),
             // Graceful shutdown will wait for outstanding requests to complete. Add a timeout so
             // requests don't hang forever.
             TimeoutLayer::new(Duration::from_secs(10)),
+            // Runs in front of the session layer so cookies signed with an older key still verify
+            middleware::from_fn_with_state(app_state.cookie_keys.clone(), resign_cookies),
             auth_layer,
             Extension(worker_layer),
         )))
@@ -1,11 +1,13 @@
 use axum::extract::FromRef;
-use axum_extra::extract::cookie::Key;
 use color_eyre::Result;
 use shipwright_config::{Config, Environment, load_config};
 use shipwright_db::{Database, DbPool, connect_pool};
 use shipwright_mailer::EmailClient;
 
-use crate::{error::Error, middlewares::flash};
+use crate::{
+    error::Error,
+    middlewares::{cookie_keys::CookieKeys, flash},
+};
 
 /// The application's state that is available in [`crate::controllers`] and [`crate::middlewares`].
 #[derive(Clone)]
@@ -14,6 +16,8 @@ pub struct AppState {
     pub config: Config,
     pub db_pool: DbPool,
     pub flash_config: flash::Config,
+    /// Signs the session and flash cookies.
+    pub cookie_keys: CookieKeys,
     pub email_client: EmailClient,
 }
 
@@ -21,7 +25,9 @@ impl AppState {
     pub async fn build(env: Environment) -> Result<Self, Error> {
         let config: Config = load_config(&env)?;
         let db_pool = connect_pool(Database::Primary, &config).await?;
-        let flash_config = flash::Config::new(Key::generate());
+        let cookie_keys =
+            CookieKeys::from_config(&config.cookie, &env).map_err(Error::CookieKey)?;
+        let flash_config = flash::Config::new(cookie_keys.signing_key().clone());
         let email_client = EmailClient::new(&config.mailer);
 
         Ok(Self {
@@ -29,6 +35,7 @@ impl AppState {
             config,
             db_pool,
             flash_config,
+            cookie_keys,
             email_client,
         })
     }
@@ -0,0 +1,36 @@
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use shipwright_db::entities::api_token::ApiToken;
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::format;
+use crate::middlewares::flash::IncomingFlashes;
+
+pub enum ApiTokenView {
+    Index(ViewEngine<View>, IncomingFlashes, Vec<ApiToken>),
+    /// The new token, shown once.
+    Created(ViewEngine<View>, String, ApiToken),
+}
+
+impl IntoResponse for ApiTokenView {
+    fn into_response(self) -> Response {
+        match self {
+            ApiTokenView::Index(ViewEngine(v), IncomingFlashes { flashes, .. }, api_tokens) => {
+                format::render()
+                    .view(
+                        &v,
+                        "account/api_tokens/index.html",
+                        json!({ "flashes": flashes, "api_tokens": api_tokens }),
+                    )
+                    .into_response()
+            }
+            ApiTokenView::Created(ViewEngine(v), token, api_token) => format::render()
+                .view(
+                    &v,
+                    "account/api_tokens/created.html",
+                    json!({ "token": token, "api_token": api_token }),
+                )
+                .into_response(),
+        }
+    }
+}
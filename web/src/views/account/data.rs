@@ -0,0 +1,49 @@
+use axum::{
+    http::header::CONTENT_DISPOSITION,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use shipwright_db::entities::{account_deletion::AccountDeletion, user_data::UserData};
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::format;
+use crate::middlewares::flash::IncomingFlashes;
+
+pub enum DataView {
+    /// The export link and the deletion form, with the scheduled deletion and the grace period
+    /// in days.
+    Index(
+        ViewEngine<View>,
+        IncomingFlashes,
+        Option<AccountDeletion>,
+        u32,
+    ),
+    /// Everything stored about the user, downloaded as a file.
+    Export(UserData),
+}
+
+impl IntoResponse for DataView {
+    fn into_response(self) -> Response {
+        match self {
+            DataView::Index(
+                ViewEngine(v),
+                IncomingFlashes { flashes, .. },
+                deletion,
+                grace_days,
+            ) => format::render()
+                .view(
+                    &v,
+                    "account/data/index.html",
+                    json!({ "flashes": flashes, "deletion": deletion, "grace_days": grace_days }),
+                )
+                .into_response(),
+            DataView::Export(data) => format::render()
+                .header(
+                    CONTENT_DISPOSITION,
+                    r#"attachment; filename="account-data.json""#,
+                )
+                .json(data)
+                .into_response(),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::format;
+use crate::middlewares::flash::IncomingFlashes;
+
+pub enum EmailView {
+    /// The form for changing the email, with the current email.
+    Index(ViewEngine<View>, IncomingFlashes, String),
+    /// The button that confirms the change with the token of the link.
+    Confirm(ViewEngine<View>, IncomingFlashes, Option<String>),
+}
+
+impl IntoResponse for EmailView {
+    fn into_response(self) -> Response {
+        match self {
+            EmailView::Index(ViewEngine(v), IncomingFlashes { flashes, .. }, email) => {
+                format::render()
+                    .view(
+                        &v,
+                        "account/email/index.html",
+                        json!({"flashes": flashes, "email": email}),
+                    )
+                    .into_response()
+            }
+            EmailView::Confirm(ViewEngine(v), IncomingFlashes { flashes, .. }, token) => {
+                format::render()
+                    .view(
+                        &v,
+                        "account/email/confirm.html",
+                        json!({"flashes": flashes, "token": token}),
+                    )
+                    .into_response()
+            }
+        }
+    }
+}
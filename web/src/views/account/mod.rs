@@ -0,0 +1,5 @@
+pub mod api_tokens;
+pub mod data;
+pub mod email;
+pub mod password;
+pub mod sessions;
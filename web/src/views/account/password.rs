@@ -0,0 +1,25 @@
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::format;
+use crate::middlewares::flash::IncomingFlashes;
+
+pub enum PasswordView {
+    /// The form for choosing a new password.
+    Index(ViewEngine<View>, IncomingFlashes),
+}
+
+impl IntoResponse for PasswordView {
+    fn into_response(self) -> Response {
+        match self {
+            PasswordView::Index(ViewEngine(v), IncomingFlashes { flashes, .. }) => format::render()
+                .view(
+                    &v,
+                    "account/password/index.html",
+                    json!({"flashes": flashes}),
+                )
+                .into_response(),
+        }
+    }
+}
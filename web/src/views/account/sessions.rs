@@ -0,0 +1,58 @@
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use serde_json::json;
+use shipwright_context::Account;
+use shipwright_db::entities::session::ActiveSession;
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::format;
+use crate::middlewares::flash::IncomingFlashes;
+
+pub enum SessionView {
+    /// The user's sessions and the id of the session making the request.
+    Index(
+        ViewEngine<View>,
+        IncomingFlashes,
+        Vec<ActiveSession>,
+        Option<String>,
+    ),
+}
+
+/// A session as listed on the page, named by its handle rather than its id.
+#[derive(Serialize)]
+struct SessionItem {
+    handle: String,
+    current: bool,
+    #[serde(flatten)]
+    session: ActiveSession,
+}
+
+impl IntoResponse for SessionView {
+    fn into_response(self) -> Response {
+        match self {
+            SessionView::Index(
+                ViewEngine(v),
+                IncomingFlashes { flashes, .. },
+                sessions,
+                current,
+            ) => {
+                let sessions: Vec<SessionItem> = sessions
+                    .into_iter()
+                    .map(|session| SessionItem {
+                        handle: Account::session_handle(&session.id),
+                        current: Some(&session.id) == current.as_ref(),
+                        session,
+                    })
+                    .collect();
+
+                format::render()
+                    .view(
+                        &v,
+                        "account/sessions/index.html",
+                        json!({ "flashes": flashes, "sessions": sessions }),
+                    )
+                    .into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::format;
+use crate::middlewares::flash::IncomingFlashes;
+
+pub enum ForgotPasswordView {
+    Index(ViewEngine<View>, IncomingFlashes),
+}
+
+impl IntoResponse for ForgotPasswordView {
+    fn into_response(self) -> Response {
+        match self {
+            ForgotPasswordView::Index(ViewEngine(v), IncomingFlashes { flashes, .. }) => {
+                format::render()
+                    .view(
+                        &v,
+                        "auth/forgot_password/index.html",
+                        json!({"flashes": flashes}),
+                    )
+                    .into_response()
+            }
+        }
+    }
+}
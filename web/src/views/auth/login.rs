@@ -1,25 +1,55 @@
 use axum::response::{IntoResponse, Response};
-use shipwright_ui::view_engine::{View, ViewEngine};
 use serde_json::json;
+use shipwright_ui::view_engine::{View, ViewEngine};
 
 use crate::{
     format::{self},
     middlewares::flash::IncomingFlashes,
+    oauth::OAuthProvider,
+    return_url::ReturnUrl,
 };
 
 pub enum LoginView {
-    Index(ViewEngine<View>, IncomingFlashes, Option<String>),
+    /// The login form and a button for each OAuth2 / OpenID Connect provider, the `bool` is
+    /// whether users can ask for a magic link instead.
+    Index(
+        ViewEngine<View>,
+        IncomingFlashes,
+        Option<ReturnUrl>,
+        Vec<OAuthProvider>,
+        bool,
+    ),
+    /// The second step of the login for users with two-factor authentication.
+    TwoFactor(ViewEngine<View>, IncomingFlashes),
 }
 
 impl IntoResponse for LoginView {
     fn into_response(self) -> Response {
         match self {
-            LoginView::Index(ViewEngine(v), IncomingFlashes { flashes, .. }, next) => {
+            LoginView::Index(
+                ViewEngine(v),
+                IncomingFlashes { flashes, .. },
+                next,
+                providers,
+                magic_link,
+            ) => format::render()
+                .view(
+                    &v,
+                    "auth/login/index.html",
+                    json!({
+                        "flashes": flashes,
+                        "next": next,
+                        "providers": providers,
+                        "magic_link": magic_link,
+                    }),
+                )
+                .into_response(),
+            LoginView::TwoFactor(ViewEngine(v), IncomingFlashes { flashes, .. }) => {
                 format::render()
                     .view(
                         &v,
-                        "auth/login/index.html",
-                        json!({ "flashes": flashes, "next": next}),
+                        "auth/login/two_factor.html",
+                        json!({ "flashes": flashes }),
                     )
                     .into_response()
             }
@@ -0,0 +1,38 @@
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::format;
+use crate::middlewares::flash::IncomingFlashes;
+
+pub enum MagicLinkView {
+    /// The form asking for the email to send the link to.
+    Index(ViewEngine<View>, IncomingFlashes),
+    /// The button that signs in with the token of the link.
+    Verify(ViewEngine<View>, IncomingFlashes, Option<String>),
+}
+
+impl IntoResponse for MagicLinkView {
+    fn into_response(self) -> Response {
+        match self {
+            MagicLinkView::Index(ViewEngine(v), IncomingFlashes { flashes, .. }) => {
+                format::render()
+                    .view(
+                        &v,
+                        "auth/magic_link/index.html",
+                        json!({"flashes": flashes}),
+                    )
+                    .into_response()
+            }
+            MagicLinkView::Verify(ViewEngine(v), IncomingFlashes { flashes, .. }, token) => {
+                format::render()
+                    .view(
+                        &v,
+                        "auth/magic_link/verify.html",
+                        json!({"flashes": flashes, "token": token}),
+                    )
+                    .into_response()
+            }
+        }
+    }
+}
@@ -1,3 +1,7 @@
+pub mod forgot_password;
 pub mod login;
+pub mod magic_link;
 pub mod register;
 pub mod register_confirm;
+pub mod reset_password;
+pub mod two_factor;
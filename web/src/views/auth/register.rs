@@ -1,19 +1,40 @@
 use axum::response::{IntoResponse, Response};
-use shipwright_ui::view_engine::{View, ViewEngine};
 use serde_json::json;
+use shipwright_ui::view_engine::{View, ViewEngine};
 
+use crate::form::FormState;
 use crate::format;
+use crate::htmx::HxRequest;
 use crate::middlewares::flash::IncomingFlashes;
+use crate::return_url::ReturnUrl;
 
 pub enum RegisterView {
-    Index(ViewEngine<View>, IncomingFlashes),
+    /// The registration form, `next` is where the user goes once they're confirmed.
+    Index(ViewEngine<View>, IncomingFlashes, Option<ReturnUrl>),
+    /// The registration form again after it failed validation, htmx requests only get the form.
+    Form(HxRequest, ViewEngine<View>, Option<ReturnUrl>, FormState),
 }
 
 impl IntoResponse for RegisterView {
     fn into_response(self) -> Response {
         match self {
-            RegisterView::Index(ViewEngine(v), IncomingFlashes { flashes, .. }) => format::render()
-                .view(&v, "auth/register/index.html", json!({"flashes": flashes}))
+            RegisterView::Index(ViewEngine(v), IncomingFlashes { flashes, .. }, next) => {
+                format::render()
+                    .view(
+                        &v,
+                        "auth/register/index.html",
+                        json!({"flashes": flashes, "next": next}),
+                    )
+                    .into_response()
+            }
+            RegisterView::Form(hx, ViewEngine(v), next, form) => format::render()
+                .invalid_form(
+                    &hx,
+                    &v,
+                    "auth/register/index.html",
+                    "form",
+                    json!({"next": next, "form": form}),
+                )
                 .into_response(),
         }
     }
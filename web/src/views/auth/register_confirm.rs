@@ -4,20 +4,22 @@ use serde_json::json;
 
 use crate::format;
 use crate::middlewares::flash::IncomingFlashes;
+use crate::return_url::ReturnUrl;
 
 pub enum RegisterConfirmView {
-    Index(ViewEngine<View>, IncomingFlashes),
+    /// The confirmation form, `next` is where the user goes once they're confirmed.
+    Index(ViewEngine<View>, IncomingFlashes, Option<ReturnUrl>),
 }
 
 impl IntoResponse for RegisterConfirmView {
     fn into_response(self) -> Response {
         match self {
-            RegisterConfirmView::Index(ViewEngine(v), IncomingFlashes { flashes, .. }) => {
+            RegisterConfirmView::Index(ViewEngine(v), IncomingFlashes { flashes, .. }, next) => {
                 format::render()
                     .view(
                         &v,
                         "auth/register_confirm/index.html",
-                        json!({"flashes": flashes}),
+                        json!({"flashes": flashes, "next": next}),
                     )
                     .into_response()
             }
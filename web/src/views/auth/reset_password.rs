@@ -0,0 +1,26 @@
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::format;
+use crate::middlewares::flash::IncomingFlashes;
+
+pub enum ResetPasswordView {
+    Index(ViewEngine<View>, IncomingFlashes, Option<String>),
+}
+
+impl IntoResponse for ResetPasswordView {
+    fn into_response(self) -> Response {
+        match self {
+            ResetPasswordView::Index(ViewEngine(v), IncomingFlashes { flashes, .. }, token) => {
+                format::render()
+                    .view(
+                        &v,
+                        "auth/reset_password/index.html",
+                        json!({"flashes": flashes, "token": token}),
+                    )
+                    .into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::format;
+use crate::middlewares::flash::IncomingFlashes;
+
+pub enum TwoFactorView {
+    /// The secret and its QR code for the user to add to their authenticator app.
+    Enroll(ViewEngine<View>, IncomingFlashes, String, String),
+    Enabled(ViewEngine<View>, IncomingFlashes),
+    RecoveryCodes(ViewEngine<View>, Vec<String>),
+}
+
+impl IntoResponse for TwoFactorView {
+    fn into_response(self) -> Response {
+        match self {
+            TwoFactorView::Enroll(ViewEngine(v), IncomingFlashes { flashes, .. }, secret, qr_code) => {
+                format::render()
+                    .view(
+                        &v,
+                        "auth/two_factor/index.html",
+                        json!({"flashes": flashes, "enabled": false, "secret": secret, "qr_code": qr_code}),
+                    )
+                    .into_response()
+            }
+            TwoFactorView::Enabled(ViewEngine(v), IncomingFlashes { flashes, .. }) => {
+                format::render()
+                    .view(
+                        &v,
+                        "auth/two_factor/index.html",
+                        json!({"flashes": flashes, "enabled": true}),
+                    )
+                    .into_response()
+            }
+            TwoFactorView::RecoveryCodes(ViewEngine(v), recovery_codes) => format::render()
+                .view(
+                    &v,
+                    "auth/two_factor/recovery_codes.html",
+                    json!({"recovery_codes": recovery_codes}),
+                )
+                .into_response(),
+        }
+    }
+}
@@ -1,36 +1,122 @@
 use axum::response::{IntoResponse, Response};
-use shipwright_db::entities::invoices::Invoice;
-use shipwright_ui::view_engine::{View, ViewEngine};
 use serde_json::json;
+use shipwright_db::{Page, entities::invoices::Invoice};
+use shipwright_ui::view_engine::{View, ViewEngine};
 
-use crate::{format, middlewares::flash::IncomingFlashes};
+use crate::{
+    form::FormState,
+    format,
+    htmx::HxRequest,
+    middlewares::{auth::Permissions, flash::IncomingFlashes},
+    negotiation::Format,
+};
 
+/// Renders the invoice templates, or serializes the invoices for JSON clients.
 pub enum InvoiceView {
-    Index(ViewEngine<View>, Vec<Invoice>, IncomingFlashes),
-    Show(ViewEngine<View>, Invoice, IncomingFlashes),
+    Index(
+        Format,
+        ViewEngine<View>,
+        Page<Invoice>,
+        IncomingFlashes,
+        Permissions,
+    ),
+    Show(
+        Format,
+        ViewEngine<View>,
+        Invoice,
+        IncomingFlashes,
+        Permissions,
+    ),
+    /// The update form of the show page, for htmx requests swapping it
+    Update(ViewEngine<View>, Invoice),
+    /// The index page with an invalid new invoice, htmx requests only get the form
+    CreateForm(
+        HxRequest,
+        ViewEngine<View>,
+        Page<Invoice>,
+        FormState,
+        IncomingFlashes,
+        Permissions,
+    ),
+    /// The show page with an invalid update of the invoice, htmx requests only get the form
+    UpdateForm(
+        HxRequest,
+        ViewEngine<View>,
+        Invoice,
+        FormState,
+        IncomingFlashes,
+        Permissions,
+    ),
 }
 
 impl IntoResponse for InvoiceView {
     fn into_response(self) -> Response {
         match self {
-            InvoiceView::Index(ViewEngine(v), invoices, IncomingFlashes { flashes, .. }) => {
-                format::render()
-                    .view(
-                        &v,
-                        "invoices/index.html",
-                        json!({ "invoices": invoices, "flashes": flashes }),
-                    )
-                    .into_response()
-            }
-            InvoiceView::Show(ViewEngine(v), invoice, IncomingFlashes { flashes, .. }) => {
-                format::render()
-                    .view(
-                        &v,
-                        "invoices/show.html",
-                        json!({ "invoice": invoice, "flashes": flashes }),
-                    )
-                    .into_response()
-            }
+            InvoiceView::Index(
+                format,
+                ViewEngine(v),
+                page,
+                IncomingFlashes { flashes, .. },
+                permissions,
+            ) => format::render()
+                .negotiate(
+                    format,
+                    &v,
+                    "invoices/index.html",
+                    json!({ "invoices": page.records, "page": page.meta, "flashes": flashes, "permissions": permissions }),
+                    &page,
+                )
+                .into_response(),
+            InvoiceView::Show(
+                format,
+                ViewEngine(v),
+                invoice,
+                IncomingFlashes { flashes, .. },
+                permissions,
+            ) => format::render()
+                .negotiate(
+                    format,
+                    &v,
+                    "invoices/show.html",
+                    json!({ "invoice": invoice, "flashes": flashes, "permissions": permissions }),
+                    &invoice,
+                )
+                .into_response(),
+            InvoiceView::Update(ViewEngine(v), invoice) => format::render()
+                .view_block(&v, "invoices/show.html", "update", json!({ "invoice": invoice }))
+                .into_response(),
+            InvoiceView::CreateForm(
+                hx,
+                ViewEngine(v),
+                page,
+                form,
+                IncomingFlashes { flashes, .. },
+                permissions,
+            ) => format::render()
+                .invalid_form(
+                    &hx,
+                    &v,
+                    "invoices/index.html",
+                    "form",
+                    json!({ "invoices": page.records, "page": page.meta, "form": form, "flashes": flashes, "permissions": permissions }),
+                )
+                .into_response(),
+            InvoiceView::UpdateForm(
+                hx,
+                ViewEngine(v),
+                invoice,
+                form,
+                IncomingFlashes { flashes, .. },
+                permissions,
+            ) => format::render()
+                .invalid_form(
+                    &hx,
+                    &v,
+                    "invoices/show.html",
+                    "update",
+                    json!({ "invoice": invoice, "form": form, "flashes": flashes, "permissions": permissions }),
+                )
+                .into_response(),
         }
     }
 }
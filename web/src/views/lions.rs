@@ -1,36 +1,116 @@
 use axum::response::{IntoResponse, Response};
-use shipwright_db::entities::lions::Lion;
-use shipwright_ui::view_engine::{View, ViewEngine};
 use serde_json::json;
+use shipwright_db::{Page, entities::lions::Lion};
+use shipwright_ui::view_engine::{View, ViewEngine};
 
-use crate::{format, middlewares::flash::IncomingFlashes};
+use crate::{
+    form::FormState,
+    format,
+    htmx::HxRequest,
+    middlewares::{auth::Permissions, flash::IncomingFlashes},
+    negotiation::Format,
+};
 
+/// Renders the lion templates, or serializes the lions for JSON clients.
 pub enum LionView {
-    Index(ViewEngine<View>, Vec<Lion>, IncomingFlashes),
-    Show(ViewEngine<View>, Lion, IncomingFlashes),
+    Index(
+        Format,
+        ViewEngine<View>,
+        Page<Lion>,
+        IncomingFlashes,
+        Permissions,
+    ),
+    Show(Format, ViewEngine<View>, Lion, IncomingFlashes, Permissions),
+    /// The update form of the show page, for htmx requests swapping it
+    Update(ViewEngine<View>, Lion),
+    /// The index page with an invalid new lion, htmx requests only get the form
+    CreateForm(
+        HxRequest,
+        ViewEngine<View>,
+        Page<Lion>,
+        FormState,
+        IncomingFlashes,
+        Permissions,
+    ),
+    /// The show page with an invalid update of the lion, htmx requests only get the form
+    UpdateForm(
+        HxRequest,
+        ViewEngine<View>,
+        Lion,
+        FormState,
+        IncomingFlashes,
+        Permissions,
+    ),
 }
 
 impl IntoResponse for LionView {
     fn into_response(self) -> Response {
         match self {
-            LionView::Index(ViewEngine(v), lions, IncomingFlashes { flashes, .. }) => {
-                format::render()
-                    .view(
-                        &v,
-                        "lions/index.html",
-                        json!({ "lions": lions, "flashes": flashes }),
-                    )
-                    .into_response()
-            }
-            LionView::Show(ViewEngine(v), lion, IncomingFlashes { flashes, .. }) => {
-                format::render()
-                    .view(
-                        &v,
-                        "lions/show.html",
-                        json!({ "lion": lion, "flashes": flashes }),
-                    )
-                    .into_response()
-            }
+            LionView::Index(
+                format,
+                ViewEngine(v),
+                page,
+                IncomingFlashes { flashes, .. },
+                permissions,
+            ) => format::render()
+                .negotiate(
+                    format,
+                    &v,
+                    "lions/index.html",
+                    json!({ "lions": page.records, "page": page.meta, "flashes": flashes, "permissions": permissions }),
+                    &page,
+                )
+                .into_response(),
+            LionView::Show(
+                format,
+                ViewEngine(v),
+                lion,
+                IncomingFlashes { flashes, .. },
+                permissions,
+            ) => format::render()
+                .negotiate(
+                    format,
+                    &v,
+                    "lions/show.html",
+                    json!({ "lion": lion, "flashes": flashes, "permissions": permissions }),
+                    &lion,
+                )
+                .into_response(),
+            LionView::Update(ViewEngine(v), lion) => format::render()
+                .view_block(&v, "lions/show.html", "update", json!({ "lion": lion }))
+                .into_response(),
+            LionView::CreateForm(
+                hx,
+                ViewEngine(v),
+                page,
+                form,
+                IncomingFlashes { flashes, .. },
+                permissions,
+            ) => format::render()
+                .invalid_form(
+                    &hx,
+                    &v,
+                    "lions/index.html",
+                    "form",
+                    json!({ "lions": page.records, "page": page.meta, "form": form, "flashes": flashes, "permissions": permissions }),
+                )
+                .into_response(),
+            LionView::UpdateForm(
+                hx,
+                ViewEngine(v),
+                lion,
+                form,
+                IncomingFlashes { flashes, .. },
+                permissions,
+            ) => format::render()
+                .invalid_form(
+                    &hx,
+                    &v,
+                    "lions/show.html",
+                    "update",
+                    json!({ "lion": lion, "form": form, "flashes": flashes, "permissions": permissions }),
+                )
+                .into_response(),
         }
     }
 }
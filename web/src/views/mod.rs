@@ -1,5 +1,7 @@
+pub mod account;
 pub mod auth;
 pub mod home;
 pub mod invoices;
 pub mod lions;
+pub mod teams;
 pub mod todos;
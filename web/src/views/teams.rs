@@ -0,0 +1,93 @@
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use shipwright_db::entities::{
+    team::{Membership, Team, TeamMember},
+    team_invitation::TeamInvitation,
+};
+use shipwright_ui::view_engine::{View, ViewEngine};
+
+use crate::format;
+use crate::middlewares::flash::IncomingFlashes;
+use crate::return_url::ReturnUrl;
+
+pub enum TeamView {
+    /// The teams of the user and the form for creating one.
+    Index(ViewEngine<View>, IncomingFlashes, Vec<Membership>),
+    /// A team with its members, and the pending invitations if the user owns it.
+    Show(
+        ViewEngine<View>,
+        IncomingFlashes,
+        Membership,
+        Vec<TeamMember>,
+        Vec<TeamInvitation>,
+    ),
+    /// The page of an invitation link, with the invitation if it is still valid, the token of
+    /// the link, the page to return to after signing in and whether the user is signed in.
+    Accept(
+        ViewEngine<View>,
+        IncomingFlashes,
+        Option<(TeamInvitation, Team)>,
+        Option<String>,
+        Option<ReturnUrl>,
+        bool,
+    ),
+}
+
+impl IntoResponse for TeamView {
+    fn into_response(self) -> Response {
+        match self {
+            TeamView::Index(ViewEngine(v), IncomingFlashes { flashes, .. }, teams) => {
+                format::render()
+                    .view(
+                        &v,
+                        "teams/index.html",
+                        json!({"flashes": flashes, "teams": teams}),
+                    )
+                    .into_response()
+            }
+            TeamView::Show(
+                ViewEngine(v),
+                IncomingFlashes { flashes, .. },
+                team,
+                members,
+                invitations,
+            ) => format::render()
+                .view(
+                    &v,
+                    "teams/show.html",
+                    json!({
+                        "flashes": flashes,
+                        "team": team,
+                        "is_owner": team.is_owner(),
+                        "members": members,
+                        "invitations": invitations,
+                    }),
+                )
+                .into_response(),
+            TeamView::Accept(
+                ViewEngine(v),
+                IncomingFlashes { flashes, .. },
+                invitation,
+                invite_token,
+                next,
+                signed_in,
+            ) => {
+                let (invitation, team) = invitation.unzip();
+                format::render()
+                    .view(
+                        &v,
+                        "teams/invitations/accept.html",
+                        json!({
+                            "flashes": flashes,
+                            "invitation": invitation,
+                            "invite_token": invite_token,
+                            "team": team,
+                            "next": next,
+                            "signed_in": signed_in,
+                        }),
+                    )
+                    .into_response()
+            }
+        }
+    }
+}
@@ -1,36 +1,116 @@
 use axum::response::{IntoResponse, Response};
-use shipwright_db::entities::todo::Todo;
-use shipwright_ui::view_engine::{View, ViewEngine};
 use serde_json::json;
+use shipwright_db::{Page, entities::todo::Todo};
+use shipwright_ui::view_engine::{View, ViewEngine};
 
-use crate::{format, middlewares::flash::IncomingFlashes};
+use crate::{
+    form::FormState,
+    format,
+    htmx::HxRequest,
+    middlewares::{auth::Permissions, flash::IncomingFlashes},
+    negotiation::Format,
+};
 
+/// Renders the todo templates, or serializes the todos for JSON clients.
 pub enum TodoView {
-    Index(ViewEngine<View>, Vec<Todo>, IncomingFlashes),
-    Show(ViewEngine<View>, Todo, IncomingFlashes),
+    Index(
+        Format,
+        ViewEngine<View>,
+        Page<Todo>,
+        IncomingFlashes,
+        Permissions,
+    ),
+    Show(Format, ViewEngine<View>, Todo, IncomingFlashes, Permissions),
+    /// The update form of the show page, for htmx requests swapping it
+    Update(ViewEngine<View>, Todo),
+    /// The index page with an invalid new todo, htmx requests only get the form
+    CreateForm(
+        HxRequest,
+        ViewEngine<View>,
+        Page<Todo>,
+        FormState,
+        IncomingFlashes,
+        Permissions,
+    ),
+    /// The show page with an invalid update of the todo, htmx requests only get the form
+    UpdateForm(
+        HxRequest,
+        ViewEngine<View>,
+        Todo,
+        FormState,
+        IncomingFlashes,
+        Permissions,
+    ),
 }
 
 impl IntoResponse for TodoView {
     fn into_response(self) -> Response {
         match self {
-            TodoView::Index(ViewEngine(v), todos, IncomingFlashes { flashes, .. }) => {
-                format::render()
-                    .view(
-                        &v,
-                        "todos/index.html",
-                        json!({ "todos": todos, "flashes": flashes }),
-                    )
-                    .into_response()
-            }
-            TodoView::Show(ViewEngine(v), todo, IncomingFlashes { flashes, .. }) => {
-                format::render()
-                    .view(
-                        &v,
-                        "todos/show.html",
-                        json!({ "todo": todo, "flashes": flashes }),
-                    )
-                    .into_response()
-            }
+            TodoView::Index(
+                format,
+                ViewEngine(v),
+                page,
+                IncomingFlashes { flashes, .. },
+                permissions,
+            ) => format::render()
+                .negotiate(
+                    format,
+                    &v,
+                    "todos/index.html",
+                    json!({ "todos": page.records, "page": page.meta, "flashes": flashes, "permissions": permissions }),
+                    &page,
+                )
+                .into_response(),
+            TodoView::Show(
+                format,
+                ViewEngine(v),
+                todo,
+                IncomingFlashes { flashes, .. },
+                permissions,
+            ) => format::render()
+                .negotiate(
+                    format,
+                    &v,
+                    "todos/show.html",
+                    json!({ "todo": todo, "flashes": flashes, "permissions": permissions }),
+                    &todo,
+                )
+                .into_response(),
+            TodoView::Update(ViewEngine(v), todo) => format::render()
+                .view_block(&v, "todos/show.html", "update", json!({ "todo": todo }))
+                .into_response(),
+            TodoView::CreateForm(
+                hx,
+                ViewEngine(v),
+                page,
+                form,
+                IncomingFlashes { flashes, .. },
+                permissions,
+            ) => format::render()
+                .invalid_form(
+                    &hx,
+                    &v,
+                    "todos/index.html",
+                    "form",
+                    json!({ "todos": page.records, "page": page.meta, "form": form, "flashes": flashes, "permissions": permissions }),
+                )
+                .into_response(),
+            TodoView::UpdateForm(
+                hx,
+                ViewEngine(v),
+                todo,
+                form,
+                IncomingFlashes { flashes, .. },
+                permissions,
+            ) => format::render()
+                .invalid_form(
+                    &hx,
+                    &v,
+                    "todos/show.html",
+                    "update",
+                    json!({ "todo": todo, "form": form, "flashes": flashes, "permissions": permissions }),
+                )
+                .into_response(),
         }
     }
 }
@@ -0,0 +1,114 @@
+use super::{mock_logged_in_state, test_request_with_db};
+use axum::http::header::CONTENT_DISPOSITION;
+use fake::{Fake as _, Faker};
+use shipwright_db::{
+    DbPool, MIGRATOR, OwnedEntity as _,
+    entities::{
+        account_deletion::{AccountDeletion, DeleteAccount},
+        todo::{Todo, TodoChangeset},
+        user::{RegisterUser, User, UserCredentials, UserStatus},
+    },
+};
+
+async fn confirmed_user(pool: &DbPool) -> (User, UserCredentials) {
+    let user: RegisterUser = Faker.fake();
+
+    let saved_user = User::create(user.clone(), pool).await.unwrap();
+    let saved_user = User::update_status(saved_user.id, UserStatus::Confirmed, pool)
+        .await
+        .unwrap();
+
+    let credentials = UserCredentials {
+        email: user.email,
+        password: user.password,
+        next: None,
+    };
+
+    (saved_user, credentials)
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn the_export_holds_the_data_of_the_user_without_secrets(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let user = mock_logged_in_state(&request, &pool).await;
+        let changeset = TodoChangeset {
+            description: "buy milk".to_string(),
+        };
+        Todo::create_for_owner(user.id, changeset, &pool)
+            .await
+            .unwrap();
+
+        let response = request.get("/account/data/export").await;
+
+        response.assert_status_ok();
+        response.assert_header(
+            CONTENT_DISPOSITION,
+            r#"attachment; filename="account-data.json""#,
+        );
+        let export = response.json::<serde_json::Value>();
+        assert_eq!(export["account"]["email"], user.email.as_str());
+        assert_eq!(export["todos"][0]["description"], "buy milk");
+        assert!(!response.text().contains(&user.password_hash));
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn deleting_the_account_is_scheduled_and_can_be_cancelled(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (user, credentials) = confirmed_user(&pool).await;
+        request.post("/auth/login").form(&credentials).await;
+
+        let response = request
+            .post("/account/data/delete")
+            .form(&DeleteAccount {
+                current_password: credentials.password.clone(),
+            })
+            .await;
+
+        response.assert_header("location", "/account/data");
+        let deletion = AccountDeletion::try_get_for_user(user.id, &pool)
+            .await
+            .unwrap()
+            .expect("the deletion should have been scheduled");
+        // The account stays until the grace period is over
+        assert!(deletion.delete_after > deletion.requested_at);
+        request
+            .get("/account/data")
+            .await
+            .assert_text_contains(deletion.delete_after.date().to_string());
+
+        request.post("/account/data/delete/cancel").await;
+
+        let deletion = AccountDeletion::try_get_for_user(user.id, &pool)
+            .await
+            .unwrap();
+        assert!(deletion.is_none());
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn deleting_the_account_needs_the_current_password(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (user, credentials) = confirmed_user(&pool).await;
+        request.post("/auth/login").form(&credentials).await;
+
+        request
+            .post("/account/data/delete")
+            .form(&DeleteAccount {
+                current_password: "not my password".to_string(),
+            })
+            .await
+            .assert_header("location", "/account/data");
+
+        let deletion = AccountDeletion::try_get_for_user(user.id, &pool)
+            .await
+            .unwrap();
+        assert!(deletion.is_none());
+    })
+    .await
+}
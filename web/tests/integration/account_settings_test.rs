@@ -0,0 +1,204 @@
+use super::test_request_with_db;
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use fake::{Fake as _, Faker};
+use shipwright_db::{
+    DbPool, MIGRATOR,
+    entities::{
+        email_change_token::{ConfirmEmailChange, EmailChangeToken},
+        user::{ChangeEmail, ChangePassword, RegisterUser, User, UserCredentials, UserStatus},
+    },
+};
+use tower_sessions::cookie::Cookie;
+
+async fn confirmed_user(pool: &DbPool) -> (User, UserCredentials) {
+    let user: RegisterUser = Faker.fake();
+
+    let saved_user = User::create(user.clone(), pool).await.unwrap();
+    let saved_user = User::update_status(saved_user.id, UserStatus::Confirmed, pool)
+        .await
+        .unwrap();
+
+    let credentials = UserCredentials {
+        email: user.email,
+        password: user.password,
+        next: None,
+    };
+
+    (saved_user, credentials)
+}
+
+/// Logs in from another browser, the session cookie is returned rather than saved.
+async fn login_elsewhere(request: &TestServer, credentials: &UserCredentials) -> Cookie<'static> {
+    request
+        .post("/auth/login")
+        .clear_cookies()
+        .do_not_save_cookies()
+        .form(credentials)
+        .await
+        .cookie("id")
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn changing_the_password_needs_the_current_password(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (user, credentials) = confirmed_user(&pool).await;
+        request.post("/auth/login").form(&credentials).await;
+
+        let response = request
+            .post("/account/password")
+            .form(&ChangePassword {
+                current_password: "not my password".to_string(),
+                password: "a new password".to_string(),
+                confirm_password: "a new password".to_string(),
+            })
+            .await;
+
+        response.assert_header("location", "/account/password");
+        let unchanged = User::try_get_by_id(&user.id, &pool).await.unwrap().unwrap();
+        assert_eq!(unchanged.password_hash, user.password_hash);
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn changing_the_password_signs_out_the_other_sessions(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (_, credentials) = confirmed_user(&pool).await;
+        let elsewhere = login_elsewhere(&request, &credentials).await;
+        request.post("/auth/login").form(&credentials).await;
+
+        let response = request
+            .post("/account/password")
+            .form(&ChangePassword {
+                current_password: credentials.password.clone(),
+                password: "a new password".to_string(),
+                confirm_password: "a new password".to_string(),
+            })
+            .await;
+
+        response.assert_header("location", "/account/password");
+        request.get("/todos").await.assert_status_ok();
+        request
+            .get("/todos")
+            .clear_cookies()
+            .add_cookie(elsewhere)
+            .do_not_save_cookies()
+            .await
+            .assert_status(StatusCode::TEMPORARY_REDIRECT);
+
+        // 😉 The new password logs in from now on
+        request.post("/auth/logout").await;
+        request
+            .post("/auth/login")
+            .form(&UserCredentials {
+                password: "a new password".to_string(),
+                ..credentials
+            })
+            .await;
+        request.get("/todos").await.assert_status_ok();
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn a_new_email_is_only_used_once_it_is_confirmed(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (user, credentials) = confirmed_user(&pool).await;
+        request.post("/auth/login").form(&credentials).await;
+
+        let response = request
+            .post("/account/email")
+            .form(&ChangeEmail {
+                email: "new@example.com".to_string(),
+                current_password: credentials.password.clone(),
+            })
+            .await;
+
+        response.assert_header("location", "/account/email");
+        let unchanged = User::try_get_by_id(&user.id, &pool).await.unwrap().unwrap();
+        assert_eq!(unchanged.email, user.email);
+
+        // Only the hash of the emailed token is stored
+        let new_emails = sqlx::query_scalar!(
+            "SELECT new_email FROM email_change_tokens WHERE user_id = $1",
+            user.id
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(new_emails, vec!["new@example.com".to_string()]);
+        // The emailed token can't be read back, so the link is followed with a new one
+        let change_token = EmailChangeToken::create(user.id, "new@example.com", &pool)
+            .await
+            .unwrap();
+
+        // Opening the link doesn't use it up
+        request
+            .get("/account/email/confirm")
+            .add_query_param("token", &change_token)
+            .await
+            .assert_text_contains(&change_token);
+
+        let form = ConfirmEmailChange { change_token };
+        let response = request.post("/account/email/confirm").form(&form).await;
+
+        response.assert_header("location", "/account/email");
+        let changed = User::try_get_by_id(&user.id, &pool).await.unwrap().unwrap();
+        assert_eq!(changed.email, "new@example.com");
+
+        // The same link can't be used twice
+        let response = request.post("/account/email/confirm").form(&form).await;
+        response.assert_header("location", "/account/email");
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn changing_the_email_needs_the_current_password(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (user, credentials) = confirmed_user(&pool).await;
+        request.post("/auth/login").form(&credentials).await;
+
+        request
+            .post("/account/email")
+            .form(&ChangeEmail {
+                email: "new@example.com".to_string(),
+                current_password: "not my password".to_string(),
+            })
+            .await
+            .assert_header("location", "/account/email");
+
+        let tokens = EmailChangeToken::delete_for_user(user.id, &pool)
+            .await
+            .unwrap();
+        assert_eq!(tokens, 0, "no confirmation link should have been sent");
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn an_email_taken_in_the_meantime_is_not_changed(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (user, _) = confirmed_user(&pool).await;
+        let (other, _) = confirmed_user(&pool).await;
+        let change_token = EmailChangeToken::create(user.id, &other.email, &pool)
+            .await
+            .unwrap();
+
+        let response = request
+            .post("/account/email/confirm")
+            .form(&ConfirmEmailChange { change_token })
+            .await;
+
+        response.assert_header("location", "/account/email");
+        let unchanged = User::try_get_by_id(&user.id, &pool).await.unwrap().unwrap();
+        assert_eq!(unchanged.email, user.email);
+    })
+    .await
+}
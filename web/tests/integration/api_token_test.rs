@@ -0,0 +1,175 @@
+use super::{mock_logged_in_state, test_request_with_db};
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use fake::{Fake as _, Faker};
+use serde_json::Value;
+use shipwright_context::Account;
+use shipwright_db::{
+    DbPool, MIGRATOR,
+    entities::{
+        api_token::{ApiScope, ApiToken, CreateApiToken},
+        role::Role,
+        todo::TodoChangeset,
+        user::{RegisterUser, User, UserStatus},
+    },
+};
+use time::{Duration, OffsetDateTime};
+
+/// Creates a confirmed user with a token, returns the user and the token.
+async fn mock_api_token(
+    pool: &DbPool,
+    scope: ApiScope,
+    expires_at: Option<OffsetDateTime>,
+) -> (User, String) {
+    let user: RegisterUser = Faker.fake();
+    let user = User::create(user, pool).await.unwrap();
+    let user = User::update_status(user.id, UserStatus::Confirmed, pool)
+        .await
+        .unwrap();
+    Role::assign(user.id, Role::MEMBER, pool).await.unwrap();
+
+    let token = Account::generate_api_token();
+    ApiToken::create(
+        user.id,
+        &CreateApiToken {
+            name: "test script".to_string(),
+            scope,
+            expires_in_days: 0,
+        },
+        &Account::hash_api_token(&token),
+        expires_at,
+        pool,
+    )
+    .await
+    .unwrap();
+
+    (user, token)
+}
+
+async fn get_me(request: &TestServer, token: &str) -> axum_test::TestResponse {
+    request.get("/api/me").authorization_bearer(token).await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn bearer_token_authenticates_api_requests(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let (user, token) = mock_api_token(&pool, ApiScope::Read, None).await;
+
+        let response = get_me(&request, &token).await;
+
+        response.assert_status_ok();
+        assert_eq!(response.json::<Value>()["email"], user.email);
+        assert!(
+            response.maybe_cookie("id").is_none(),
+            "oops a session was created for a bearer request"
+        );
+
+        let api_token = ApiToken::try_get_by_hash(&Account::hash_api_token(&token), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(
+            api_token.last_used_at.is_some(),
+            "token use was not recorded"
+        );
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn api_refuses_missing_invalid_and_expired_tokens(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let (_, expired) = mock_api_token(
+            &pool,
+            ApiScope::Read,
+            Some(OffsetDateTime::now_utc() - Duration::days(1)),
+        )
+        .await;
+
+        request
+            .get("/api/me")
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+        get_me(&request, "swp_notarealtoken")
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+        get_me(&request, &expired)
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn read_tokens_cannot_write(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let (_, read) = mock_api_token(&pool, ApiScope::Read, None).await;
+        let (_, write) = mock_api_token(&pool, ApiScope::Write, None).await;
+        let todo: TodoChangeset = Faker.fake();
+
+        request
+            .get("/api/todos")
+            .authorization_bearer(&read)
+            .await
+            .assert_status_ok();
+        request
+            .post("/api/todos")
+            .authorization_bearer(&read)
+            .json(&todo)
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+        request
+            .post("/api/todos")
+            .authorization_bearer(&write)
+            .json(&todo)
+            .await
+            .assert_status(StatusCode::CREATED);
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn account_page_creates_and_revokes_tokens(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let user = mock_logged_in_state(&request, &pool).await;
+
+        let response = request
+            .post("/account/api-tokens")
+            .form(&CreateApiToken {
+                name: "laptop".to_string(),
+                scope: ApiScope::Write,
+                expires_in_days: 30,
+            })
+            .await;
+        response.assert_status_ok();
+
+        let body = response.text();
+        let token = body
+            .split("<code>")
+            .nth(1)
+            .and_then(|rest| rest.split("</code>").next())
+            .expect("the token was not shown")
+            .to_string();
+
+        let api_token = ApiToken::try_get_by_hash(&Account::hash_api_token(&token), &pool)
+            .await
+            .unwrap()
+            .expect("the token was not stored");
+        assert_eq!(api_token.user_id, user.id);
+        assert!(api_token.expires_at.is_some(), "the expiry was not stored");
+
+        request.clear_cookies();
+        get_me(&request, &token).await.assert_status_ok();
+
+        request.save_cookies();
+        mock_logged_in_state(&request, &pool).await;
+        // 😉Someone else's token can't be revoked
+        request
+            .post(&format!("/account/api-tokens/{}/revoke", api_token.id))
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+        get_me(&request, &token).await.assert_status_ok();
+    })
+    .await
+}
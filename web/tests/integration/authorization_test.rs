@@ -0,0 +1,93 @@
+use super::{mock_logged_in_admin, mock_logged_in_state, test_request_with_db};
+use fake::{Fake as _, Faker};
+use shipwright_db::{
+    DbPool, MIGRATOR,
+    entities::{
+        lions::LionChangeset,
+        role::{Permission, Role},
+        todo::TodoChangeset,
+    },
+};
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn members_may_write_todos_but_not_lions(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_state(&request, &pool).await;
+
+        let todo: TodoChangeset = Faker.fake();
+        let response = request.post("/todos").form(&todo).await;
+        response.assert_status_see_other();
+
+        let lion: LionChangeset = Faker.fake();
+        let response = request.post("/lions").form(&lion).await;
+        response.assert_status_forbidden();
+
+        let response = request.delete("/lions/1").await;
+        response.assert_status_forbidden();
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn anonymous_visitors_may_read_but_not_write_lions(pool: DbPool) {
+    test_request_with_db::<_, _>(pool, |request| async move {
+        let response = request.get("/lions").await;
+        response.assert_status_ok();
+        assert!(
+            !response.text().contains("Add a Lion"),
+            "the create form was shown to a visitor who may not use it"
+        );
+
+        let lion: LionChangeset = Faker.fake();
+        let response = request.post("/lions").form(&lion).await;
+        response.assert_status_forbidden();
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn admins_may_write_lions(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_admin(&request, &pool).await;
+
+        let response = request.get("/lions").await;
+        response.assert_text_contains("Add a Lion");
+
+        let lion: LionChangeset = Faker.fake();
+        let response = request.post("/lions").form(&lion).await;
+        response.assert_status_see_other();
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn permissions_are_the_union_of_the_roles(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let user = mock_logged_in_state(&request, &pool).await;
+
+        let permissions = Role::load_permissions_for_user(user.id, &pool)
+            .await
+            .unwrap();
+        assert_eq!(permissions, vec![Permission::from("todos.write")]);
+
+        // Granting a role twice is harmless
+        Role::assign(user.id, Role::ADMIN, &pool).await.unwrap();
+        Role::assign(user.id, Role::ADMIN, &pool).await.unwrap();
+
+        let mut permissions = Role::load_permissions_for_user(user.id, &pool)
+            .await
+            .unwrap();
+        permissions.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            permissions,
+            vec![
+                Permission::from("invoices.write"),
+                Permission::from("lions.write"),
+                Permission::from("todos.write"),
+            ]
+        );
+    })
+    .await
+}
@@ -0,0 +1,177 @@
+use super::test_request_with_config;
+use axum::http::{HeaderName, HeaderValue, StatusCode, header::AUTHORIZATION};
+use axum_test::TestServer;
+use fake::{Fake as _, Faker};
+use shipwright_db::{
+    DbPool, MIGRATOR,
+    entities::user::{RegisterUser, User, UserStatus},
+};
+
+async fn confirmed_user(pool: &DbPool) -> RegisterUser {
+    let user: RegisterUser = Faker.fake();
+
+    let saved_user = User::create(user.clone(), pool).await.unwrap();
+    User::update_status(saved_user.id, UserStatus::Confirmed, pool)
+        .await
+        .unwrap();
+
+    user
+}
+
+/// Opens the login page and reads the csrf token of the session from it.
+async fn csrf_token(request: &TestServer) -> String {
+    let page = request.get("/auth/login").await.text();
+
+    page.split(r#"<meta name="csrf-token" content=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .expect("the page has no csrf token")
+        .to_string()
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn forms_posted_without_a_token_are_refused(pool: DbPool) {
+    test_request_with_config(
+        pool.clone(),
+        |config| config.csrf.enabled = true,
+        |mut request| async move {
+            request.save_cookies();
+            let user = confirmed_user(&pool).await;
+            csrf_token(&request).await;
+
+            let response = request
+                .post("/auth/login")
+                .form(&[("email", user.email), ("password", user.password)])
+                .await;
+
+            response.assert_status(StatusCode::FORBIDDEN);
+            response.assert_text_contains("reload the page");
+            request
+                .get("/todos")
+                .await
+                .assert_status(StatusCode::TEMPORARY_REDIRECT);
+        },
+    )
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn forms_posted_with_the_token_of_the_session_are_accepted(pool: DbPool) {
+    test_request_with_config(
+        pool.clone(),
+        |config| config.csrf.enabled = true,
+        |mut request| async move {
+            request.save_cookies();
+            let user = confirmed_user(&pool).await;
+            let token = csrf_token(&request).await;
+
+            let response = request
+                .post("/auth/login")
+                .form(&[
+                    ("email", user.email),
+                    ("password", user.password),
+                    ("_csrf", token),
+                ])
+                .await;
+
+            response.assert_status_see_other();
+            request.get("/todos").await.assert_status_ok();
+        },
+    )
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn htmx_requests_send_the_token_in_a_header(pool: DbPool) {
+    test_request_with_config(
+        pool.clone(),
+        |config| config.csrf.enabled = true,
+        |mut request| async move {
+            request.save_cookies();
+            let user = confirmed_user(&pool).await;
+            let token = csrf_token(&request).await;
+
+            let response = request
+                .post("/auth/login")
+                .add_header(
+                    HeaderName::from_static("x-csrf-token"),
+                    HeaderValue::from_str(&token).unwrap(),
+                )
+                .form(&[("email", user.email), ("password", user.password)])
+                .await;
+
+            response.assert_status_see_other();
+        },
+    )
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn tokens_of_other_sessions_are_refused(pool: DbPool) {
+    test_request_with_config(
+        pool.clone(),
+        |config| config.csrf.enabled = true,
+        |mut request| async move {
+            request.save_cookies();
+            let user = confirmed_user(&pool).await;
+            let token = csrf_token(&request).await;
+
+            let response = request
+                .post("/auth/login")
+                .clear_cookies()
+                .do_not_save_cookies()
+                .form(&[
+                    ("email", user.email),
+                    ("password", user.password),
+                    ("_csrf", token),
+                ])
+                .await;
+
+            response.assert_status(StatusCode::FORBIDDEN);
+        },
+    )
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn api_requests_with_a_bearer_token_need_no_csrf_token(pool: DbPool) {
+    test_request_with_config(
+        pool.clone(),
+        |config| config.csrf.enabled = true,
+        |request| async move {
+            // The api refuses the unknown token itself, the csrf check stays out of its way
+            let response = request
+                .post("/api/todos")
+                .add_header(AUTHORIZATION, HeaderValue::from_static("Bearer unknown"))
+                .json(&serde_json::json!({ "description": "buy milk" }))
+                .await;
+
+            response.assert_status(StatusCode::UNAUTHORIZED);
+        },
+    )
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn forms_over_the_size_limit_are_refused_as_too_large(pool: DbPool) {
+    test_request_with_config(
+        pool.clone(),
+        |config| config.csrf.enabled = true,
+        |mut request| async move {
+            request.save_cookies();
+            let token = csrf_token(&request).await;
+
+            let response = request
+                .post("/auth/login")
+                .form(&[
+                    ("email", "x".repeat(3 * 1024 * 1024)),
+                    ("password", "password".to_string()),
+                    ("_csrf", token),
+                ])
+                .await;
+
+            response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+        },
+    )
+    .await
+}
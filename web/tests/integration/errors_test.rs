@@ -0,0 +1,107 @@
+use crate::{mock_logged_in_state, test_request_with_db};
+
+use axum::http::{HeaderValue, StatusCode, header::ACCEPT};
+use serde_json::{Value, json};
+use shipwright_db::{DbPool, MIGRATOR, entities::todo::TodoChangeset};
+
+fn accept_json() -> HeaderValue {
+    HeaderValue::from_static("application/json")
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn browsers_get_an_error_page(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_state(&request, &pool).await;
+
+        let response = request.get("/no-such-page").await;
+        response.assert_status_not_found();
+        response.assert_header("content-type", "text/html; charset=utf-8");
+        response.assert_text_contains("404 Not Found");
+        response.assert_text_contains("doesn't exist");
+
+        request
+            .get("/todos/999999")
+            .await
+            .assert_text_contains("404 Not Found");
+    })
+    .await;
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn json_clients_get_problem_details(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_state(&request, &pool).await;
+
+        let response = request
+            .get("/todos/999999")
+            .add_header(ACCEPT, accept_json())
+            .await;
+
+        response.assert_status_not_found();
+        response.assert_header("content-type", "application/problem+json");
+        assert_eq!(
+            response.json::<Value>(),
+            json!({ "type": "about:blank", "title": "Not Found", "status": 404, "detail": "no record found" })
+        );
+    })
+    .await;
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn invalid_fields_are_listed(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_state(&request, &pool).await;
+        let invalid = TodoChangeset {
+            description: String::new(),
+        };
+
+        let response = request
+            .post("/todos")
+            .add_header(ACCEPT, accept_json())
+            .json(&invalid)
+            .await;
+
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            response.json::<Value>()["errors"],
+            json!({ "description": ["Description must be at least 1 character long"] })
+        );
+
+        // Browsers get the form again, see forms_test
+        let page = request.post("/todos").form(&invalid).await;
+        page.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        page.assert_text_contains(r#"id="description-errors""#);
+    })
+    .await;
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn internal_errors_are_not_returned(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_state(&request, &pool).await;
+        sqlx::query("DROP TABLE todos")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let response = request.get("/todos").await;
+        response.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        response.assert_text_contains("Something went wrong");
+        assert!(!response.text().contains("no such table"));
+
+        let response = request
+            .get("/todos")
+            .add_header(ACCEPT, accept_json())
+            .await;
+        response.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            response.json::<Value>(),
+            json!({ "type": "about:blank", "title": "Internal Server Error", "status": 500 })
+        );
+    })
+    .await;
+}
@@ -1,2 +0,0 @@
-INSERT INTO todos (description) VALUES ('buy milk');
-
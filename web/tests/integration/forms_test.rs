@@ -0,0 +1,113 @@
+use crate::{mock_logged_in_state, test_request, test_request_with_db};
+
+use axum::http::StatusCode;
+use shipwright_db::{
+    DbPool, Entity as _, MIGRATOR, OwnedEntity as _,
+    entities::{
+        todo::{Todo, TodoChangeset},
+        user::RegisterUser,
+    },
+};
+
+fn invalid_user() -> RegisterUser {
+    RegisterUser {
+        email: "not-an-email".to_string(),
+        password: "short".to_string(),
+        confirm_password: "shorter".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn invalid_registrations_show_the_form_again() {
+    test_request(|request| async move {
+        let response = request.post("/auth/register").form(&invalid_user()).await;
+
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        response.assert_text_contains("<h1>Register</h1>");
+        response.assert_text_contains(r#"value="not-an-email""#);
+        response.assert_text_contains("Must be a valid email address");
+        response.assert_text_contains("password must be at least 8 characters");
+        response.assert_text_contains("passwords do not match");
+        // Passwords are never sent back
+        assert!(!response.text().contains("short"));
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn htmx_registrations_swap_only_the_form() {
+    test_request(|request| async move {
+        let response = request
+            .post("/auth/register")
+            .add_header("hx-request", "true")
+            .form(&invalid_user())
+            .await;
+
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        let body = response.text();
+        assert!(body.trim_start().starts_with("<form"));
+        assert!(!body.contains("<h1>Register</h1>"));
+        assert!(body.contains(r#"id="email-errors""#));
+    })
+    .await;
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn invalid_todos_show_the_form_with_the_list(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let user = mock_logged_in_state(&request, &pool).await;
+        Todo::create_for_owner(user.id, changeset("buy milk"), &pool)
+            .await
+            .unwrap();
+
+        let response = request.post("/todos").form(&changeset("")).await;
+
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        response.assert_text_contains("buy milk");
+        response.assert_text_contains("Description must be at least 1 character long");
+
+        let response = request
+            .post("/todos")
+            .add_header("hx-request", "true")
+            .form(&changeset(""))
+            .await;
+
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(response.text().trim_start().starts_with("<form"));
+        assert!(!response.text().contains("buy milk"));
+    })
+    .await;
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn invalid_updates_keep_the_todo(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let user = mock_logged_in_state(&request, &pool).await;
+        let todo = Todo::create_for_owner(user.id, changeset("buy milk"), &pool)
+            .await
+            .unwrap();
+
+        let response = request
+            .put(&format!("/todos/{}", todo.id))
+            .add_header("hx-request", "true")
+            .form(&changeset(""))
+            .await;
+
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        response.assert_text_contains(r#"value="""#);
+        response.assert_text_contains("Description must be at least 1 character long");
+        assert_eq!(
+            Todo::load(todo.id, &pool).await.unwrap().description,
+            "buy milk"
+        );
+    })
+    .await;
+}
+
+fn changeset(description: &str) -> TodoChangeset {
+    TodoChangeset {
+        description: description.to_string(),
+    }
+}
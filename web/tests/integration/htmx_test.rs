@@ -0,0 +1,87 @@
+use crate::{mock_logged_in_state, test_request_with_db};
+
+use serde_json::{Value, json};
+use shipwright_db::{
+    DbPool, MIGRATOR, OwnedEntity as _,
+    entities::todo::{Todo, TodoChangeset},
+};
+
+fn changeset(description: &str) -> TodoChangeset {
+    TodoChangeset {
+        description: description.to_string(),
+    }
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn updates_from_htmx_swap_only_the_form(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let user = mock_logged_in_state(&request, &pool).await;
+        let todo = Todo::create_for_owner(user.id, changeset("buy milk"), &pool)
+            .await
+            .unwrap();
+
+        let response = request
+            .put(&format!("/todos/{}", todo.id))
+            .add_header("hx-request", "true")
+            .form(&changeset("buy oat milk"))
+            .await;
+
+        response.assert_status_ok();
+        let body = response.text();
+        assert!(body.trim_start().starts_with("<form"));
+        assert!(body.contains("buy oat milk"));
+        assert!(!body.contains("<html"));
+        // The flash is an event rather than a cookie
+        assert!(response.maybe_header("set-cookie").is_none_or(|cookie| {
+            !cookie.to_str().unwrap().contains("axum-flash")
+        }));
+        let trigger: Value = serde_json::from_str(response.header("hx-trigger").to_str().unwrap())
+            .unwrap();
+        assert_eq!(
+            trigger,
+            json!({ "flash": { "messages": [{ "level": "success", "message": "✅ updated todo" }] } })
+        );
+    })
+    .await;
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn redirects_of_htmx_requests_become_a_location(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_state(&request, &pool).await;
+
+        let response = request
+            .post("/todos")
+            .add_header("hx-request", "true")
+            .form(&changeset("buy milk"))
+            .await;
+
+        response.assert_status_ok();
+        let todo_url = response.header("hx-location");
+        assert!(todo_url.to_str().unwrap().starts_with("/todos/"));
+        assert!(response.maybe_header("location").is_none());
+        assert!(response.maybe_header("hx-trigger").is_some());
+    })
+    .await;
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn boosted_requests_are_redirected(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_state(&request, &pool).await;
+
+        let response = request
+            .post("/todos")
+            .add_header("hx-request", "true")
+            .add_header("hx-boosted", "true")
+            .form(&changeset("buy milk"))
+            .await;
+
+        response.assert_status_see_other();
+        assert!(response.maybe_header("hx-trigger").is_none());
+    })
+    .await;
+}
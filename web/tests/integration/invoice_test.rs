@@ -1,6 +1,6 @@
-use crate::{authenticated_request, test_request_with_db};
+use crate::{authenticated_request, mock_logged_in_admin, test_request_with_db};
 use shipwright_db::{DbPool, MIGRATOR, entities::invoices::InvoiceChangeset};
-use fake::{Fake, Faker};
+use fake::Fake;
 
 #[sqlx::test(migrator = "MIGRATOR")]
 async fn index_page_works_for_authenticated_users(pool: DbPool) {
@@ -14,9 +14,16 @@ async fn index_page_works_for_authenticated_users(pool: DbPool) {
 
 #[sqlx::test(migrator = "MIGRATOR")]
 async fn create_invoice_redirects_and_displays_in_ui(pool: DbPool) {
-    let invoice: InvoiceChangeset = Faker.fake();
+    // The amount is optional, it is set so the page can be checked for it
+    let amount: f64 = (1.00..100.00).fake();
+    let invoice = InvoiceChangeset {
+        amount: Some(amount),
+    };
+
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_admin(&request, &pool).await;
 
-    test_request_with_db::<_, _>(pool, |request| async move {
         let response = request.post("/invoices").form(&invoice).await;
 
         response.assert_status_see_other();
@@ -31,7 +38,7 @@ async fn create_invoice_redirects_and_displays_in_ui(pool: DbPool) {
 
         let response = request.get(location).await;
         
-        response.assert_text_contains(invoice.amount.unwrap().to_string());
+        response.assert_text_contains(amount.to_string());
     })
     .await
 }
@@ -1,4 +1,4 @@
-use crate::{authenticated_request, test_request_with_db};
+use crate::{authenticated_request, mock_logged_in_admin, test_request_with_db};
 use shipwright_db::{DbPool, MIGRATOR, entities::lions::LionChangeset};
 use fake::{Fake, Faker};
 
@@ -16,7 +16,10 @@ async fn index_page_works_for_authenticated_users(pool: DbPool) {
 async fn create_lion_redirects_and_displays_in_ui(pool: DbPool) {
     let lion: LionChangeset = Faker.fake();
 
-    test_request_with_db::<_, _>(pool, |request| async move {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_admin(&request, &pool).await;
+
         let response = request.post("/lions").form(&lion).await;
 
         response.assert_status_see_other();
@@ -3,17 +3,22 @@ use fake::{Fake as _, Faker};
 use shipwright_db::{
     DbPool, MIGRATOR,
     entities::{
+        login_throttle::LoginThrottle,
         session::Session,
-        user::{RegisterUser, User, UserCredentials},
+        user::{RegisterUser, User, UserCredentials, UserStatus},
     },
 };
+use time::{Duration, OffsetDateTime};
 
 #[sqlx::test(migrator = "MIGRATOR")]
 async fn login_creates_session_on_success(pool: DbPool) {
     test_request_with_db::<_, _>(pool.clone(), |request| async move {
         let user: RegisterUser = Faker.fake();
 
-        User::create(user.clone(), &pool).await.unwrap();
+        let saved_user = User::create(user.clone(), &pool).await.unwrap();
+        User::update_status(saved_user.id, UserStatus::Confirmed, &pool)
+            .await
+            .unwrap();
 
         let response = request
             .post("/auth/login")
@@ -30,7 +35,7 @@ async fn login_creates_session_on_success(pool: DbPool) {
 
         let session = sqlx::query_as!(
             Session,
-            "SELECT * FROM sessions WHERE id = ?1",
+            "SELECT * FROM sessions WHERE id = $1",
             session_cookie
         )
         .fetch_optional(&pool)
@@ -71,6 +76,46 @@ async fn login_does_not_create_session_on_invalid_credentials(pool: DbPool) {
     .await
 }
 
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn login_redirects_pending_users_to_confirm_page(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let user: RegisterUser = Faker.fake();
+
+        // 😉User has registered but not confirmed their email
+        User::create(user.clone(), &pool)
+            .await
+            .expect("failed to create user in test db");
+
+        let response = request
+            .post("/auth/login")
+            .form(&UserCredentials {
+                email: user.email,
+                password: user.password,
+                next: None,
+            })
+            .await;
+
+        response.assert_status_see_other();
+
+        let location = response
+            .headers()
+            .get("location")
+            .expect("unable to get redirect location header from response")
+            .to_str()
+            .unwrap();
+
+        assert_eq!(
+            location, "/auth/register/confirm",
+            "redirected to the wrong page"
+        );
+        assert!(
+            response.maybe_cookie("id").is_none(),
+            "oops a session cookie was created for a pending user"
+        );
+    })
+    .await
+}
+
 #[sqlx::test(migrator = "MIGRATOR")]
 async fn login_redirects_to_login_page_for_invalid_password(pool: DbPool) {
     test_request_with_db::<_, _>(pool.clone(), |request| async move {
@@ -134,3 +179,170 @@ async fn login_redirects_to_login_page_for_invalid_user(pool: DbPool) {
     })
     .await
 }
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn login_is_throttled_after_a_failed_attempt(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let user: RegisterUser = Faker.fake();
+
+        let saved_user = User::create(user.clone(), &pool).await.unwrap();
+        User::update_status(saved_user.id, UserStatus::Confirmed, &pool)
+            .await
+            .unwrap();
+
+        request
+            .post("/auth/login")
+            .form(&UserCredentials {
+                email: user.email.clone(),
+                password: "wrongPa$$word".into(),
+                next: None,
+            })
+            .await;
+
+        // 😉The right password is refused until the backoff has passed
+        let response = request
+            .post("/auth/login")
+            .form(&UserCredentials {
+                email: user.email.clone(),
+                password: user.password,
+                next: None,
+            })
+            .await;
+
+        response.assert_status_see_other();
+        assert!(
+            response.maybe_cookie("id").is_none(),
+            "oops a session cookie was created for a throttled login"
+        );
+
+        let throttle = LoginThrottle::try_get(&LoginThrottle::email_key(&user.email), &pool)
+            .await
+            .unwrap()
+            .expect("no throttle found in the database");
+
+        assert_eq!(
+            throttle.failures, 1,
+            "the throttled login counted as a failure"
+        );
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn login_shows_lockout_as_flash(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let user: RegisterUser = Faker.fake();
+
+        User::create(user.clone(), &pool).await.unwrap();
+        LoginThrottle::upsert(
+            &LoginThrottle::email_key(&user.email),
+            5,
+            OffsetDateTime::now_utc() + Duration::minutes(15),
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let response = request
+            .post("/auth/login")
+            .form(&UserCredentials {
+                email: user.email,
+                password: user.password,
+                next: None,
+            })
+            .await;
+
+        response.assert_status_see_other();
+
+        let location = response
+            .headers()
+            .get("location")
+            .expect("unable to get redirect location header from response")
+            .to_str()
+            .unwrap();
+
+        assert_eq!(location, "/auth/login", "redirected to the wrong page");
+        assert!(
+            response.maybe_cookie("axum-flash").is_some(),
+            "lockout was not shown as a flash message"
+        );
+    })
+    .await
+}
+
+async fn confirmed_user(pool: &DbPool) -> RegisterUser {
+    let user: RegisterUser = Faker.fake();
+
+    let saved_user = User::create(user.clone(), pool).await.unwrap();
+    User::update_status(saved_user.id, UserStatus::Confirmed, pool)
+        .await
+        .unwrap();
+
+    user
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn login_redirects_to_next(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let user = confirmed_user(&pool).await;
+
+        let response = request
+            .post("/auth/login")
+            .form(&UserCredentials {
+                email: user.email,
+                password: user.password,
+                next: Some("/todos?page=2".to_string()),
+            })
+            .await;
+
+        response.assert_status_see_other();
+        response.assert_header("location", "/todos?page=2");
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn login_never_redirects_to_other_sites(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        for next in ["https://evil.example", "//evil.example", "/\\evil.example"] {
+            let user = confirmed_user(&pool).await;
+
+            let response = request
+                .post("/auth/login")
+                .form(&UserCredentials {
+                    email: user.email,
+                    password: user.password,
+                    next: Some(next.to_string()),
+                })
+                .await;
+
+            response.assert_header("location", "/");
+        }
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn login_page_drops_next_urls_of_other_sites(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let response = request
+            .get("/auth/login")
+            .add_query_param("next", "https://evil.example/login")
+            .await;
+
+        response.assert_status_ok();
+        assert!(!response.text().contains("evil.example"));
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn protected_pages_redirect_to_login_and_back(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let response = request.get("/todos?page=2").await;
+
+        response.assert_status(axum::http::StatusCode::TEMPORARY_REDIRECT);
+        response.assert_header("location", "/auth/login?next=%2Ftodos%3Fpage%3D2");
+    })
+    .await
+}
@@ -0,0 +1,147 @@
+use super::{test_request_with_config, test_request_with_db};
+use axum::http::StatusCode;
+use fake::{Fake as _, Faker};
+use shipwright_db::{
+    DbPool, MIGRATOR,
+    entities::{
+        magic_link_token::{MagicLinkLogin, MagicLinkToken, RequestMagicLink},
+        user::{RegisterUser, User, UserStatus},
+    },
+    single_use_token,
+};
+
+async fn confirmed_user(pool: &DbPool) -> User {
+    let user: RegisterUser = Faker.fake();
+
+    let saved_user = User::create(user, pool).await.unwrap();
+    User::update_status(saved_user.id, UserStatus::Confirmed, pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn magic_links_are_only_routed_when_enabled(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        request
+            .get("/auth/magic-link")
+            .await
+            .assert_status_not_found();
+        let login_page = request.get("/auth/login").await.text();
+        assert!(!login_page.contains("/auth/magic-link"));
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn requesting_a_link_issues_a_login_token(pool: DbPool) {
+    test_request_with_config(
+        pool.clone(),
+        |config| config.auth.magic_link = true,
+        |request| async move {
+            let user = confirmed_user(&pool).await;
+
+            let response = request
+                .post("/auth/magic-link")
+                .form(&RequestMagicLink {
+                    email: user.email.clone(),
+                })
+                .await;
+
+            response.assert_status_see_other();
+
+            let tokens = sqlx::query_scalar!(
+                "SELECT token_hash FROM magic_link_tokens WHERE user_id = $1",
+                user.id
+            )
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+
+            assert_eq!(tokens.len(), 1, "expected exactly one login token");
+        },
+    )
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn following_a_link_logs_in_once(pool: DbPool) {
+    test_request_with_config(
+        pool.clone(),
+        |config| config.auth.magic_link = true,
+        |mut request| async move {
+            request.save_cookies();
+            let user = confirmed_user(&pool).await;
+            let login_token = MagicLinkToken::create(user.id, &pool).await.unwrap();
+
+            // Opening the link doesn't use it up
+            request
+                .get("/auth/magic-link/verify")
+                .add_query_param("token", &login_token)
+                .await
+                .assert_text_contains(&login_token);
+
+            // Only the hash is stored, a copy of the database doesn't give away the link
+            let stored = sqlx::query_scalar!(
+                "SELECT token_hash FROM magic_link_tokens WHERE user_id = $1",
+                user.id
+            )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            assert_ne!(stored, login_token);
+            assert_eq!(stored, single_use_token::hash(&login_token));
+
+            let form = MagicLinkLogin { login_token };
+            let response = request.post("/auth/magic-link/verify").form(&form).await;
+
+            response.assert_status_see_other();
+            response.assert_header("location", "/");
+            request.get("/protected").await.assert_status_ok();
+
+            request.post("/auth/logout").await;
+
+            // 😉 The same link can't be used twice
+            let response = request.post("/auth/magic-link/verify").form(&form).await;
+
+            response.assert_header("location", "/auth/magic-link");
+            request
+                .get("/protected")
+                .await
+                .assert_status(StatusCode::TEMPORARY_REDIRECT);
+        },
+    )
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn expired_links_are_refused(pool: DbPool) {
+    test_request_with_config(
+        pool.clone(),
+        |config| config.auth.magic_link = true,
+        |mut request| async move {
+            request.save_cookies();
+            let user = confirmed_user(&pool).await;
+            let login_token = MagicLinkToken::create(user.id, &pool).await.unwrap();
+
+            sqlx::query!(
+                "UPDATE magic_link_tokens SET expires_at = CURRENT_TIMESTAMP WHERE user_id = $1",
+                user.id
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            let response = request
+                .post("/auth/magic-link/verify")
+                .form(&MagicLinkLogin { login_token })
+                .await;
+
+            response.assert_header("location", "/auth/magic-link");
+            request
+                .get("/protected")
+                .await
+                .assert_status(StatusCode::TEMPORARY_REDIRECT);
+        },
+    )
+    .await
+}
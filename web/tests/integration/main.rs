@@ -2,10 +2,13 @@ use std::sync::OnceLock;
 
 use axum_test::{TestServer, TestServerBuilder};
 use fake::{Fake, Faker};
-use shipwright_config::Environment;
+use shipwright_config::{Config, Environment};
 use shipwright_db::{
     DbPool,
-    entities::user::{RegisterUser, User, UserCredentials},
+    entities::{
+        role::Role,
+        user::{RegisterUser, User, UserCredentials, UserStatus},
+    },
 };
 use shipwright_web::{app::App, state::AppState, tracing::Tracing};
 
@@ -23,6 +26,10 @@ pub async fn mock_logged_in_state(request: &TestServer, pool: &DbPool) -> User {
     let user: RegisterUser = Faker.fake();
 
     let saved_user = User::create(user.clone(), pool).await.unwrap();
+    let saved_user = User::update_status(saved_user.id, UserStatus::Confirmed, pool)
+        .await
+        .unwrap();
+    Role::assign(saved_user.id, Role::MEMBER, pool).await.unwrap();
 
     request
         .post("/auth/login")
@@ -35,6 +42,14 @@ pub async fn mock_logged_in_state(request: &TestServer, pool: &DbPool) -> User {
 
     saved_user
 }
+
+pub async fn mock_logged_in_admin(request: &TestServer, pool: &DbPool) -> User {
+    let user = mock_logged_in_state(request, pool).await;
+    Role::assign(user.id, Role::ADMIN, pool).await.unwrap();
+
+    user
+}
+
 pub async fn authenticated_request<F, Fut>(test_db: DbPool, callback: F)
 where
     F: FnOnce(TestServer) -> Fut,
@@ -49,6 +64,8 @@ where
     // [sqlx::test] sets up a test database when running the test and cleans up afterwards
     // https://docs.rs/sqlx/latest/sqlx/attr.test.html
     app_state.db_pool = test_db.clone();
+    // Requests are sent without a csrf token, csrf_test turns the check back on
+    app_state.config.csrf.enabled = false;
 
     if std::env::var("TEST_LOG").is_ok() {
         lazy_tracing(&app_state);
@@ -76,6 +93,17 @@ pub async fn test_request_with_db<F, Fut>(test_db: DbPool, callback: F)
 where
     F: FnOnce(TestServer) -> Fut,
     Fut: std::future::Future<Output = ()>,
+{
+    test_request_with_config(test_db, |_| {}, callback).await;
+}
+
+/// Like [`test_request_with_db`] but the config can be changed before the app is built, e.g. to
+/// point it at a mock server.
+pub async fn test_request_with_config<C, F, Fut>(test_db: DbPool, configure: C, callback: F)
+where
+    C: FnOnce(&mut Config),
+    F: FnOnce(TestServer) -> Fut,
+    Fut: std::future::Future<Output = ()>,
 {
     lazy_eyre();
 
@@ -86,6 +114,8 @@ where
     // [sqlx::test] sets up a test database when running the test and cleans up afterwards
     // https://docs.rs/sqlx/latest/sqlx/attr.test.html
     app_state.db_pool = test_db;
+    app_state.config.csrf.enabled = false;
+    configure(&mut app_state.config);
 
     if std::env::var("TEST_LOG").is_ok() {
         lazy_tracing(&app_state);
@@ -113,9 +143,10 @@ where
 {
     lazy_eyre();
 
-    let app_state = AppState::build(Environment::Test)
+    let mut app_state = AppState::build(Environment::Test)
         .await
         .expect("failed to build app context");
+    app_state.config.csrf.enabled = false;
 
     if std::env::var("TEST_LOG").is_ok() {
         lazy_tracing(&app_state);
@@ -136,7 +167,23 @@ where
     callback(server).await;
 }
 
+mod account_data_test;
+mod account_settings_test;
+mod api_token_test;
+mod authorization_test;
+mod csrf_test;
+mod errors_test;
+mod forms_test;
+mod htmx_test;
 mod invoice_test;
+mod lion_test;
 mod login_test;
+mod magic_link_test;
+mod oauth_test;
+mod openapi_test;
+mod password_reset_test;
+mod register_confirm_test;
+mod session_test;
+mod teams_test;
 mod todos_test;
-mod lion_test;
+mod two_factor_test;
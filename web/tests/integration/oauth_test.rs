@@ -0,0 +1,347 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use super::test_request_with_config;
+use axum::{
+    Form, Json, Router,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode, Uri, header::AUTHORIZATION},
+    routing::{get, post},
+};
+use axum_test::{TestResponse, TestServer};
+use fake::{Fake as _, Faker};
+use serde_json::{Value, json};
+use shipwright_config::OAuthProviderConfig;
+use shipwright_context::Account;
+use shipwright_db::{
+    DbPool, MIGRATOR,
+    entities::{
+        identity::{Identity, IdentityClaims},
+        user::{RegisterUser, User, UserCredentials, UserStatus},
+    },
+};
+use tokio::net::TcpListener;
+
+/// A stand-in OpenID Connect provider, so no real provider is needed.
+///
+/// Tests play the part of the user at the provider with [`MockIssuer::authorize`], the app then
+/// exchanges the code with PKCE and fetches the claims like it would from a real provider.
+#[derive(Clone, Default)]
+struct MockIssuer {
+    /// The code challenge and claims of each authorization code that hasn't been exchanged yet.
+    grants: Arc<Mutex<HashMap<String, (String, IdentityClaims)>>>,
+    /// The claims of each access token.
+    tokens: Arc<Mutex<HashMap<String, IdentityClaims>>>,
+}
+
+impl MockIssuer {
+    async fn start() -> (Self, OAuthProviderConfig) {
+        let issuer = MockIssuer::default();
+
+        let router = Router::new()
+            .route("/token", post(token))
+            .route("/userinfo", get(userinfo))
+            .with_state(issuer.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let config = OAuthProviderConfig {
+            display_name: "Mock".to_string(),
+            client_id: "mock-client".to_string(),
+            client_secret: "mock-secret".to_string(),
+            authorize_url: format!("{url}/authorize"),
+            token_url: format!("{url}/token"),
+            userinfo_url: format!("{url}/userinfo"),
+            scopes: vec!["openid".to_string(), "email".to_string()],
+        };
+
+        (issuer, config)
+    }
+
+    /// Grants the app an authorization code for the claims.
+    fn authorize(&self, code_challenge: &str, claims: IdentityClaims) -> String {
+        let code = format!("code-{}", claims.sub);
+        self.grants
+            .lock()
+            .unwrap()
+            .insert(code.clone(), (code_challenge.to_string(), claims));
+        code
+    }
+}
+
+async fn token(
+    State(issuer): State<MockIssuer>,
+    Form(params): Form<HashMap<String, String>>,
+) -> Result<Json<Value>, StatusCode> {
+    let (code_challenge, claims) = issuer
+        .grants
+        .lock()
+        .unwrap()
+        .remove(params.get("code").ok_or(StatusCode::BAD_REQUEST)?)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let code_verifier = params.get("code_verifier").ok_or(StatusCode::BAD_REQUEST)?;
+    if params.get("client_secret").map(String::as_str) != Some("mock-secret")
+        || Account::pkce_challenge(code_verifier) != code_challenge
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let access_token = format!("token-{}", claims.sub);
+    issuer
+        .tokens
+        .lock()
+        .unwrap()
+        .insert(access_token.clone(), claims);
+
+    Ok(Json(
+        json!({ "access_token": access_token, "token_type": "Bearer" }),
+    ))
+}
+
+async fn userinfo(
+    State(issuer): State<MockIssuer>,
+    headers: HeaderMap,
+) -> Result<Json<IdentityClaims>, StatusCode> {
+    let access_token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    issuer
+        .tokens
+        .lock()
+        .unwrap()
+        .get(access_token)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::UNAUTHORIZED)
+}
+
+fn location(response: &TestResponse) -> String {
+    response
+        .headers()
+        .get("location")
+        .expect("unable to get redirect location header from response")
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+/// Starts a login with the mock provider and returns the query string the app sent along.
+async fn start_login(request: &TestServer) -> HashMap<String, String> {
+    let response = request.get("/auth/oauth/mock").await;
+    response.assert_status_see_other();
+
+    let uri = location(&response).parse::<Uri>().unwrap();
+    let Query(params) = Query::<HashMap<String, String>>::try_from_uri(&uri).unwrap();
+    params
+}
+
+/// Logs in through the mock provider as the user with the claims.
+async fn login_as(
+    request: &TestServer,
+    issuer: &MockIssuer,
+    claims: IdentityClaims,
+) -> TestResponse {
+    let params = start_login(request).await;
+    let code = issuer.authorize(&params["code_challenge"], claims);
+
+    request
+        .get("/auth/oauth/mock/callback")
+        .add_query_params([("code", code.as_str()), ("state", params["state"].as_str())])
+        .await
+}
+
+async fn with_mock_issuer<F, Fut>(pool: DbPool, callback: F)
+where
+    F: FnOnce(TestServer, MockIssuer) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let (issuer, provider) = MockIssuer::start().await;
+
+    test_request_with_config(
+        pool,
+        |config| {
+            config.auth.oauth.insert("mock".to_string(), provider);
+        },
+        |mut request| async move {
+            request.save_cookies();
+            callback(request, issuer).await
+        },
+    )
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn oauth_login_registers_a_new_user(pool: DbPool) {
+    with_mock_issuer(pool.clone(), |request, issuer| async move {
+        let claims = IdentityClaims {
+            sub: "mock-subject-1".to_string(),
+            email: Some("hownow@browncow.com".to_string()),
+            email_verified: true,
+        };
+
+        let response = login_as(&request, &issuer, claims).await;
+
+        response.assert_status_see_other();
+        assert_eq!(location(&response), "/", "redirected to the wrong page");
+        request.get("/protected").await.assert_status_ok();
+
+        let user = User::try_get_by_email("hownow@browncow.com", &pool)
+            .await
+            .unwrap()
+            .expect("no user was registered");
+        assert_eq!(user.status, UserStatus::Confirmed);
+
+        let identity = Identity::try_get_by_subject("mock", "mock-subject-1", &pool)
+            .await
+            .unwrap()
+            .expect("no identity was stored");
+        assert_eq!(identity.user_id, user.id);
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn oauth_login_links_existing_user_by_verified_email(pool: DbPool) {
+    with_mock_issuer(pool.clone(), |request, issuer| async move {
+        let user: RegisterUser = Faker.fake();
+        let saved_user = User::create(user.clone(), &pool).await.unwrap();
+
+        let claims = IdentityClaims {
+            sub: "mock-subject-2".to_string(),
+            email: Some(user.email),
+            email_verified: true,
+        };
+
+        login_as(&request, &issuer, claims)
+            .await
+            .assert_status_see_other();
+        request.get("/protected").await.assert_status_ok();
+
+        let identity = Identity::try_get_by_subject("mock", "mock-subject-2", &pool)
+            .await
+            .unwrap()
+            .expect("no identity was stored");
+        assert_eq!(
+            identity.user_id, saved_user.id,
+            "identity was linked to another user"
+        );
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn oauth_login_takes_a_pending_user_from_whoever_registered_it(pool: DbPool) {
+    with_mock_issuer(pool.clone(), |mut request, issuer| async move {
+        // Someone registers with an email they don't own and never confirms it
+        let squatter: RegisterUser = Faker.fake();
+        let saved_user = User::create(squatter.clone(), &pool).await.unwrap();
+        assert_eq!(saved_user.status, UserStatus::Pending);
+
+        let claims = IdentityClaims {
+            sub: "mock-subject-5".to_string(),
+            email: Some(squatter.email.clone()),
+            email_verified: true,
+        };
+        login_as(&request, &issuer, claims)
+            .await
+            .assert_status_see_other();
+        request.get("/protected").await.assert_status_ok();
+
+        let user = User::try_get_by_id(&saved_user.id, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(user.status, UserStatus::Confirmed);
+
+        request.clear_cookies();
+        request
+            .post("/auth/login")
+            .form(&UserCredentials {
+                email: squatter.email,
+                password: squatter.password,
+                next: None,
+            })
+            .await;
+        request
+            .get("/protected")
+            .await
+            .assert_status(StatusCode::TEMPORARY_REDIRECT);
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn oauth_login_refuses_unverified_email(pool: DbPool) {
+    with_mock_issuer(pool.clone(), |request, issuer| async move {
+        let user: RegisterUser = Faker.fake();
+        User::create(user.clone(), &pool).await.unwrap();
+
+        // 😉Anyone can type someone else's email in at a provider
+        let claims = IdentityClaims {
+            sub: "mock-subject-3".to_string(),
+            email: Some(user.email),
+            email_verified: false,
+        };
+
+        let response = login_as(&request, &issuer, claims).await;
+
+        response.assert_status_see_other();
+        assert_eq!(
+            location(&response),
+            "/auth/login",
+            "redirected to the wrong page"
+        );
+        request
+            .get("/protected")
+            .await
+            .assert_status(StatusCode::TEMPORARY_REDIRECT);
+
+        let identity = Identity::try_get_by_subject("mock", "mock-subject-3", &pool)
+            .await
+            .unwrap();
+        assert!(
+            identity.is_none(),
+            "identity was linked on an unverified email"
+        );
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn oauth_callback_refuses_a_forged_state(pool: DbPool) {
+    with_mock_issuer(pool.clone(), |request, issuer| async move {
+        let params = start_login(&request).await;
+        let code = issuer.authorize(
+            &params["code_challenge"],
+            IdentityClaims {
+                sub: "mock-subject-4".to_string(),
+                email: Some("hownow@browncow.com".to_string()),
+                email_verified: true,
+            },
+        );
+
+        let response = request
+            .get("/auth/oauth/mock/callback")
+            .add_query_params([("code", code.as_str()), ("state", "forged")])
+            .await;
+
+        response.assert_status_see_other();
+        assert_eq!(
+            location(&response),
+            "/auth/login",
+            "redirected to the wrong page"
+        );
+        request
+            .get("/protected")
+            .await
+            .assert_status(StatusCode::TEMPORARY_REDIRECT);
+    })
+    .await
+}
@@ -0,0 +1,141 @@
+use crate::{mock_logged_in_state, test_request, test_request_with_db};
+
+use axum::http::{HeaderValue, StatusCode, header::ACCEPT};
+use serde_json::{Value, json};
+use shipwright_db::{DbPool, MIGRATOR};
+use shipwright_web::controllers::{Controller, OwnedController, Resources, resources};
+
+/// Every `$ref` in `value`.
+fn refs(value: &Value) -> Vec<String> {
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .flat_map(|(key, value)| match (key.as_str(), value) {
+                ("$ref", Value::String(reference)) => vec![reference.clone()],
+                _ => refs(value),
+            })
+            .collect(),
+        Value::Array(values) => values.iter().flat_map(refs).collect(),
+        _ => vec![],
+    }
+}
+
+/// The paths of the record controllers the router mounts.
+#[derive(Default)]
+struct ResourcePaths(Vec<&'static str>);
+
+impl Resources for ResourcePaths {
+    fn controller<C: Controller>(mut self) -> Self {
+        self.0.push(C::PATH);
+        self
+    }
+
+    fn owned_controller<C: OwnedController>(mut self) -> Self {
+        self.0.push(C::PATH);
+        self
+    }
+}
+
+#[tokio::test]
+async fn the_document_describes_the_controllers() {
+    test_request(|request| async move {
+        let response = request.get("/api/openapi.json").await;
+
+        response.assert_status_ok();
+        let document = response.json::<Value>();
+        assert_eq!(document["openapi"], "3.1.0");
+        for path in ["/todos", "/todos/batch", "/todos/{id}", "/lions/{id}", "/api/todos"] {
+            assert!(document["paths"][path].is_object(), "{path} is missing");
+        }
+        assert_eq!(
+            document["paths"]["/todos"]["post"]["requestBody"]["content"]["application/json"]
+                ["schema"],
+            json!({ "$ref": "#/components/schemas/TodoChangeset" })
+        );
+        assert_eq!(
+            document["paths"]["/todos/{id}"]["get"]["responses"]["200"]["content"]
+                ["application/json"]["schema"],
+            json!({ "$ref": "#/components/schemas/Todo" })
+        );
+
+        // Validator constraints end up in the schemas
+        let schemas = &document["components"]["schemas"];
+        assert_eq!(
+            schemas["TodoChangeset"]["properties"]["description"]["minLength"],
+            1
+        );
+        assert_eq!(schemas["TodoChangeset"]["required"], json!(["description"]));
+
+        for reference in refs(&document) {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            assert!(schemas[name].is_object(), "{reference} does not resolve");
+        }
+    })
+    .await;
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn documented_paths_are_routed(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_state(&request, &pool).await;
+        let document = request.get("/api/openapi.json").await.json::<Value>();
+
+        for (path, item) in document["paths"].as_object().unwrap() {
+            if path.contains('{') || item.get("get").is_none() {
+                continue;
+            }
+            let response = request
+                .get(path)
+                .add_header(ACCEPT, HeaderValue::from_static("application/json"))
+                .await;
+            assert_ne!(response.status_code(), StatusCode::NOT_FOUND, "{path}");
+        }
+    })
+    .await;
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn routed_resources_are_documented(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        mock_logged_in_state(&request, &pool).await;
+        let document = request.get("/api/openapi.json").await.json::<Value>();
+        let ResourcePaths(paths) = resources(ResourcePaths::default());
+
+        assert!(!paths.is_empty());
+        for path in paths {
+            let response = request
+                .get(path)
+                .add_header(ACCEPT, HeaderValue::from_static("application/json"))
+                .await;
+            assert_ne!(response.status_code(), StatusCode::NOT_FOUND, "{path}");
+            for documented in [
+                path.to_string(),
+                format!("{path}/batch"),
+                format!("{path}/{{id}}"),
+            ] {
+                assert!(
+                    document["paths"][&documented].is_object(),
+                    "{documented} is missing"
+                );
+            }
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn the_docs_page_works_offline() {
+    test_request(|request| async move {
+        let response = request.get("/api/docs").await;
+
+        response.assert_status_ok();
+        response.assert_text_contains(r#"<section id="todos_create">"#);
+        response.assert_text_contains(r##"<a href="#schema-TodoChangeset">TodoChangeset</a>"##);
+        response.assert_text_contains("minLength: 1");
+        // Nothing is loaded from elsewhere
+        assert!(!response.text().contains("https://cdn"));
+    })
+    .await;
+}
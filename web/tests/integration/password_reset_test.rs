@@ -0,0 +1,144 @@
+use super::test_request_with_db;
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use fake::{Fake as _, Faker};
+use shipwright_db::{
+    DbPool, MIGRATOR,
+    entities::{
+        password_reset_token::{ForgotPassword, PasswordResetToken, ResetPassword},
+        user::{RegisterUser, User, UserCredentials, UserStatus},
+    },
+    single_use_token,
+};
+
+async fn login(request: &TestServer, email: &str, password: &str) {
+    request
+        .post("/auth/login")
+        .form(&UserCredentials {
+            email: email.to_string(),
+            password: password.to_string(),
+            next: None,
+        })
+        .await;
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn forgot_password_issues_a_reset_token(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let user: RegisterUser = Faker.fake();
+        let user = User::create(user, &pool).await.unwrap();
+
+        let response = request
+            .post("/auth/password/forgot")
+            .form(&ForgotPassword {
+                email: user.email.clone(),
+            })
+            .await;
+
+        response.assert_status_see_other();
+
+        let tokens = sqlx::query_scalar!(
+            "SELECT token_hash FROM password_reset_tokens WHERE user_id = $1",
+            user.id
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(tokens.len(), 1, "expected exactly one reset token");
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn reset_password_changes_password_and_ends_sessions(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+
+        let register: RegisterUser = Faker.fake();
+        let user = User::create(register.clone(), &pool).await.unwrap();
+        User::update_status(user.id, UserStatus::Confirmed, &pool)
+            .await
+            .unwrap();
+        login(&request, &register.email, &register.password).await;
+        request.get("/protected").await.assert_status_ok();
+
+        let reset_token = PasswordResetToken::create(user.id, &pool).await.unwrap();
+        let form = ResetPassword {
+            reset_token: reset_token.clone(),
+            password: "n3wPa$$word".into(),
+            confirm_password: "n3wPa$$word".into(),
+        };
+
+        // Only the hash is stored, a copy of the database doesn't give away the link
+        let stored = sqlx::query_scalar!(
+            "SELECT token_hash FROM password_reset_tokens WHERE user_id = $1",
+            user.id
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_ne!(stored, reset_token);
+        assert_eq!(stored, single_use_token::hash(&reset_token));
+
+        let response = request.post("/auth/password/reset").form(&form).await;
+
+        response.assert_status_see_other();
+
+        // 😉 The old session was tied to the old password hash
+        request
+            .get("/protected")
+            .await
+            .assert_status(StatusCode::TEMPORARY_REDIRECT);
+
+        login(&request, &register.email, &form.password).await;
+        request.get("/protected").await.assert_status_ok();
+
+        // The link can't be used twice
+        let response = request.post("/auth/password/reset").form(&form).await;
+
+        let location = response
+            .headers()
+            .get("location")
+            .expect("unable to get redirect location header from response")
+            .to_str()
+            .unwrap();
+
+        assert_eq!(location, "/auth/password/forgot", "reset token was reused");
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn reset_password_rejects_expired_tokens(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let register: RegisterUser = Faker.fake();
+        let user = User::create(register.clone(), &pool).await.unwrap();
+        let reset_token = PasswordResetToken::create(user.id, &pool).await.unwrap();
+
+        sqlx::query!(
+            "UPDATE password_reset_tokens SET expires_at = CURRENT_TIMESTAMP WHERE user_id = $1",
+            user.id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        request
+            .post("/auth/password/reset")
+            .form(&ResetPassword {
+                reset_token,
+                password: "n3wPa$$word".into(),
+                confirm_password: "n3wPa$$word".into(),
+            })
+            .await
+            .assert_status_see_other();
+
+        let unchanged = User::try_get_by_id(&user.id, &pool).await.unwrap().unwrap();
+        assert_eq!(
+            unchanged.password_hash, user.password_hash,
+            "password was changed with an expired token"
+        );
+    })
+    .await
+}
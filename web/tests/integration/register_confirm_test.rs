@@ -0,0 +1,145 @@
+use super::test_request_with_db;
+use fake::{Fake as _, Faker};
+use shipwright_db::{
+    DbPool, MIGRATOR,
+    entities::{
+        register_token::{RegisterToken, RegisterTokenValidate, ResendRegisterToken},
+        user::{RegisterUser, User, UserStatus},
+    },
+};
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn confirm_consumes_the_register_token(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let user: RegisterUser = Faker.fake();
+        let user = User::create(user, &pool).await.unwrap();
+        let register_token = RegisterToken::create(user.id, &pool).await.unwrap();
+        let form = RegisterTokenValidate {
+            register_token: register_token.register_token,
+        };
+
+        let response = request.post("/auth/register/confirm").form(&form).await;
+
+        response.assert_status_see_other();
+
+        let user = User::try_get_by_id(&user.id, &pool).await.unwrap().unwrap();
+        assert!(
+            matches!(user.status, UserStatus::Confirmed),
+            "user was not confirmed"
+        );
+
+        // 😉 The same code can't be used twice
+        let response = request.post("/auth/register/confirm").form(&form).await;
+
+        response.assert_status_unauthorized();
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn confirm_rejects_expired_register_tokens(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let user: RegisterUser = Faker.fake();
+        let user = User::create(user, &pool).await.unwrap();
+        let register_token = RegisterToken::create(user.id, &pool).await.unwrap();
+
+        sqlx::query!(
+            "UPDATE registration_tokens SET expires_at = CURRENT_TIMESTAMP WHERE user_id = $1",
+            user.id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let response = request
+            .post("/auth/register/confirm")
+            .form(&RegisterTokenValidate {
+                register_token: register_token.register_token,
+            })
+            .await;
+
+        response.assert_status_unauthorized();
+
+        let user = User::try_get_by_id(&user.id, &pool).await.unwrap().unwrap();
+        assert!(
+            matches!(user.status, UserStatus::Pending),
+            "user was confirmed with an expired token"
+        );
+
+        let purged = RegisterToken::delete_expired(&pool).await.unwrap();
+        assert_eq!(purged, 1, "expired token was not purged");
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn resend_replaces_the_register_token(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let user: RegisterUser = Faker.fake();
+        let user = User::create(user, &pool).await.unwrap();
+        let old_token = RegisterToken::create(user.id, &pool).await.unwrap();
+
+        let response = request
+            .post("/auth/register/confirm/resend")
+            .form(&ResendRegisterToken {
+                email: user.email.clone(),
+            })
+            .await;
+
+        response.assert_status_see_other();
+
+        let tokens = sqlx::query_scalar!(
+            "SELECT register_token FROM registration_tokens WHERE user_id = $1",
+            user.id
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(tokens.len(), 1, "expected exactly one register token");
+        assert_ne!(
+            tokens[0], old_token.register_token,
+            "the old register token was not replaced"
+        );
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn resend_does_not_reveal_unknown_emails(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let response = request
+            .post("/auth/register/confirm/resend")
+            .form(&ResendRegisterToken {
+                email: "thisisnotauser@fake.com".into(),
+            })
+            .await;
+
+        response.assert_status_see_other();
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn confirm_redirects_to_next(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let user: RegisterUser = Faker.fake();
+        let user = User::create(user, &pool).await.unwrap();
+
+        for (next, location) in [("/todos", "/todos"), ("https://evil.example", "/")] {
+            let register_token = RegisterToken::create(user.id, &pool).await.unwrap();
+
+            let response = request
+                .post("/auth/register/confirm")
+                .add_query_param("next", next)
+                .form(&RegisterTokenValidate {
+                    register_token: register_token.register_token,
+                })
+                .await;
+
+            response.assert_status_see_other();
+            response.assert_header("location", location);
+        }
+    })
+    .await
+}
@@ -0,0 +1,194 @@
+use super::test_request_with_db;
+use axum::http::{HeaderValue, StatusCode, header::USER_AGENT};
+use axum_test::TestServer;
+use fake::{Fake as _, Faker};
+use shipwright_context::Account;
+use shipwright_db::{
+    DbPool, MIGRATOR,
+    entities::{
+        session::Session,
+        user::{RegisterUser, User, UserCredentials, UserStatus},
+    },
+};
+use tower_sessions::cookie::Cookie;
+
+async fn confirmed_user(pool: &DbPool) -> (User, UserCredentials) {
+    let user: RegisterUser = Faker.fake();
+
+    let saved_user = User::create(user.clone(), pool).await.unwrap();
+    let saved_user = User::update_status(saved_user.id, UserStatus::Confirmed, pool)
+        .await
+        .unwrap();
+
+    let credentials = UserCredentials {
+        email: user.email,
+        password: user.password,
+        next: None,
+    };
+
+    (saved_user, credentials)
+}
+
+/// Logs in from another browser, the session cookie is returned rather than saved.
+async fn login_elsewhere(request: &TestServer, credentials: &UserCredentials) -> Cookie<'static> {
+    request
+        .post("/auth/login")
+        .clear_cookies()
+        .do_not_save_cookies()
+        .form(credentials)
+        .await
+        .cookie("id")
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn sessions_are_recorded_at_login(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let (user, credentials) = confirmed_user(&pool).await;
+
+        request
+            .post("/auth/login")
+            .add_header(
+                USER_AGENT,
+                HeaderValue::from_static("Mozilla/5.0 (X11; Linux x86_64)"),
+            )
+            .form(&credentials)
+            .await
+            .assert_status_see_other();
+
+        let sessions = Session::load_active_for_user(user.id, &pool).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(
+            sessions[0].user_agent.as_deref(),
+            Some("Mozilla/5.0 (X11; Linux x86_64)")
+        );
+        assert!(sessions[0].last_seen_at.is_some());
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn sessions_page_lists_the_sessions_of_the_user(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (_, credentials) = confirmed_user(&pool).await;
+
+        login_elsewhere(&request, &credentials).await;
+        request.post("/auth/login").form(&credentials).await;
+
+        let response = request.get("/account/sessions").await;
+        response.assert_status_ok();
+
+        let response = request.get("/account/sessions").await;
+        response.assert_text_contains("This session");
+        assert_eq!(
+            response.text().matches("Sign out</button>").count(),
+            1,
+            "only the other session can be signed out from the page"
+        );
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn sign_out_everywhere_else_keeps_the_current_session(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (user, credentials) = confirmed_user(&pool).await;
+
+        let elsewhere = login_elsewhere(&request, &credentials).await;
+        request.post("/auth/login").form(&credentials).await;
+        request.get("/todos").await.assert_status_ok();
+
+        let response = request.post("/account/sessions/revoke-others").await;
+        response.assert_status_see_other();
+
+        request
+            .get("/todos")
+            .clear_cookies()
+            .add_cookie(elsewhere)
+            .do_not_save_cookies()
+            .await
+            .assert_status(StatusCode::TEMPORARY_REDIRECT);
+        request.get("/todos").await.assert_status_ok();
+
+        let sessions = Session::load_active_for_user(user.id, &pool).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn a_single_session_can_be_signed_out(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (user, credentials) = confirmed_user(&pool).await;
+
+        let elsewhere = login_elsewhere(&request, &credentials).await;
+        request.post("/auth/login").form(&credentials).await;
+        request.get("/todos").await.assert_status_ok();
+
+        // The cookie is signed, the session id is the part after the '='
+        let elsewhere_id = elsewhere.value().split('=').nth(1).unwrap().to_string();
+        let current_id = Session::load_active_for_user(user.id, &pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|session| session.id)
+            .find(|id| *id != elsewhere_id)
+            .unwrap();
+
+        // The current session is ended by logging out
+        request
+            .post(&format!(
+                "/account/sessions/{}/revoke",
+                Account::session_handle(&current_id)
+            ))
+            .await
+            .assert_status_not_found();
+
+        let response = request
+            .post(&format!(
+                "/account/sessions/{}/revoke",
+                Account::session_handle(&elsewhere_id)
+            ))
+            .await;
+        response.assert_status_see_other();
+
+        request
+            .get("/todos")
+            .clear_cookies()
+            .add_cookie(elsewhere)
+            .do_not_save_cookies()
+            .await
+            .assert_status(StatusCode::TEMPORARY_REDIRECT);
+        request.get("/todos").await.assert_status_ok();
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn sessions_of_other_users_cannot_be_signed_out(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (other, other_credentials) = confirmed_user(&pool).await;
+        let (_, credentials) = confirmed_user(&pool).await;
+
+        let elsewhere = login_elsewhere(&request, &other_credentials).await;
+        let elsewhere_id = elsewhere.value().split('=').nth(1).unwrap().to_string();
+        request.post("/auth/login").form(&credentials).await;
+
+        request
+            .post(&format!(
+                "/account/sessions/{}/revoke",
+                Account::session_handle(&elsewhere_id)
+            ))
+            .await
+            .assert_status_not_found();
+
+        let sessions = Session::load_active_for_user(other.id, &pool)
+            .await
+            .unwrap();
+        assert_eq!(sessions.len(), 1);
+    })
+    .await
+}
@@ -0,0 +1,288 @@
+use super::{mock_logged_in_state, test_request_with_db};
+use axum::http::StatusCode;
+use fake::{Fake as _, Faker};
+use shipwright_db::{
+    DbPool, Entity as _, MIGRATOR,
+    entities::{
+        team::{CreateTeam, Team, TeamRole},
+        team_invitation::{AcceptInvitation, InviteMember, TeamInvitation},
+        user::{RegisterUser, User, UserStatus},
+        user_data::UserData,
+    },
+    single_use_token,
+};
+
+async fn confirmed_user(pool: &DbPool) -> User {
+    let user: RegisterUser = Faker.fake();
+
+    let saved_user = User::create(user, pool).await.unwrap();
+    User::update_status(saved_user.id, UserStatus::Confirmed, pool)
+        .await
+        .unwrap()
+}
+
+/// A team owned by `owner` with an invitation sent to `email`, and the token of its link.
+async fn team_with_invitation(
+    owner: &User,
+    email: &str,
+    pool: &DbPool,
+) -> (Team, TeamInvitation, String) {
+    let team = Team::create(
+        CreateTeam {
+            name: "Rustaceans".to_string(),
+        },
+        pool,
+    )
+    .await
+    .unwrap();
+    Team::add_member(team.id, owner.id, TeamRole::Owner, pool)
+        .await
+        .unwrap();
+    let invite = InviteMember {
+        email: email.to_string(),
+    };
+    let (invitation, invite_token) = TeamInvitation::create(team.id, &invite, owner.id, pool)
+        .await
+        .unwrap();
+
+    (team, invitation, invite_token)
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn creating_a_team_makes_the_user_its_owner(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let user = mock_logged_in_state(&request, &pool).await;
+
+        let response = request
+            .post("/teams")
+            .form(&CreateTeam {
+                name: "Rustaceans".to_string(),
+            })
+            .await;
+
+        response.assert_status_see_other();
+        let teams = Team::load_memberships(user.id, &pool).await.unwrap();
+        assert_eq!(teams.len(), 1);
+        assert!(teams[0].is_owner());
+        let page = request.get(&format!("/teams/{}", teams[0].team_id)).await;
+        page.assert_status_ok();
+        page.assert_text_contains("Rustaceans");
+        page.assert_text_contains("Invite a member");
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn teams_of_others_are_not_found(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let owner = confirmed_user(&pool).await;
+        let (team, invitation, invite_token) =
+            team_with_invitation(&owner, "someone@example.com", &pool).await;
+        mock_logged_in_state(&request, &pool).await;
+
+        request
+            .get(&format!("/teams/{}", team.id))
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+        request
+            .post(&format!(
+                "/teams/{}/invitations/{}/revoke",
+                team.id, invitation.id
+            ))
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+        assert!(
+            TeamInvitation::try_get_valid(&invite_token, &pool)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn owners_invite_by_email_and_only_the_latest_link_works(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let owner = mock_logged_in_state(&request, &pool).await;
+        let (team, first, _) = team_with_invitation(&owner, "new@example.com", &pool).await;
+
+        let response = request
+            .post(&format!("/teams/{}/invitations", team.id))
+            .form(&InviteMember {
+                email: "new@example.com".to_string(),
+            })
+            .await;
+
+        response.assert_header("location", format!("/teams/{}", team.id));
+        let pending = TeamInvitation::load_pending_for_team(team.id, &pool)
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].email, "new@example.com");
+        assert_ne!(pending[0].token_hash, first.token_hash);
+        request
+            .get(&format!("/teams/{}", team.id))
+            .await
+            .assert_text_contains("new@example.com");
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn the_invitee_joins_the_team_once(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let owner = confirmed_user(&pool).await;
+        let invitee = mock_logged_in_state(&request, &pool).await;
+        let (team, invitation, invite_token) =
+            team_with_invitation(&owner, &invitee.email, &pool).await;
+        // Only the hash is stored, a copy of the database doesn't give away the link
+        assert_ne!(invitation.token_hash, invite_token);
+        assert_eq!(invitation.token_hash, single_use_token::hash(&invite_token));
+        let accept = AcceptInvitation {
+            invite_token: invite_token.clone(),
+        };
+
+        request
+            .get(&format!("/teams/invitations/accept?token={}", invite_token))
+            .await
+            .assert_text_contains("Accept invitation");
+        let response = request
+            .post("/teams/invitations/accept")
+            .form(&accept)
+            .await;
+
+        response.assert_header("location", format!("/teams/{}", team.id));
+        let membership = Team::try_get_membership(team.id, invitee.id, &pool)
+            .await
+            .unwrap()
+            .expect("the invitee should have joined the team");
+        assert!(!membership.is_owner());
+        // The link is used up
+        let response = request
+            .post("/teams/invitations/accept")
+            .form(&accept)
+            .await;
+        response.assert_header("location", "/teams");
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn invitations_sent_to_another_email_are_refused(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let owner = confirmed_user(&pool).await;
+        let user = mock_logged_in_state(&request, &pool).await;
+        let (team, _, invite_token) =
+            team_with_invitation(&owner, "someone@example.com", &pool).await;
+
+        request
+            .post("/teams/invitations/accept")
+            .form(&AcceptInvitation {
+                invite_token: invite_token.clone(),
+            })
+            .await
+            .assert_status_see_other();
+
+        assert!(
+            Team::try_get_membership(team.id, user.id, &pool)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        // The invitation is kept for the right account
+        assert!(
+            TeamInvitation::try_get_valid(&invite_token, &pool)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn invitees_without_a_session_return_to_the_invitation_after_registering(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |request| async move {
+        let owner = confirmed_user(&pool).await;
+        let (_, _, invite_token) = team_with_invitation(&owner, "new@example.com", &pool).await;
+        let next = format!("%2Fteams%2Finvitations%2Faccept%3Ftoken%3D{}", invite_token);
+
+        let response = request
+            .post("/teams/invitations/accept")
+            .form(&AcceptInvitation {
+                invite_token: invite_token.clone(),
+            })
+            .await;
+        response.assert_header("location", format!("/auth/login?next={next}"));
+
+        let page = request
+            .get(&format!("/teams/invitations/accept?token={}", invite_token))
+            .await;
+        page.assert_text_contains(r#"href="/auth/register?next="#);
+
+        let response = request
+            .post(&format!("/auth/register?next={next}"))
+            .form(&RegisterUser {
+                email: "new@example.com".to_string(),
+                password: "password123".to_string(),
+                confirm_password: "password123".to_string(),
+            })
+            .await;
+        response.assert_header("location", format!("/auth/register/confirm?next={next}"));
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn revoked_invitations_stop_working(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let owner = mock_logged_in_state(&request, &pool).await;
+        let (team, invitation, invite_token) =
+            team_with_invitation(&owner, "new@example.com", &pool).await;
+
+        let response = request
+            .post(&format!(
+                "/teams/{}/invitations/{}/revoke",
+                team.id, invitation.id
+            ))
+            .await;
+
+        response.assert_header("location", format!("/teams/{}", team.id));
+        assert!(
+            TeamInvitation::try_get_valid(&invite_token, &pool)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn teams_are_deleted_with_their_last_member(pool: DbPool) {
+    let owner = confirmed_user(&pool).await;
+    let member = confirmed_user(&pool).await;
+    let (team, _, _) = team_with_invitation(&owner, "new@example.com", &pool).await;
+    Team::add_member(team.id, member.id, TeamRole::Member, &pool)
+        .await
+        .unwrap();
+
+    UserData::delete(owner.id, &pool).await.unwrap();
+    assert!(Team::load(team.id, &pool).await.is_ok());
+    assert!(
+        TeamInvitation::load_pending_for_team(team.id, &pool)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    UserData::delete(member.id, &pool).await.unwrap();
+    assert!(Team::load(team.id, &pool).await.is_err());
+}
@@ -0,0 +1,242 @@
+use super::{mock_logged_in_state, test_request_with_db};
+use axum::http::StatusCode;
+use fake::{Fake as _, Faker};
+use shipwright_context::Account;
+use shipwright_db::{
+    DbPool, MIGRATOR,
+    entities::{
+        two_factor::{RecoveryCode, TotpCode, TotpCredential},
+        user::{RegisterUser, User, UserCredentials, UserStatus},
+    },
+};
+use time::OffsetDateTime;
+
+/// Creates a confirmed user with two-factor authentication turned on.
+async fn mock_two_factor_user(pool: &DbPool) -> (RegisterUser, TotpCredential) {
+    let user: RegisterUser = Faker.fake();
+
+    let saved_user = User::create(user.clone(), pool).await.unwrap();
+    User::update_status(saved_user.id, UserStatus::Confirmed, pool)
+        .await
+        .unwrap();
+
+    TotpCredential::try_start_enrolment(saved_user.id, &Account::generate_totp_secret(), pool)
+        .await
+        .unwrap()
+        .expect("enrolment was not started");
+    let credential = TotpCredential::enable(saved_user.id, pool).await.unwrap();
+
+    (user, credential)
+}
+
+fn current_code(credential: &TotpCredential) -> String {
+    Account::totp(&credential.secret, "test", "test@example.com")
+        .unwrap()
+        .generate(OffsetDateTime::now_utc().unix_timestamp() as u64)
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn enrolment_enables_two_factor_and_shows_recovery_codes(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let user = mock_logged_in_state(&request, &pool).await;
+
+        request.get("/auth/two-factor").await.assert_status_ok();
+
+        let credential = TotpCredential::try_get_by_user_id(user.id, &pool)
+            .await
+            .unwrap()
+            .expect("no enrolment found in the database");
+        assert!(
+            !credential.enabled,
+            "2fa was enabled before a code was entered"
+        );
+
+        let response = request
+            .post("/auth/two-factor/enable")
+            .form(&TotpCode {
+                code: current_code(&credential),
+            })
+            .await;
+
+        response.assert_status_ok();
+
+        let credential = TotpCredential::try_get_by_user_id(user.id, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(credential.enabled, "2fa was not enabled");
+
+        assert_eq!(
+            response.text().matches("<li><code>").count(),
+            Account::RECOVERY_CODES,
+            "recovery codes were not shown"
+        );
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn login_with_two_factor_requires_a_code(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (user, credential) = mock_two_factor_user(&pool).await;
+
+        let response = request
+            .post("/auth/login")
+            .form(&UserCredentials {
+                email: user.email,
+                password: user.password,
+                next: None,
+            })
+            .await;
+
+        response.assert_status_see_other();
+        assert_eq!(
+            response.headers().get("location").unwrap(),
+            "/auth/login/two-factor",
+            "redirected to the wrong page"
+        );
+
+        // 😉Half way through logging in is still logged out
+        request
+            .get("/protected")
+            .await
+            .assert_status(StatusCode::TEMPORARY_REDIRECT);
+
+        request
+            .post("/auth/login/two-factor")
+            .form(&TotpCode {
+                code: current_code(&credential),
+            })
+            .await
+            .assert_status_see_other();
+
+        request.get("/protected").await.assert_status_ok();
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn login_with_wrong_code_redirects_back(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (user, _) = mock_two_factor_user(&pool).await;
+
+        request
+            .post("/auth/login")
+            .form(&UserCredentials {
+                email: user.email,
+                password: user.password,
+                next: None,
+            })
+            .await;
+
+        let response = request
+            .post("/auth/login/two-factor")
+            .form(&TotpCode {
+                code: "000000".into(),
+            })
+            .await;
+
+        response.assert_status_see_other();
+        assert_eq!(
+            response.headers().get("location").unwrap(),
+            "/auth/login/two-factor",
+            "redirected to the wrong page"
+        );
+
+        request
+            .get("/protected")
+            .await
+            .assert_status(StatusCode::TEMPORARY_REDIRECT);
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn login_with_recovery_code_consumes_it(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (user, credential) = mock_two_factor_user(&pool).await;
+
+        let recovery_code = Account::generate_recovery_codes().remove(0);
+        RecoveryCode::create(
+            credential.user_id,
+            &Account::hash_recovery_code(&recovery_code),
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        request
+            .post("/auth/login")
+            .form(&UserCredentials {
+                email: user.email,
+                password: user.password,
+                next: None,
+            })
+            .await;
+
+        request
+            .post("/auth/login/two-factor")
+            .form(&TotpCode {
+                code: recovery_code.clone(),
+            })
+            .await
+            .assert_status_see_other();
+
+        request.get("/protected").await.assert_status_ok();
+
+        let unused = RecoveryCode::try_consume(
+            credential.user_id,
+            &Account::hash_recovery_code(&recovery_code),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert!(!unused, "the recovery code can be used again");
+    })
+    .await
+}
+
+#[sqlx::test(migrator = "MIGRATOR")]
+async fn a_code_can_only_be_used_once(pool: DbPool) {
+    test_request_with_db::<_, _>(pool.clone(), |mut request| async move {
+        request.save_cookies();
+        let (user, credential) = mock_two_factor_user(&pool).await;
+        let code = current_code(&credential);
+        let credentials = UserCredentials {
+            email: user.email,
+            password: user.password,
+            next: None,
+        };
+
+        request.post("/auth/login").form(&credentials).await;
+        request
+            .post("/auth/login/two-factor")
+            .form(&TotpCode { code: code.clone() })
+            .await
+            .assert_status_see_other();
+        request.get("/protected").await.assert_status_ok();
+
+        // 😉 Someone who saw the code tries it from another browser
+        request.clear_cookies();
+        request.post("/auth/login").form(&credentials).await;
+        let response = request
+            .post("/auth/login/two-factor")
+            .form(&TotpCode { code })
+            .await;
+
+        assert_eq!(
+            response.headers().get("location").unwrap(),
+            "/auth/login/two-factor",
+            "the code was accepted twice"
+        );
+        request
+            .get("/protected")
+            .await
+            .assert_status(StatusCode::TEMPORARY_REDIRECT);
+    })
+    .await
+}
@@ -0,0 +1,34 @@
+use apalis::prelude::Data;
+use chrono::{DateTime, Utc};
+use shipwright_db::{
+    DbPool,
+    entities::{account_deletion::AccountDeletion, user_data::UserData},
+};
+use time::OffsetDateTime;
+
+/// Runs every hour to delete the accounts whose grace period is over.
+pub const SCHEDULE: &str = "0 0 * * * *";
+
+/// The job carries no data, the cron stream only tells us when to run.
+#[derive(Debug, Clone)]
+pub struct DeleteAccounts;
+
+impl From<DateTime<Utc>> for DeleteAccounts {
+    fn from(_tick: DateTime<Utc>) -> Self {
+        Self
+    }
+}
+
+pub async fn job(_tick: DeleteAccounts, db_pool: Data<DbPool>) -> Result<(), shipwright_db::Error> {
+    let due = AccountDeletion::load_due(OffsetDateTime::now_utc(), &*db_pool).await?;
+
+    // An account that can't be deleted doesn't hold up the others, it is retried next time
+    let mut result = Ok(());
+    for user_id in due {
+        if let Err(e) = UserData::delete(user_id, &db_pool).await {
+            result = Err(e);
+        }
+    }
+
+    result
+}
@@ -1,2 +1,4 @@
+pub mod delete_accounts;
+pub mod purge_register_tokens;
 pub mod send_email;
 // pub mod sync_api_data;
@@ -0,0 +1,25 @@
+use apalis::prelude::Data;
+use chrono::{DateTime, Utc};
+use shipwright_db::{DbPool, entities::register_token::RegisterToken};
+
+/// Runs every hour to delete registration tokens that can no longer be used.
+pub const SCHEDULE: &str = "0 0 * * * *";
+
+/// The job carries no data, the cron stream only tells us when to run.
+#[derive(Debug, Clone)]
+pub struct PurgeRegisterTokens;
+
+impl From<DateTime<Utc>> for PurgeRegisterTokens {
+    fn from(_tick: DateTime<Utc>) -> Self {
+        Self
+    }
+}
+
+pub async fn job(
+    _tick: PurgeRegisterTokens,
+    db_pool: Data<DbPool>,
+) -> Result<(), shipwright_db::Error> {
+    RegisterToken::delete_expired(&*db_pool).await?;
+
+    Ok(())
+}
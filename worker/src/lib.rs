@@ -1,6 +1,9 @@
+use std::str::FromStr as _;
+
 use apalis::prelude::*;
+use apalis_cron::{CronStream, Schedule};
 use shipwright_config::Config;
-use shipwright_db::{Database, connect_pool, create_database_if_not_exists};
+use shipwright_db::{Database, DbPool, connect_jobs_pool, create_database_if_not_exists};
 use shipwright_mailer::{EmailClient, EmailPayload};
 use tokio::task::JoinHandle;
 
@@ -15,10 +18,14 @@ pub struct WorkerInitializer {
 }
 
 impl WorkerInitializer {
-    pub async fn init(config: &Config, email_client: EmailClient) -> Result<Self, Error> {
+    pub async fn init(
+        config: &Config,
+        email_client: EmailClient,
+        db_pool: DbPool,
+    ) -> Result<Self, Error> {
         create_database_if_not_exists(Database::Jobs, config).await?;
 
-        let pool = connect_pool(Database::Jobs, config).await?;
+        let pool = connect_jobs_pool(config).await?;
 
         WorkerStorage::setup(&pool)
             .await
@@ -26,6 +33,11 @@ impl WorkerInitializer {
 
         let email_storage: WorkerStorage<EmailPayload> = WorkerStorage::new(pool.clone());
 
+        let purge_register_tokens = Schedule::from_str(jobs::purge_register_tokens::SCHEDULE)
+            .expect("invalid schedule for purging register tokens");
+        let delete_accounts = Schedule::from_str(jobs::delete_accounts::SCHEDULE)
+            .expect("invalid schedule for deleting accounts");
+
         let email_storage_cloned = email_storage.clone();
         let monitor_task = tokio::task::spawn(async move {
             Monitor::new()
@@ -37,6 +49,20 @@ impl WorkerInitializer {
                         .backend(email_storage_cloned)
                         .build_fn(jobs::send_email::job)
                 })
+                .register({
+                    WorkerBuilder::new("purge-register-tokens-worker")
+                        .data(db_pool.clone())
+                        .enable_tracing()
+                        .backend(CronStream::new(purge_register_tokens))
+                        .build_fn(jobs::purge_register_tokens::job)
+                })
+                .register({
+                    WorkerBuilder::new("delete-accounts-worker")
+                        .data(db_pool)
+                        .enable_tracing()
+                        .backend(CronStream::new(delete_accounts))
+                        .build_fn(jobs::delete_accounts::job)
+                })
                 .run()
                 .await
                 .unwrap();